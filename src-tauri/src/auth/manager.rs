@@ -98,6 +98,16 @@ impl TokenManager {
         statuses
     }
     
+    /// 获取所有系统的URL匹配模式（系统ID, 模式）
+    pub fn get_url_patterns(&self) -> Vec<(String, String)> {
+        let mut patterns: Vec<(String, String)> = self.systems
+            .iter()
+            .map(|(system_id, system)| (system_id.clone(), system.url_pattern().to_string()))
+            .collect();
+        patterns.sort();
+        patterns
+    }
+    
     /// 获取特定系统的token
     pub fn get_system_token(&self, system_id: &str) -> Option<String> {
         debug!("🔎 查找系统 [{}] 的token", system_id);
@@ -228,6 +238,9 @@ pub fn init_token_manager() -> Result<()> {
         .set(manager)
         .map_err(|_| anyhow!("Token管理器已经初始化过了"))?;
     
+    // 系统列表变化后，抓包过滤器需要按新的URL模式重新生成
+    crate::packet_capture::filter::notify_filter_changed();
+    
     info!("✅ Token管理器初始化完成");
     Ok(())
 }
@@ -239,16 +252,14 @@ pub fn get_token_manager() -> Option<Arc<Mutex<TokenManager>>> {
 
 /// 处理传入的HTTP请求
 pub fn process_incoming_request(request: &HttpRequest) -> Result<()> {
-    info!("🎮 manager开始处理HTTP请求: {} {}", request.method, request.path);
+    debug!("🎮 manager开始处理HTTP请求: {} {}", request.method, request.path);
     
     if let Some(manager) = get_token_manager() {
-        info!("✅ Token管理器已获取，准备加锁处理...");
         let mut mgr = manager.lock().unwrap();
-        info!("🔒 Token管理器加锁成功，开始调用process_request...");
         let result = mgr.process_request(request);
         
         match &result {
-            Ok(_) => info!("✅ manager处理HTTP请求完成"),
+            Ok(_) => debug!("✅ manager处理HTTP请求完成"),
             Err(e) => error!("❌ manager处理HTTP请求失败: {}", e),
         }
         
@@ -270,6 +281,17 @@ pub fn get_all_token_status() -> Vec<TokenStatus> {
     }
 }
 
+/// 获取所有系统的URL匹配模式
+pub fn get_system_url_patterns() -> Vec<(String, String)> {
+    if let Some(manager) = get_token_manager() {
+        let mgr = manager.lock().unwrap();
+        mgr.get_url_patterns()
+    } else {
+        warn!("⚠️ Token管理器未初始化，没有可用的URL模式");
+        Vec::new()
+    }
+}
+
 /// 获取特定系统的token
 pub fn get_system_token(system_id: &str) -> Option<String> {
     if let Some(manager) = get_token_manager() {
@@ -39,15 +39,14 @@ pub fn init_auth_system() -> Result<()> {
 
 // 处理来自抓包模块的HTTP请求
 pub fn process_http_request(request: &HttpRequest) -> Result<()> {
-    info!("🎯 auth模块收到HTTP请求: {} {} (来源: {}:{})", 
+    debug!("🎯 auth模块收到HTTP请求: {} {} (来源: {}:{})", 
            request.method, request.path, request.src_ip, request.src_port);
     
-    info!("🔍 开始调用manager::process_incoming_request...");
     let result = manager::process_incoming_request(request);
     
     match &result {
         Ok(_) => {
-            info!("✅ auth模块处理HTTP请求成功");
+            debug!("✅ auth模块处理HTTP请求成功");
         }
         Err(e) => {
             error!("❌ auth模块处理HTTP请求失败: {}", e);
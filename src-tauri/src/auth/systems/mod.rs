@@ -7,7 +7,7 @@ pub mod registry;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 use std::time::{SystemTime, UNIX_EPOCH};
-use crate::packet_capture::HttpRequest;
+use crate::packet_capture::{tls, HttpRequest};
 use crate::auth::events;
 use log::{info, warn, debug};
 use regex::Regex;
@@ -20,6 +20,9 @@ pub trait SystemAuth {
     /// 获取系统名称
     fn system_name(&self) -> &str;
     
+    /// 获取URL匹配正则模式
+    fn url_pattern(&self) -> &str;
+    
     /// 处理HTTP请求，尝试提取token（核心方法）
     fn process_http_request(&mut self, request: &HttpRequest) -> Result<()>;
     
@@ -69,6 +72,8 @@ pub struct SystemConfig {
     pub url_pattern: String,
     /// Token所在的header名称
     pub header_name: String,
+    /// Token所在的Cookie名称，设置后从Cookie中提取token而不是header
+    pub cookie_name: Option<String>,
     /// Token提取正则模式
     pub token_pattern: String,
     /// Token过期时间（秒）
@@ -114,23 +119,41 @@ impl BaseSystem {
         debug!("🔎 系统[{}]开始提取token，Headers数量: {}", 
                self.config.system_id, request.headers.len());
         
-        // 查找指定的header
-        let auth_header = request.headers
-            .iter()
-            .find(|(name, _)| name.eq_ignore_ascii_case(&self.config.header_name))
-            .map(|(_, value)| value);
-            
-        let auth_header = match auth_header {
-            Some(header) => {
-                debug!("📋 系统[{}]找到{}header: {}...", 
-                       self.config.system_id, self.config.header_name,
-                       &header[..header.len().min(20)]);
-                header
-            }
+        // 使用会话Cookie的系统从指定的Cookie中提取token
+        let auth_header = match &self.config.cookie_name {
+            Some(cookie_name) => match request.cookie_value(cookie_name) {
+                Some(value) => {
+                    debug!("🍪 系统[{}]找到Cookie {}: {}...", 
+                           self.config.system_id, cookie_name,
+                           value.chars().take(20).collect::<String>());
+                    value
+                }
+                None => {
+                    debug!("❌ 系统[{}]未找到Cookie {}", 
+                           self.config.system_id, cookie_name);
+                    return None;
+                }
+            },
             None => {
-                debug!("❌ 系统[{}]未找到{}header", 
-                       self.config.system_id, self.config.header_name);
-                return None;
+                // 查找指定的header
+                let auth_header = request.headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&self.config.header_name))
+                    .map(|(_, value)| value.as_str());
+
+                match auth_header {
+                    Some(header) => {
+                        debug!("📋 系统[{}]找到{}header: {}...", 
+                               self.config.system_id, self.config.header_name,
+                               &header[..header.len().min(20)]);
+                        header
+                    }
+                    None => {
+                        debug!("❌ 系统[{}]未找到{}header", 
+                               self.config.system_id, self.config.header_name);
+                        return None;
+                    }
+                }
             }
         };
 
@@ -161,6 +184,10 @@ impl SystemAuth for BaseSystem {
         &self.config.system_name
     }
     
+    fn url_pattern(&self) -> &str {
+        &self.config.url_pattern
+    }
+    
     fn process_http_request(&mut self, request: &HttpRequest) -> Result<()> {
         let url = build_url(request);
         debug!("🎯 系统[{}]开始处理HTTP请求: {} {}", 
@@ -356,13 +383,22 @@ impl TokenInfo {
 
 /// 构建完整URL（公共方法）
 pub fn build_url(request: &HttpRequest) -> String {
+    // 服务端出现过TLS握手时按HTTPS处理，没有Host头时使用ClientHello中的SNI
+    let is_tls = request.dst_port == 443
+        || tls::is_tls_endpoint(&request.dst_ip, request.dst_port);
     let host = if !request.host.is_empty() {
         request.host.clone()
+    } else if let Some(sni) = tls::server_name(&request.dst_ip, request.dst_port) {
+        if request.dst_port == 443 {
+            sni
+        } else {
+            format!("{}:{}", sni, request.dst_port)
+        }
     } else {
         format!("{}:{}", request.dst_ip, request.dst_port)
     };
     
-    let protocol = if request.dst_port == 443 { "https" } else { "http" };
+    let protocol = if is_tls { "https" } else { "http" };
     format!("{}://{}{}", protocol, host, request.path)
 }
 
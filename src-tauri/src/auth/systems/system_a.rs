@@ -30,6 +30,7 @@ pub fn create_system() -> BaseSystem {
         system_name: "GitLab".to_string(),
         url_pattern: r"192\.168\.91\.1.*".to_string(),
         header_name: "x-csrf-token".to_string(),
+        cookie_name: None,
         token_pattern: r"(.+)".to_string(), // 直接匹配任意字符，不需要Bearer前缀
         expires_duration: 3600, // 1小时
         validator: Box::new(LengthValidator),
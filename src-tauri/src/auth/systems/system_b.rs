@@ -38,6 +38,7 @@ pub fn create_system() -> BaseSystem {
         system_name: "用户中心".to_string(),
         url_pattern: r"https?://[^/]*user[^/]*\..*?/api/.*".to_string(),
         header_name: "X-Auth-Token".to_string(),
+        cookie_name: None,
         token_pattern: r"([A-Za-z0-9]{32,})".to_string(),
         expires_duration: 7200, // 2小时
         validator: Box::new(UserTokenValidator),
@@ -36,6 +36,7 @@ pub fn create_system() -> BaseSystem {
         system_name: "数据平台".to_string(),
         url_pattern: r"https?://[^/]*data[^/]*\..*?/api/.*".to_string(),
         header_name: "Access-Token".to_string(),
+        cookie_name: None,
         token_pattern: r"([A-Fa-f0-9]{64})".to_string(),
         expires_duration: 1800, // 30分钟
         validator: Box::new(DataTokenValidator),
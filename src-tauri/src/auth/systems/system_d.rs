@@ -36,6 +36,7 @@ pub fn create_system() -> BaseSystem {
         system_name: "业务系统".to_string(),
         url_pattern: r"https?://[^/]*business[^/]*\..*?/api/.*".to_string(),
         header_name: "Authentication".to_string(),
+        cookie_name: None,
         token_pattern: r"Token\s+([A-Za-z0-9\+/=]{40,})".to_string(),
         expires_duration: 1200, // 20分钟
         validator: Box::new(BusinessTokenValidator),
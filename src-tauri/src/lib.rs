@@ -33,10 +33,34 @@ pub mod commands {
         packet_capture::set_http_channel(channel).map_err(|e| e.to_string())
     }
     
-    // 初始化数据包捕获
+    // 设置 HTTP 事务通道
     #[tauri::command]
-    pub fn init_packet_capture() -> Result<(), String> {
-        packet_capture::init_packet_capture().map_err(|e| e.to_string())
+    pub fn set_transaction_channel(channel: Channel<packet_capture::HttpTransaction>) -> Result<(), String> {
+        packet_capture::set_transaction_channel(channel).map_err(|e| e.to_string())
+    }
+    
+    // 设置 TLS 连接通道
+    #[tauri::command]
+    pub fn set_tls_channel(channel: Channel<packet_capture::TlsConnection>) -> Result<(), String> {
+        packet_capture::set_tls_channel(channel).map_err(|e| e.to_string())
+    }
+    
+    // 设置 WebSocket 消息通道
+    #[tauri::command]
+    pub fn set_websocket_channel(channel: Channel<packet_capture::WebSocketMessage>) -> Result<(), String> {
+        packet_capture::set_websocket_channel(channel).map_err(|e| e.to_string())
+    }
+    
+    // 初始化数据包捕获（devices 为网卡名称或 "auto"，可同时捕获多个网卡，不传则使用上次的选择）
+    #[tauri::command]
+    pub fn init_packet_capture(devices: Option<Vec<String>>) -> Result<(), String> {
+        packet_capture::init_packet_capture(devices).map_err(|e| e.to_string())
+    }
+    
+    // 回放抓包文件（speed 为回放倍速，不传则尽快处理）
+    #[tauri::command]
+    pub fn init_file_capture(path: String, speed: Option<f64>) -> Result<(), String> {
+        packet_capture::init_file_capture(path, speed).map_err(|e| e.to_string())
     }
     
     // 停止数据包捕获
@@ -51,12 +75,90 @@ pub mod commands {
         packet_capture::has_packet_capture_prerequisites()
     }
     
+    // 检查抓包权限，返回每项检查的结果和修复命令
+    #[tauri::command]
+    pub fn check_capture_permissions() -> packet_capture::permissions::CapturePermissionReport {
+        packet_capture::permissions::check_capture_permissions()
+    }
+    
     // 获取网络设备列表
     #[tauri::command]
     pub fn get_network_devices() -> Result<Vec<packet_capture::NetworkDevice>, String> {
         packet_capture::get_network_devices().map_err(|e| e.to_string())
     }
     
+    // 获取默认使用的网卡（上次选择或自动选择）
+    #[tauri::command]
+    pub fn get_default_capture_devices() -> Result<Vec<String>, String> {
+        packet_capture::get_default_capture_devices().map_err(|e| e.to_string())
+    }
+    
+    // 获取捕获过滤器配置
+    #[tauri::command]
+    pub fn get_capture_filter_config() -> packet_capture::filter::CaptureFilterConfig {
+        packet_capture::filter::get_filter_config()
+    }
+    
+    // 校验并保存捕获过滤器配置，返回生成的过滤表达式
+    #[tauri::command]
+    pub fn set_capture_filter_config(config: packet_capture::filter::CaptureFilterConfig) -> Result<String, String> {
+        packet_capture::filter::set_filter_config(config).map_err(|e| e.to_string())
+    }
+    
+    // 按消息 ID 获取消息体的字节（base64 编码），decoded 为 true 时返回解压后的内容
+    #[tauri::command]
+    pub fn get_message_body(id: u64, decoded: bool) -> Result<String, String> {
+        packet_capture::body::get_body(id, decoded).map_err(|e| e.to_string())
+    }
+
+    // 保存 multipart 请求中的某个部分到下载目录，返回保存路径
+    #[tauri::command]
+    pub fn save_multipart_part(request_id: u64, index: usize) -> Result<String, String> {
+        packet_capture::multipart::save_part(request_id, index).map_err(|e| e.to_string())
+    }
+
+    // 查询从 Set-Cookie 中收集的 Cookie，host 为空时返回全部
+    #[tauri::command]
+    pub fn get_cookies(host: Option<String>) -> Vec<packet_capture::cookie::Cookie> {
+        packet_capture::cookie::get_cookies(host.as_deref())
+    }
+
+    // 清空收集的 Cookie
+    #[tauri::command]
+    pub fn clear_cookies() {
+        packet_capture::cookie::clear_cookies()
+    }
+
+    // 获取当前使用的 TLS 密钥日志文件
+    #[tauri::command]
+    pub fn get_key_log_file() -> Option<String> {
+        packet_capture::keylog::key_log_path()
+    }
+
+    // 设置 TLS 密钥日志文件，path 为空时使用 SSLKEYLOGFILE 环境变量，返回包含密钥的会话数量
+    #[tauri::command]
+    pub fn set_key_log_file(path: Option<String>) -> Result<usize, String> {
+        packet_capture::keylog::set_key_log_file(path).map_err(|e| e.to_string())
+    }
+
+    // 启动本地 HTTP/HTTPS 代理，config 为空时使用上次保存的配置
+    #[tauri::command]
+    pub fn start_proxy(config: Option<packet_capture::proxy::ProxyConfig>) -> Result<packet_capture::proxy::ProxyStatus, String> {
+        packet_capture::proxy::start_proxy(config).map_err(|e| e.to_string())
+    }
+
+    // 停止本地代理
+    #[tauri::command]
+    pub fn stop_proxy() -> Result<(), String> {
+        packet_capture::proxy::stop_proxy().map_err(|e| e.to_string())
+    }
+
+    // 获取本地代理状态
+    #[tauri::command]
+    pub fn get_proxy_status() -> packet_capture::proxy::ProxyStatus {
+        packet_capture::proxy::get_proxy_status()
+    }
+    
     // 创建数据包详情窗口
     #[tauri::command]
     pub async fn create_packet_window(
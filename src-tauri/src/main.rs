@@ -28,10 +28,27 @@ fn main() {
             big_data_rpa_v3_lib::commands::get_capture_status,
             big_data_rpa_v3_lib::commands::set_status_channel,
             big_data_rpa_v3_lib::commands::set_http_channel,
+            big_data_rpa_v3_lib::commands::set_transaction_channel,
+            big_data_rpa_v3_lib::commands::set_tls_channel,
+            big_data_rpa_v3_lib::commands::set_websocket_channel,
             big_data_rpa_v3_lib::commands::init_packet_capture,
+            big_data_rpa_v3_lib::commands::init_file_capture,
             big_data_rpa_v3_lib::commands::stop_packet_capture,
             big_data_rpa_v3_lib::commands::has_chmodbpf,
+            big_data_rpa_v3_lib::commands::check_capture_permissions,
             big_data_rpa_v3_lib::commands::get_network_devices,
+            big_data_rpa_v3_lib::commands::get_default_capture_devices,
+            big_data_rpa_v3_lib::commands::get_capture_filter_config,
+            big_data_rpa_v3_lib::commands::set_capture_filter_config,
+            big_data_rpa_v3_lib::commands::get_message_body,
+            big_data_rpa_v3_lib::commands::save_multipart_part,
+            big_data_rpa_v3_lib::commands::get_cookies,
+            big_data_rpa_v3_lib::commands::clear_cookies,
+            big_data_rpa_v3_lib::commands::get_key_log_file,
+            big_data_rpa_v3_lib::commands::set_key_log_file,
+            big_data_rpa_v3_lib::commands::start_proxy,
+            big_data_rpa_v3_lib::commands::stop_proxy,
+            big_data_rpa_v3_lib::commands::get_proxy_status,
             big_data_rpa_v3_lib::commands::create_packet_window,
             big_data_rpa_v3_lib::commands::focus_packet_window,
             // Auth系统命令
@@ -62,12 +79,18 @@ fn main() {
                     
                     #[cfg(target_os = "windows")]
                     info!("检测到Npcap已安装，可以直接使用抓包功能");
+
+                    #[cfg(target_os = "linux")]
+                    info!("检测到抓包权限（root 或网络能力），可以直接使用抓包功能");
                 } else {
                     #[cfg(target_os = "macos")]
                     info!("未检测到ChmodBPF，抓包功能可能受限");
 
                     #[cfg(target_os = "windows")]
                     info!("未检测到Npcap，抓包功能可能受限");
+
+                    #[cfg(target_os = "linux")]
+                    info!("未检测到抓包权限，请为可执行文件设置 cap_net_raw,cap_net_admin 能力");
                 }
             }
 
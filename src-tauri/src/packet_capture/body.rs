@@ -0,0 +1,272 @@
+use super::charset;
+use super::compression;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// 每个消息体（原始字节和解压后的内容）最多保留的字节数，超过部分丢弃并标记为截断
+pub const MAX_BODY_SIZE: usize = 1024 * 1024;
+/// 最多缓存多少字节的消息体，供前端按需查看原始数据
+const MAX_CACHED_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+// 最近捕获的消息体，按消息 ID 查找
+static BODY_CACHE: OnceCell<Mutex<BodyCache>> = OnceCell::new();
+
+/// HTTP 消息体，保留原始字节、解压后的内容以及按字符集解码后的文本
+///
+/// 通过 IPC 只传输文本和长度等信息，原始字节和解压后的内容由前端通过 get_body 按需获取。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HttpBody {
+    /// 原始字节（最多 MAX_BODY_SIZE，压缩时为压缩后的数据）
+    #[serde(skip)]
+    pub raw: Vec<u8>,
+    /// 消息体的实际长度
+    pub size: usize,
+    /// 消息体或解压后的内容超过 MAX_BODY_SIZE 时为 true，只保留开头部分
+    pub truncated: bool,
+    /// 内容编码（Content-Encoding），未压缩时为 None
+    pub content_encoding: Option<String>,
+    /// 解压后的内容（最多 MAX_BODY_SIZE），未压缩或解压失败时为 None
+    #[serde(skip)]
+    pub decoded: Option<Vec<u8>>,
+    /// 解码文本使用的字符集
+    pub charset: Option<String>,
+    /// 解码后的文本，二进制内容或字符集未知时为 None
+    pub text: Option<String>,
+}
+
+impl HttpBody {
+    /// 根据 Content-Type 和 Content-Encoding 创建消息体
+    pub fn new(data: Vec<u8>, content_type: &str, content_encoding: &str) -> Self {
+        let size = data.len();
+        Self::with_size(data, size, content_type, content_encoding)
+    }
+
+    /// 只收到了消息体开头部分时使用，size 为消息体的实际长度
+    pub fn with_size(
+        mut data: Vec<u8>,
+        size: usize,
+        content_type: &str,
+        content_encoding: &str,
+    ) -> Self {
+        let mut truncated = size > data.len() || data.len() > MAX_BODY_SIZE;
+        if truncated {
+            data.truncate(MAX_BODY_SIZE);
+        }
+
+        // 压缩数据被截断时仍保留已解压的部分
+        let content_encoding = Some(content_encoding.trim())
+            .filter(|encoding| !encoding.is_empty() && !encoding.eq_ignore_ascii_case("identity"))
+            .map(str::to_string);
+        let decoded = content_encoding
+            .as_deref()
+            .and_then(|encoding| compression::decompress(&data, encoding))
+            .map(|decompressed| {
+                truncated |= decompressed.truncated;
+                decompressed.data
+            });
+
+        let content = decoded.as_deref().unwrap_or(&data);
+        let (charset, text) = match decode_text(content, content_type, truncated) {
+            Some(decoded) => (Some(decoded.charset), Some(decoded.text)),
+            None => (None, None),
+        };
+
+        Self {
+            raw: data,
+            size,
+            truncated,
+            content_encoding,
+            decoded,
+            charset,
+            text,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// 消息体内容：解压成功时为解压后的数据，否则为原始字节
+    pub fn content(&self) -> &[u8] {
+        self.decoded.as_deref().unwrap_or(&self.raw)
+    }
+
+    /// 文本形式的消息体，无法解码时按 UTF-8 宽松转换
+    pub fn text_lossy(&self) -> String {
+        match &self.text {
+            Some(text) => text.clone(),
+            None => String::from_utf8_lossy(self.content()).into_owned(),
+        }
+    }
+}
+
+/// 从 Content-Type 中读取 charset 参数
+pub fn charset_param(content_type: &str) -> Option<String> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_ascii_lowercase())
+        .filter(|charset| !charset.is_empty())
+}
+
+/// Content-Type 中的媒体类型（小写，不含参数）
+pub fn mime_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// 是否是 JSON 媒体类型
+pub fn is_json_type(content_type: &str) -> bool {
+    let mime = mime_type(content_type);
+    mime == "application/json" || mime.ends_with("+json")
+}
+
+/// 根据 Content-Type 判断是否是文本类型
+fn is_text_type(content_type: &str) -> bool {
+    let mime = mime_type(content_type);
+
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime.as_str(),
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/x-javascript"
+                | "application/x-www-form-urlencoded"
+                | "application/graphql"
+        )
+}
+
+/// 按声明或检测到的字符集解码消息体，二进制内容或无法解码时返回 None
+fn decode_text(data: &[u8], content_type: &str, truncated: bool) -> Option<charset::Decoded> {
+    let declared = charset_param(content_type);
+
+    // 被截断的消息体末尾可能是不完整的多字节字符，最多去掉 3 个字节后重试
+    let max_trim = if truncated { 3.min(data.len()) } else { 0 };
+    let decoded = (0..=max_trim)
+        .find_map(|trim| charset::decode(&data[..data.len() - trim], declared.as_deref()))?;
+
+    // 没有声明字符集时，解码结果中不应包含控制字符
+    if declared.is_none() && !is_text_type(content_type) && !looks_like_text(&decoded.text) {
+        return None;
+    }
+    Some(decoded)
+}
+
+/// 不包含换行和制表符以外控制字符的内容视为文本
+fn looks_like_text(text: &str) -> bool {
+    text.chars()
+        .all(|c| !c.is_control() || matches!(c, '\r' | '\n' | '\t'))
+}
+
+/// 缓存的消息体，按捕获顺序排列，超过 MAX_CACHED_BODY_BYTES 时淘汰最早的
+#[derive(Default)]
+struct BodyCache {
+    entries: VecDeque<(u64, HttpBody)>,
+    bytes: usize,
+}
+
+impl HttpBody {
+    /// 缓存中占用的字节数
+    fn cached_bytes(&self) -> usize {
+        self.raw.len() + self.decoded.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// 缓存消息体的原始字节和解压后的内容，以便前端按消息 ID 查看
+pub fn remember(id: u64, body: &HttpBody) {
+    if body.raw.is_empty() {
+        return;
+    }
+    let cached = HttpBody {
+        raw: body.raw.clone(),
+        decoded: body.decoded.clone(),
+        ..HttpBody::default()
+    };
+    let cache = BODY_CACHE.get_or_init(|| Mutex::new(BodyCache::default()));
+    let mut cache = cache.lock().unwrap();
+    cache.bytes += cached.cached_bytes();
+    cache.entries.push_back((id, cached));
+    while cache.bytes > MAX_CACHED_BODY_BYTES {
+        match cache.entries.pop_front() {
+            Some((_, evicted)) => cache.bytes -= evicted.cached_bytes(),
+            None => break,
+        }
+    }
+}
+
+/// 获取消息体的字节，decoded 为 true 时返回解压后的内容（没有时返回原始字节），
+/// 结果编码为 base64
+pub fn get_body(id: u64, decoded: bool) -> Result<String> {
+    with_cached_body(id, |body| {
+        let data = if decoded { body.content() } else { &body.raw };
+        STANDARD.encode(data)
+    })
+}
+
+/// 在缓存中查找消息体，找到时以其调用 f
+pub(super) fn with_cached_body<T>(id: u64, f: impl FnOnce(&HttpBody) -> T) -> Result<T> {
+    let cache = BODY_CACHE
+        .get()
+        .ok_or_else(|| anyhow!("消息 {} 的消息体不存在或已过期", id))?
+        .lock()
+        .unwrap();
+    let (_, body) = cache
+        .entries
+        .iter()
+        .rev()
+        .find(|(cached_id, _)| *cached_id == id)
+        .ok_or_else(|| anyhow!("消息 {} 的消息体不存在或已过期", id))?;
+    Ok(f(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialized_body_omits_bytes() {
+        let body = HttpBody::new(vec![0, 159, 146, 150], "application/octet-stream", "");
+        let json = serde_json::to_value(&body).unwrap();
+        assert!(json.get("raw").is_none());
+        assert!(json.get("decoded").is_none());
+        assert_eq!(json["size"], 4);
+        assert!(json["text"].is_null());
+    }
+
+    #[test]
+    fn truncated_body_keeps_declared_size() {
+        let body = HttpBody::with_size(b"abc".to_vec(), 10, "text/plain", "");
+        assert!(body.truncated);
+        assert_eq!(body.size, 10);
+        assert_eq!(body.text.as_deref(), Some("abc"));
+
+        let big = HttpBody::new(vec![b'a'; MAX_BODY_SIZE + 1], "", "");
+        assert!(big.truncated);
+        assert_eq!(big.raw.len(), MAX_BODY_SIZE);
+        assert_eq!(big.size, MAX_BODY_SIZE + 1);
+    }
+
+    #[test]
+    fn remembered_body_is_fetched_by_id() {
+        let id = u64::MAX - 1;
+        remember(id, &HttpBody::new(b"hello".to_vec(), "text/plain", ""));
+        assert_eq!(get_body(id, false).unwrap(), STANDARD.encode(b"hello"));
+        // 没有解压后的内容时返回原始字节
+        assert_eq!(get_body(id, true).unwrap(), STANDARD.encode(b"hello"));
+        assert!(get_body(u64::MAX, false).is_err());
+    }
+}
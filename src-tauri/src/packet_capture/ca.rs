@@ -0,0 +1,177 @@
+use super::settings;
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use once_cell::sync::OnceCell;
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose,
+};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::ServerConfig;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use time::{Duration, OffsetDateTime};
+
+/// 根证书和私钥的文件名（位于应用配置目录）
+const CA_CERT_FILE: &str = "proxy_ca.pem";
+const CA_KEY_FILE: &str = "proxy_ca_key.pem";
+const CA_COMMON_NAME: &str = "Big Data RPA v3 Local CA";
+const CA_ORGANIZATION: &str = "Big Data RPA v3";
+const CA_VALIDITY_DAYS: i64 = 3650;
+/// 主机证书有效期，苹果系统要求 TLS 服务器证书不超过 825 天
+const HOST_CERT_VALIDITY_DAYS: i64 = 365;
+/// 最多缓存多少个主机的证书
+const MAX_CACHED_HOSTS: usize = 1024;
+
+// 本地根证书（首次使用时加载或生成）
+static AUTHORITY: OnceCell<CertificateAuthority> = OnceCell::new();
+
+/// 本地代理使用的根证书，为每个主机签发服务器证书
+pub struct CertificateAuthority {
+    /// 用于签名的根证书，由私钥和固定参数重建，与文件中的证书具有相同的名称和公钥
+    issuer: Certificate,
+    key: KeyPair,
+    cert_der: CertificateDer<'static>,
+    cert_pem: String,
+    cert_path: PathBuf,
+    /// 按主机缓存的 TLS 服务端配置
+    server_configs: Mutex<HashMap<String, Arc<ServerConfig>>>,
+}
+
+impl CertificateAuthority {
+    /// 从目录加载根证书，不存在时生成新的根证书并保存
+    pub fn load_or_create(dir: &Path) -> Result<Self> {
+        let cert_path = dir.join(CA_CERT_FILE);
+        let key_path = dir.join(CA_KEY_FILE);
+
+        let (key, cert_pem) = if cert_path.is_file() && key_path.is_file() {
+            let key_pem = std::fs::read_to_string(&key_path)
+                .map_err(|e| anyhow!("读取根证书私钥失败: {}", e))?;
+            let cert_pem = std::fs::read_to_string(&cert_path)
+                .map_err(|e| anyhow!("读取根证书失败: {}", e))?;
+            let key = KeyPair::from_pem(&key_pem).map_err(|e| anyhow!("根证书私钥无效: {}", e))?;
+            debug!("已加载本地根证书: {}", cert_path.display());
+            (key, cert_pem)
+        } else {
+            let key = KeyPair::generate().map_err(|e| anyhow!("生成根证书私钥失败: {}", e))?;
+            let now = OffsetDateTime::now_utc();
+            let mut params = ca_params();
+            params.not_before = now - Duration::days(1);
+            params.not_after = now + Duration::days(CA_VALIDITY_DAYS);
+            let cert = params
+                .self_signed(&key)
+                .map_err(|e| anyhow!("生成根证书失败: {}", e))?;
+
+            std::fs::create_dir_all(dir).map_err(|e| anyhow!("创建配置目录失败: {}", e))?;
+            std::fs::write(&key_path, key.serialize_pem())
+                .map_err(|e| anyhow!("保存根证书私钥失败: {}", e))?;
+            std::fs::write(&cert_path, cert.pem()).map_err(|e| anyhow!("保存根证书失败: {}", e))?;
+            info!("已生成本地根证书: {}", cert_path.display());
+            (key, cert.pem())
+        };
+
+        let cert_der = parse_pem_certificate(&cert_pem)?;
+        let issuer = ca_params()
+            .self_signed(&key)
+            .map_err(|e| anyhow!("加载根证书失败: {}", e))?;
+
+        Ok(Self {
+            issuer,
+            key,
+            cert_der,
+            cert_pem,
+            cert_path,
+            server_configs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 根证书文件路径，用户需要将其安装为受信任的根证书
+    pub fn cert_path(&self) -> &Path {
+        &self.cert_path
+    }
+
+    /// PEM 格式的根证书
+    pub fn cert_pem(&self) -> &str {
+        &self.cert_pem
+    }
+
+    /// 获取使用指定主机证书的 TLS 服务端配置
+    pub fn server_config(&self, host: &str) -> Result<Arc<ServerConfig>> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if let Some(config) = self.server_configs.lock().unwrap().get(&host) {
+            return Ok(config.clone());
+        }
+
+        let (chain, key) = self.issue(&host)?;
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(chain, key)
+            .map_err(|e| anyhow!("创建 TLS 服务端配置失败: {}", e))?;
+        // 只解析 HTTP/1.1
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        let config = Arc::new(config);
+
+        let mut configs = self.server_configs.lock().unwrap();
+        if configs.len() >= MAX_CACHED_HOSTS {
+            configs.clear();
+        }
+        configs.insert(host, config.clone());
+        Ok(config)
+    }
+
+    /// 为主机签发服务器证书，返回证书链和私钥
+    fn issue(&self, host: &str) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+        let mut params = CertificateParams::new(vec![host.to_string()])
+            .map_err(|e| anyhow!("无效的主机名 {}: {}", host, e))?;
+        params.distinguished_name.push(DnType::CommonName, host);
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+        let now = OffsetDateTime::now_utc();
+        params.not_before = now - Duration::days(1);
+        params.not_after = now + Duration::days(HOST_CERT_VALIDITY_DAYS);
+
+        // 每个主机使用独立的密钥，证书序列号由公钥生成，因此也各不相同
+        let key = KeyPair::generate().map_err(|e| anyhow!("生成证书私钥失败: {}", e))?;
+        let cert = params
+            .signed_by(&key, &self.issuer, &self.key)
+            .map_err(|e| anyhow!("签发 {} 的证书失败: {}", host, e))?;
+        debug!("已签发主机证书: {}", host);
+
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+        Ok((vec![cert.der().clone(), self.cert_der.clone()], key))
+    }
+}
+
+/// 根证书的固定参数
+fn ca_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, CA_COMMON_NAME);
+    params
+        .distinguished_name
+        .push(DnType::OrganizationName, CA_ORGANIZATION);
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    params
+}
+
+/// 解析 PEM 格式的证书
+fn parse_pem_certificate(pem: &str) -> Result<CertificateDer<'static>> {
+    use rustls::pki_types::pem::PemObject;
+    CertificateDer::from_pem_slice(pem.as_bytes()).map_err(|e| anyhow!("根证书格式错误: {}", e))
+}
+
+/// 应用使用的根证书，保存在应用配置目录
+pub fn authority() -> Result<&'static CertificateAuthority> {
+    AUTHORITY.get_or_try_init(|| {
+        let dir = settings::config_dir().ok_or_else(|| anyhow!("无法确定应用配置目录"))?;
+        CertificateAuthority::load_or_create(&dir)
+    })
+}
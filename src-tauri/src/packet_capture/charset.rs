@@ -0,0 +1,187 @@
+use encoding_rs::{Encoding, GB18030, UTF_8};
+
+/// 解码后的文本及其字符集
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// 字符集名称（小写），例如 utf-8、gbk、gb18030
+    pub charset: String,
+    pub text: String,
+}
+
+/// 按 BOM、声明的字符集或检测结果解码，无法无损解码时返回 None
+///
+/// 没有声明字符集时依次尝试 UTF-8 和 GB18030（兼容 GBK/GB2312）。
+pub fn decode(data: &[u8], declared: Option<&str>) -> Option<Decoded> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(data) {
+        return decode_with(encoding, &data[bom_len..]);
+    }
+
+    if let Some(label) = declared {
+        // 无法识别的字符集按未声明处理
+        if let Some(encoding) = Encoding::for_label(label.trim().as_bytes()) {
+            return decode_with(encoding, data);
+        }
+    }
+
+    decode_with(UTF_8, data).or_else(|| decode_with(GB18030, data))
+}
+
+/// 使用指定字符集严格解码
+fn decode_with(encoding: &'static Encoding, data: &[u8]) -> Option<Decoded> {
+    let text = encoding
+        .decode_without_bom_handling_and_without_replacement(data)?
+        .into_owned();
+    Some(Decoded {
+        charset: encoding.name().to_ascii_lowercase(),
+        text,
+    })
+}
+
+/// 解码消息头：UTF-8 或 GB18030，都不符合时按 UTF-8 宽松转换
+pub fn decode_header(data: &[u8]) -> String {
+    match decode(data, None) {
+        Some(decoded) => decoded.text,
+        None => String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+/// 百分号解码为原始字节，plus_as_space 为 true 时 '+' 解码为空格（表单编码）
+pub fn percent_decode(input: &str, plus_as_space: bool) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        output.push(high << 4 | low);
+                        i += 3;
+                        continue;
+                    }
+                    // 不合法的转义保留原样
+                    _ => output.push(b'%'),
+                }
+            }
+            b'+' if plus_as_space => output.push(b' '),
+            b => output.push(b),
+        }
+        i += 1;
+    }
+    output
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// 解码百分号编码的查询参数或表单字段，无法确定字符集时保留原始（编码后）文本
+pub fn decode_component(input: &str, declared: Option<&str>) -> String {
+    let bytes = percent_decode(input, true);
+    match decode(&bytes, declared) {
+        Some(decoded) => decoded.text,
+        None => input.to_string(),
+    }
+}
+
+/// 解析 application/x-www-form-urlencoded 格式的键值对（查询字符串或表单）
+pub fn decode_pairs(input: &str, declared: Option<&str>) -> Vec<(String, String)> {
+    input
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                decode_component(name, declared),
+                decode_component(value, declared),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_capture::body::HttpBody;
+
+    /// "中文" 的 GBK 编码
+    const GBK_CHINESE: [u8; 4] = [0xd6, 0xd0, 0xce, 0xc4];
+
+    #[test]
+    fn gbk_body_declared_in_content_type() {
+        let mut data = b"name=".to_vec();
+        data.extend_from_slice(&GBK_CHINESE);
+        let body = HttpBody::new(data, "text/plain; charset=GBK", "");
+        assert_eq!(body.charset.as_deref(), Some("gbk"));
+        assert_eq!(body.text.as_deref(), Some("name=中文"));
+    }
+
+    #[test]
+    fn undeclared_gb18030_falls_back_after_utf8() {
+        // 包含 GB18030 的四字节字符，不是合法的 UTF-8
+        let (data, _, _) = GB18030.encode("中文😀");
+        assert!(std::str::from_utf8(&data).is_err());
+        let decoded = decode(&data, None).unwrap();
+        assert_eq!(decoded.charset, "gb18030");
+        assert_eq!(decoded.text, "中文😀");
+
+        // 合法的 UTF-8 优先
+        let decoded = decode("中文".as_bytes(), None).unwrap();
+        assert_eq!(decoded.charset, "utf-8");
+
+        // 无法识别的字符集按未声明处理
+        let decoded = decode(&GBK_CHINESE, Some("x-unknown")).unwrap();
+        assert_eq!(decoded.text, "中文");
+    }
+
+    #[test]
+    fn bom_overrides_declared_charset() {
+        let mut data = vec![0xef, 0xbb, 0xbf];
+        data.extend_from_slice("中文".as_bytes());
+        let decoded = decode(&data, Some("gbk")).unwrap();
+        assert_eq!(decoded.charset, "utf-8");
+        assert_eq!(decoded.text, "中文");
+
+        let decoded = decode(&[0xff, 0xfe, b'h', 0, b'i', 0], None).unwrap();
+        assert_eq!(decoded.charset, "utf-16le");
+        assert_eq!(decoded.text, "hi");
+    }
+
+    #[test]
+    fn invalid_bytes_are_not_decoded() {
+        assert_eq!(decode(&[0xff, 0xff], None), None);
+        assert_eq!(decode(&[0xd6], Some("gbk")), None);
+        assert_eq!(decode_header(&[b'a', 0xff]), "a\u{fffd}");
+    }
+
+    #[test]
+    fn percent_encoded_gbk_query_values() {
+        assert_eq!(
+            decode_pairs("q=%D6%D0%CE%C4&page=1&empty", None),
+            vec![
+                ("q".to_string(), "中文".to_string()),
+                ("page".to_string(), "1".to_string()),
+                ("empty".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(decode_component("%D6%D0%CE%C4+x", Some("gbk")), "中文 x");
+        assert_eq!(decode_component("%E4%B8%AD%E6%96%87", None), "中文");
+        // 无法解码时保留原始文本
+        assert_eq!(decode_component("%FF", None), "%FF");
+    }
+
+    #[test]
+    fn malformed_percent_escapes_kept() {
+        assert_eq!(percent_decode("%", false), b"%");
+        assert_eq!(percent_decode("%4", false), b"%4");
+        assert_eq!(percent_decode("a%4g%41", false), b"a%4gA");
+        assert_eq!(percent_decode("100%%", false), b"100%%");
+        assert_eq!(percent_decode("a+b", false), b"a+b");
+        assert_eq!(percent_decode("a+b", true), b"a b");
+    }
+}
@@ -0,0 +1,188 @@
+use super::body::MAX_BODY_SIZE;
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use log::debug;
+use std::io::{ErrorKind, Read};
+
+/// 解压结果
+#[derive(Debug)]
+pub struct Decompressed {
+    pub data: Vec<u8>,
+    /// 解压后的数据超过上限或压缩数据不完整时为 true
+    pub truncated: bool,
+}
+
+/// 按 Content-Encoding 解压消息体，未压缩、编码不支持或解压失败时返回 None
+///
+/// 支持 gzip、deflate 和 br，多个编码按逆序依次解压。
+pub fn decompress(data: &[u8], content_encoding: &str) -> Option<Decompressed> {
+    let codings: Vec<String> = content_encoding
+        .split(',')
+        .map(|coding| coding.trim().to_ascii_lowercase())
+        .filter(|coding| !coding.is_empty() && coding != "identity")
+        .collect();
+    if codings.is_empty() || data.is_empty() {
+        return None;
+    }
+
+    let mut current = Decompressed {
+        data: data.to_vec(),
+        truncated: false,
+    };
+    for coding in codings.iter().rev() {
+        let next = decompress_one(&current.data, coding)?;
+        current = Decompressed {
+            data: next.data,
+            truncated: current.truncated || next.truncated,
+        };
+    }
+    Some(current)
+}
+
+fn decompress_one(data: &[u8], coding: &str) -> Option<Decompressed> {
+    match coding {
+        "gzip" | "x-gzip" => read_limited(MultiGzDecoder::new(data)),
+        // deflate 应为 zlib 格式，但部分服务端发送的是裸 deflate 数据
+        "deflate" => {
+            read_limited(ZlibDecoder::new(data)).or_else(|| read_limited(DeflateDecoder::new(data)))
+        }
+        "br" => read_limited(brotli_decompressor::Decompressor::new(data, 4096)),
+        _ => {
+            debug!("不支持的内容编码: {}", coding);
+            None
+        }
+    }
+}
+
+/// 读取解压数据，最多 MAX_BODY_SIZE 字节（与原始数据相同的上限）；数据不完整时保留已解压的部分
+fn read_limited<R: Read>(mut reader: R) -> Option<Decompressed> {
+    let mut output = Vec::new();
+    let mut buf = [0u8; 8192];
+
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => {
+                return Some(Decompressed {
+                    data: output,
+                    truncated: false,
+                })
+            }
+            Ok(n) => {
+                let room = MAX_BODY_SIZE - output.len();
+                if n > room {
+                    debug!("解压后的数据超过 {} 字节，截断", MAX_BODY_SIZE);
+                    output.extend_from_slice(&buf[..room]);
+                    return Some(Decompressed {
+                        data: output,
+                        truncated: true,
+                    });
+                }
+                output.extend_from_slice(&buf[..n]);
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                debug!("解压失败（已解压 {} 字节）: {}", output.len(), e);
+                if output.is_empty() {
+                    return None;
+                }
+                return Some(Decompressed {
+                    data: output,
+                    truncated: true,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    const TEXT: &[u8] = b"{\"token\":\"abc\",\"user\":\"test\"}";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn br(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut output, 4096, 5, 22);
+            writer.write_all(data).unwrap();
+        }
+        output
+    }
+
+    #[test]
+    fn gzip_round_trip() {
+        let decompressed = decompress(&gzip(TEXT), "gzip").unwrap();
+        assert_eq!(decompressed.data, TEXT);
+        assert!(!decompressed.truncated);
+        assert_eq!(decompress(&gzip(TEXT), "X-GZIP").unwrap().data, TEXT);
+    }
+
+    #[test]
+    fn deflate_zlib_and_raw_round_trip() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(TEXT).unwrap();
+        let decompressed = decompress(&zlib.finish().unwrap(), "deflate").unwrap();
+        assert_eq!(decompressed.data, TEXT);
+
+        // 部分服务端发送不带 zlib 头的裸 deflate 数据
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(TEXT).unwrap();
+        let decompressed = decompress(&raw.finish().unwrap(), "deflate").unwrap();
+        assert_eq!(decompressed.data, TEXT);
+        assert!(!decompressed.truncated);
+    }
+
+    #[test]
+    fn brotli_round_trip() {
+        let decompressed = decompress(&br(TEXT), "br").unwrap();
+        assert_eq!(decompressed.data, TEXT);
+        assert!(!decompressed.truncated);
+    }
+
+    #[test]
+    fn multiple_codings_applied_in_reverse() {
+        let data = br(&gzip(TEXT));
+        assert_eq!(decompress(&data, "gzip, br").unwrap().data, TEXT);
+        assert_eq!(
+            decompress(&gzip(TEXT), "identity, gzip").unwrap().data,
+            TEXT
+        );
+    }
+
+    #[test]
+    fn decompression_bomb_stops_at_limit() {
+        let zeros = vec![0u8; MAX_BODY_SIZE + 1024 * 1024];
+        let bomb = gzip(&zeros);
+        assert!(bomb.len() < MAX_BODY_SIZE / 100);
+
+        let decompressed = decompress(&bomb, "gzip").unwrap();
+        assert!(decompressed.truncated);
+        assert_eq!(decompressed.data.len(), MAX_BODY_SIZE);
+    }
+
+    #[test]
+    fn incomplete_data_keeps_decompressed_prefix() {
+        let text = vec![b'a'; 64 * 1024];
+        let compressed = gzip(&text);
+        let decompressed = decompress(&compressed[..compressed.len() - 8], "gzip").unwrap();
+        assert!(decompressed.truncated);
+        assert!(text.starts_with(&decompressed.data));
+    }
+
+    #[test]
+    fn unknown_or_corrupt_encoding() {
+        assert!(decompress(TEXT, "compress").is_none());
+        assert!(decompress(TEXT, "gzip").is_none());
+        assert!(decompress(TEXT, "br").is_none());
+        assert!(decompress(b"", "gzip").is_none());
+        assert!(decompress(TEXT, "identity").is_none());
+    }
+}
@@ -0,0 +1,405 @@
+use super::{HttpRequest, HttpResponse};
+use log::debug;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// 每个域名最多保留的 Cookie 数量，超过时丢弃最早设置的
+const MAX_COOKIES_PER_DOMAIN: usize = 64;
+
+// 从捕获的 Set-Cookie 响应中建立的 Cookie 库
+static COOKIE_JAR: OnceCell<Mutex<CookieJar>> = OnceCell::new();
+
+/// 服务端通过 Set-Cookie 设置的 Cookie
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// 生效的域名（小写，不含前导点）
+    pub domain: String,
+    /// 没有 Domain 属性时只对设置它的主机生效，不包括子域名
+    pub host_only: bool,
+    pub path: String,
+    /// 过期时间（秒级时间戳），会话 Cookie 为 None
+    pub expires: Option<u64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<String>,
+    /// 捕获到 Set-Cookie 的时间（秒级时间戳）
+    pub created: u64,
+}
+
+impl Cookie {
+    /// 在指定时间（秒级时间戳）是否已过期
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// 是否会随发往 host 的请求发送
+    pub fn matches_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        }
+    }
+}
+
+/// 按域名保存的 Cookie
+///
+/// 过期判断使用捕获时间而不是本机时间，回放旧的抓包文件时 Cookie 仍按当时的状态显示
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    domains: HashMap<String, Vec<Cookie>>,
+    /// 最近捕获到的响应的时间（秒级时间戳）
+    latest: u64,
+}
+
+impl CookieJar {
+    /// 推进捕获时钟，乱序到达的较早时间不会让时钟倒退
+    pub fn advance(&mut self, now: u64) {
+        self.latest = self.latest.max(now);
+    }
+
+    /// 保存 Cookie，同名同域同路径的 Cookie 会被替换，已过期的 Cookie 表示删除
+    pub fn store(&mut self, cookie: Cookie) {
+        self.advance(cookie.created);
+        let cookies = self.domains.entry(cookie.domain.clone()).or_default();
+        cookies.retain(|existing| {
+            !(existing.name == cookie.name
+                && existing.path == cookie.path
+                && existing.host_only == cookie.host_only)
+        });
+
+        if cookie.is_expired(self.latest) {
+            debug!("删除 Cookie: {} (域名: {})", cookie.name, cookie.domain);
+        } else {
+            if cookies.len() >= MAX_COOKIES_PER_DOMAIN {
+                cookies.remove(0);
+            }
+            cookies.push(cookie);
+        }
+
+        self.domains.retain(|_, cookies| !cookies.is_empty());
+    }
+
+    /// 对 host 生效且在最近的捕获时间仍未过期的 Cookie，host 为空时返回全部
+    pub fn cookies(&self, host: Option<&str>) -> Vec<Cookie> {
+        let host = host.map(host_without_port).filter(|host| !host.is_empty());
+        let mut cookies: Vec<Cookie> = self
+            .domains
+            .values()
+            .flatten()
+            .filter(|cookie| !cookie.is_expired(self.latest))
+            .filter(|cookie| host.is_none_or(|host| cookie.matches_host(host)))
+            .cloned()
+            .collect();
+        cookies.sort_by(|a, b| (&a.domain, &a.path, &a.name).cmp(&(&b.domain, &b.path, &b.name)));
+        cookies
+    }
+
+    pub fn clear(&mut self) {
+        self.domains.clear();
+        self.latest = 0;
+    }
+}
+
+/// 解析请求中的 Cookie 头（可能有多个），返回按顺序排列的名称和值
+pub fn parse_cookie_header(headers: &[(String, String)]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("Cookie"))
+        .flat_map(|(_, value)| value.split(';'))
+        .filter_map(|pair| {
+            let pair = pair.trim();
+            if pair.is_empty() {
+                return None;
+            }
+            let (name, value) = pair.split_once('=').unwrap_or(("", pair));
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// 解析响应中所有的 Set-Cookie 头，域名和路径的默认值取自对应的请求
+pub fn parse_set_cookies(request: &HttpRequest, response: &HttpResponse) -> Vec<Cookie> {
+    let host = if request.host.is_empty() {
+        request.dst_ip.clone()
+    } else {
+        host_without_port(&request.host).to_ascii_lowercase()
+    };
+
+    response
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("Set-Cookie"))
+        .filter_map(|(_, value)| {
+            parse_set_cookie(value, &host, &request.url_path, response.timestamp)
+        })
+        .collect()
+}
+
+/// 解析单个 Set-Cookie 头，格式错误或 Domain 与请求主机不符时返回 None
+pub fn parse_set_cookie(
+    header: &str,
+    request_host: &str,
+    request_path: &str,
+    now: u64,
+) -> Option<Cookie> {
+    let mut fields = header.split(';');
+    let pair = fields.next()?;
+    let (name, value) = match pair.split_once('=') {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => ("", pair.trim()),
+    };
+    if name.is_empty() && value.is_empty() {
+        return None;
+    }
+
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        domain: request_host.to_ascii_lowercase(),
+        host_only: true,
+        path: default_path(request_path),
+        expires: None,
+        secure: false,
+        http_only: false,
+        same_site: None,
+        created: now,
+    };
+
+    // Max-Age 优先于 Expires
+    let mut max_age = None;
+    for attribute in fields {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "expires" => {
+                if let Some(expires) = parse_cookie_date(value) {
+                    cookie.expires = Some(expires);
+                }
+            }
+            "max-age" => {
+                if let Ok(seconds) = value.parse::<i64>() {
+                    max_age = Some(seconds);
+                }
+            }
+            "domain" => {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                if domain.is_empty() {
+                    continue;
+                }
+                if !domain_matches(&cookie.domain, &domain) {
+                    debug!(
+                        "忽略 Cookie {}：Domain {} 与请求主机 {} 不符",
+                        cookie.name, domain, request_host
+                    );
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" if !value.is_empty() => cookie.same_site = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if let Some(seconds) = max_age {
+        cookie.expires = Some(if seconds <= 0 {
+            0
+        } else {
+            now.saturating_add(seconds as u64)
+        });
+    }
+
+    Some(cookie)
+}
+
+/// 将 Cookie 保存到全局 Cookie 库，now 为响应的捕获时间，没有 Set-Cookie 时也用来推进时钟
+pub fn remember(cookies: &[Cookie], now: u64) {
+    let jar = COOKIE_JAR.get_or_init(|| Mutex::new(CookieJar::default()));
+    let mut jar = jar.lock().unwrap();
+    jar.advance(now);
+    for cookie in cookies {
+        jar.store(cookie.clone());
+    }
+}
+
+/// 查询全局 Cookie 库中对 host 生效的 Cookie，host 为空时返回全部
+pub fn get_cookies(host: Option<&str>) -> Vec<Cookie> {
+    COOKIE_JAR
+        .get()
+        .map(|jar| jar.lock().unwrap().cookies(host))
+        .unwrap_or_default()
+}
+
+/// 清空全局 Cookie 库
+pub fn clear_cookies() {
+    if let Some(jar) = COOKIE_JAR.get() {
+        jar.lock().unwrap().clear();
+    }
+}
+
+/// 去掉 Host 头中的端口，支持 [IPv6]:port 形式
+fn host_without_port(host: &str) -> &str {
+    let host = host.trim();
+    if let Some(rest) = host.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
+    }
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+/// host 是否等于 domain 或是其子域名；IP 地址只能完全相等
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    host.parse::<std::net::IpAddr>().is_err()
+        && host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// 没有 Path 属性时，路径取请求路径中最后一个 '/' 之前的部分
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(end) if request_path.starts_with('/') && end > 0 => request_path[..end].to_string(),
+        _ => "/".to_string(),
+    }
+}
+
+/// 解析 Expires 中的日期（秒级时间戳），兼容 RFC 1123、RFC 850 和 asctime 格式
+fn parse_cookie_date(value: &str) -> Option<u64> {
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    let tokens = value
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == ':'))
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        let numeric = token.bytes().all(|b| b.is_ascii_digit());
+        if time.is_none() && token.contains(':') {
+            time = parse_time(token);
+        } else if day.is_none() && numeric && token.len() <= 2 {
+            day = token.parse::<i64>().ok();
+        } else if month.is_none() && !numeric {
+            month = month_index(token);
+        } else if year.is_none() && numeric && (2..=4).contains(&token.len()) {
+            year = token.parse::<i64>().ok();
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    // 两位数年份：70-99 为 19xx，00-69 为 20xx
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if (0..=69).contains(&year) {
+        year += 2000;
+    }
+    if !(1..=31).contains(&day) || year < 1601 {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    Some(seconds.max(0) as u64)
+}
+
+fn parse_time(token: &str) -> Option<(i64, i64, i64)> {
+    let mut fields = token.split(':').map(|field| field.parse::<i64>().ok());
+    let (hour, minute, second) = (fields.next()??, fields.next()??, fields.next()??);
+    if fields.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    Some((hour, minute, second))
+}
+
+fn month_index(token: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    MONTHS
+        .iter()
+        .position(|month| *month == prefix)
+        .map(|index| index as i64 + 1)
+}
+
+/// 公历日期距 1970-01-01 的天数
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_set_cookie_attributes() {
+        let cookie = parse_set_cookie(
+            "SID=abc; Path=/; Domain=.Example.com; Secure; HttpOnly; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            "www.example.com",
+            "/a/b",
+            100,
+        )
+        .unwrap();
+        assert_eq!(cookie.domain, "example.com");
+        assert!(!cookie.host_only && cookie.secure && cookie.http_only);
+        assert_eq!(cookie.expires, Some(1445412480));
+        assert!(cookie.matches_host("a.example.com"));
+        assert!(!cookie.matches_host("badexample.com"));
+
+        let cookie = parse_set_cookie("x=1; Max-Age=60", "h", "/a/b", 100).unwrap();
+        assert_eq!(cookie.expires, Some(160));
+        assert_eq!(cookie.path, "/a");
+        assert!(parse_set_cookie("x=1; Domain=other.com", "h.com", "/", 0).is_none());
+    }
+
+    #[test]
+    fn expiry_uses_capture_time() {
+        // 2015 年捕获的 Cookie，Max-Age 相对于捕获时间，按本机时间早已过期
+        let captured = 1445412000;
+        let mut jar = CookieJar::default();
+        jar.store(parse_set_cookie("a=1; Max-Age=3600", "h.com", "/", captured).unwrap());
+        assert_eq!(jar.cookies(Some("h.com")).len(), 1);
+
+        // 之后捕获到的响应推进时钟，超过 Max-Age 后不再返回
+        jar.store(parse_set_cookie("b=2", "h.com", "/", captured + 1800).unwrap());
+        assert_eq!(jar.cookies(None).len(), 2);
+        jar.advance(captured + 7200);
+        let cookies = jar.cookies(None);
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name, "b");
+
+        // 较早的时间不会让时钟倒退
+        jar.advance(captured);
+        assert_eq!(jar.cookies(None).len(), 1);
+    }
+
+    #[test]
+    fn replaces_and_deletes_cookies() {
+        let mut jar = CookieJar::default();
+        jar.store(parse_set_cookie("a=1", "h.com", "/", 10).unwrap());
+        jar.store(parse_set_cookie("a=2", "h.com", "/", 10).unwrap());
+        assert_eq!(jar.cookies(Some("h.com:8080")).len(), 1);
+        assert_eq!(jar.cookies(Some("h.com"))[0].value, "2");
+
+        jar.store(parse_set_cookie("a=; Max-Age=0", "h.com", "/", 30).unwrap());
+        assert!(jar.cookies(None).is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use pcap::{ConnectionStatus, Device};
+use std::net::IpAddr;
+
+/// 自动选择网卡时使用的设备名
+pub const AUTO_DEVICE: &str = "auto";
+
+/// 虚拟网卡常见的名称/描述关键字（容器网桥、虚拟机、隧道等）
+const VIRTUAL_KEYWORDS: [&str; 16] = [
+    "docker", "br-", "veth", "virbr", "vmnet", "vboxnet", "virtualbox", "vmware",
+    "hyper-v", "virtual", "bridge", "awdl", "llw", "wan miniport", "bluetooth", "npcap loopback",
+];
+
+/// 伪设备，不对应具体网卡
+const PSEUDO_DEVICES: [&str; 5] = ["any", "nflog", "nfqueue", "dbus-system", "dbus-session"];
+
+/// 根据名称选择网卡，名称为 "auto" 时自动选择最合适的网卡
+pub fn resolve_device(spec: &str) -> Result<Device> {
+    let list = Device::list().map_err(|e| anyhow!("获取网络设备列表失败: {}", e))?;
+    if list.is_empty() {
+        return Err(anyhow!("没有找到可用的网络设备"));
+    }
+
+    if spec.eq_ignore_ascii_case(AUTO_DEVICE) {
+        return auto_select(&list).ok_or_else(|| anyhow!("没有找到非回环网络设备"));
+    }
+
+    list.into_iter()
+        .find(|device| device.name == spec)
+        .ok_or_else(|| anyhow!("未找到网络设备: {}", spec))
+}
+
+/// 自动选择网卡：优先已启用、已连接、有可路由地址的物理网卡
+pub fn auto_select(list: &[Device]) -> Option<Device> {
+    let best = list
+        .iter()
+        .filter(|device| !device.flags.is_loopback())
+        .filter(|device| !PSEUDO_DEVICES.contains(&device.name.as_str()))
+        .map(|device| (score_device(device), device))
+        .inspect(|(score, device)| debug!("网卡 {} 评分: {}", device.name, score))
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, device)| device.clone());
+
+    if let Some(device) = &best {
+        info!("自动选择网络设备: {}", device.name);
+    }
+    best
+}
+
+/// 网卡评分，分数越高越适合捕获
+fn score_device(device: &Device) -> i32 {
+    let mut score = 0;
+
+    if device.flags.is_up() {
+        score += 10;
+    }
+    if device.flags.is_running() {
+        score += 10;
+    }
+    match device.flags.connection_status {
+        ConnectionStatus::Connected => score += 10,
+        ConnectionStatus::Disconnected => score -= 20,
+        _ => {}
+    }
+
+    // 有可路由的 IPv4 地址的网卡最可能承载业务流量
+    let routable_v4 = device.addresses.iter().any(|address| match address.addr {
+        IpAddr::V4(ip) => !ip.is_loopback() && !ip.is_link_local() && !ip.is_unspecified(),
+        IpAddr::V6(_) => false,
+    });
+    if routable_v4 {
+        score += 20;
+    } else if device.addresses.is_empty() {
+        score -= 20;
+    }
+
+    let name = device.name.to_lowercase();
+    let desc = device.desc.as_deref().unwrap_or("").to_lowercase();
+    if VIRTUAL_KEYWORDS
+        .iter()
+        .any(|keyword| name.starts_with(keyword) || desc.contains(keyword))
+    {
+        score -= 15;
+    }
+
+    score
+}
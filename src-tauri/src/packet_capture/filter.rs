@@ -0,0 +1,407 @@
+use super::settings;
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use pcap::{Capture, Linktype};
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 默认捕获的 HTTP 端口
+pub const DEFAULT_PORTS: [u16; 3] = [80, 8080, 443];
+
+/// 匹配 TCP 的 IP 分片：非首个分片不含 TCP 首部，端口条件无法匹配，需要单独放行以便重组
+const TCP_FRAGMENT_BPF: &str = "(ip[9] = 6 and ip[6:2] & 0x3fff != 0) or (ip6[6] = 44 and ip6[40] = 6)";
+
+// 过滤器版本号，配置或认证系统变化时递增，捕获线程据此重新设置过滤器
+static FILTER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 捕获过滤器配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureFilterConfig {
+    /// 需要捕获的 TCP 端口
+    pub ports: Vec<u16>,
+    /// 只捕获与这些主机通信的流量（IP、网段或主机名），为空表示不限制
+    pub hosts: Vec<String>,
+    /// 自定义 BPF 表达式，不为空时忽略其他配置
+    pub bpf: Option<String>,
+    /// 根据已注册认证系统的 URL 模式自动生成过滤器（默认开启）
+    pub auto_from_systems: bool,
+}
+
+impl Default for CaptureFilterConfig {
+    fn default() -> Self {
+        Self {
+            ports: DEFAULT_PORTS.to_vec(),
+            hosts: Vec::new(),
+            bpf: None,
+            auto_from_systems: true,
+        }
+    }
+}
+
+impl CaptureFilterConfig {
+    /// 生成 BPF 过滤表达式，system_patterns 为认证系统的 URL 匹配模式
+    pub fn to_bpf(&self, system_patterns: &[String]) -> Result<String> {
+        if let Some(bpf) = self.bpf.as_deref().map(str::trim).filter(|bpf| !bpf.is_empty()) {
+            return Ok(bpf.to_string());
+        }
+
+        let base = self.base_bpf()?;
+        if !self.auto_from_systems || system_patterns.is_empty() {
+            return Ok(base);
+        }
+
+        let mut terms = Vec::new();
+        let mut needs_fallback = false;
+        for pattern in system_patterns {
+            match pattern_to_bpf(pattern) {
+                // 主机名在编译过滤器时才由 libpcap 解析，解析失败会使整个过滤器无法使用
+                Some(term) if validate_bpf(&term).is_err() => {
+                    warn!(
+                        "URL 模式 {} 的过滤条件 {} 无法编译（主机名可能无法解析），回退到端口过滤器",
+                        pattern, term
+                    );
+                    needs_fallback = true;
+                }
+                Some(term) => {
+                    debug!("URL 模式 {} 转换为过滤条件: {}", pattern, term);
+                    if !terms.contains(&term) {
+                        terms.push(term);
+                    }
+                }
+                None => {
+                    warn!("URL 模式 {} 无法转换为主机/端口条件，回退到端口过滤器", pattern);
+                    needs_fallback = true;
+                }
+            }
+        }
+
+        // 无法转换的系统仍需通过端口过滤器捕获
+        if needs_fallback {
+            terms.insert(0, base);
+        }
+        if terms.len() == 1 {
+            return Ok(terms.remove(0));
+        }
+        Ok(terms
+            .iter()
+            .map(|term| format!("({})", term))
+            .collect::<Vec<_>>()
+            .join(" or "))
+    }
+
+    /// 根据端口和主机生成的基础过滤表达式
+    pub fn base_bpf(&self) -> Result<String> {
+        if self.ports.is_empty() {
+            return Err(anyhow!("至少需要配置一个端口"));
+        }
+        if self.ports.contains(&0) {
+            return Err(anyhow!("端口号无效: 0"));
+        }
+
+        let ports = self
+            .ports
+            .iter()
+            .map(|port| format!("tcp port {}", port))
+            .collect::<Vec<_>>()
+            .join(" or ");
+
+        let hosts = self
+            .hosts
+            .iter()
+            .map(|host| host.trim())
+            .filter(|host| !host.is_empty())
+            .map(host_term)
+            .collect::<Result<Vec<_>>>()?;
+
+        if hosts.is_empty() {
+            Ok(ports)
+        } else {
+            Ok(format!("({}) and ({})", ports, hosts.join(" or ")))
+        }
+    }
+}
+
+/// 将认证系统的 URL 正则转换为 BPF 条件，无法确定主机时返回 None
+///
+/// 支持以字面量主机开头的模式，例如 `https?://10\.1\.2\.3:8443/api/.*`、
+/// `192\.168\.91\.1.*`（按网段处理）或 `https://oa\.example\.com/.*`。
+pub fn pattern_to_bpf(pattern: &str) -> Option<String> {
+    let mut rest = pattern.trim_start_matches('^');
+
+    // 去掉协议部分，记录协议以推断默认端口
+    let mut scheme = None;
+    for (prefix, matched) in [
+        ("(?:https?://)?", None),
+        ("(https?://)?", None),
+        ("https?://", None),
+        ("https?:\\/\\/", None),
+        ("https://", Some(443)),
+        ("https:\\/\\/", Some(443)),
+        ("http://", Some(80)),
+        ("http:\\/\\/", Some(80)),
+    ] {
+        if let Some(stripped) = rest.strip_prefix(prefix) {
+            rest = stripped;
+            scheme = matched;
+            break;
+        }
+    }
+
+    // 读取字面量主机名，遇到正则元字符时停止
+    let mut host = String::new();
+    let mut chars = rest.char_indices().peekable();
+    let mut host_end = rest.len();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&(_, escaped)) if escaped == '.' || escaped == '-' => {
+                    host.push(escaped);
+                    chars.next();
+                }
+                _ => {
+                    host_end = index;
+                    break;
+                }
+            },
+            c if c.is_ascii_alphanumeric() || c == '-' => host.push(c),
+            _ => {
+                host_end = index;
+                break;
+            }
+        }
+    }
+    let rest = &rest[host_end..];
+    if host.is_empty() {
+        return None;
+    }
+
+    // 主机名之后紧跟端口、路径或结尾时，主机名是完整的；否则只是前缀
+    let exact = rest.starts_with(':')
+        || rest.starts_with('/')
+        || rest.starts_with("\\/")
+        || rest.starts_with('$');
+
+    // 协议的默认端口只在主机名之后没有端口时使用；端口不是字面量（例如 :\d+）时不限制端口
+    let port = match rest.strip_prefix(':') {
+        Some(after) => after
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>()
+            .parse::<u16>()
+            .ok(),
+        None => scheme,
+    };
+
+    let host_term = if host.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        ipv4_term(&host, exact)?
+    } else if exact {
+        format!("host {}", host)
+    } else {
+        // 主机名前缀无法用 BPF 表达
+        return None;
+    };
+
+    match port {
+        Some(port) => Some(format!("tcp port {} and {}", port, host_term)),
+        None => Some(format!("tcp and {}", host_term)),
+    }
+}
+
+/// IPv4 地址或地址前缀转换为 host/net 条件
+fn ipv4_term(literal: &str, exact: bool) -> Option<String> {
+    let parts: Vec<&str> = literal.split('.').collect();
+    if parts.len() > 4 || parts.iter().any(|part| part.len() > 3) {
+        return None;
+    }
+
+    // 完整的四段地址，且最后一段不能再延长（精确匹配或已有三位数字）
+    if parts.len() == 4 && (exact || parts[3].len() == 3) {
+        let ip: Ipv4Addr = literal.parse().ok()?;
+        return Some(format!("host {}", ip));
+    }
+
+    // 前缀：最后一段可能还有后续数字，只有前面的完整段可以确定
+    let complete = if exact { parts.len() } else { parts.len() - 1 };
+    let octets = parts[..complete]
+        .iter()
+        .map(|part| part.parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()?;
+    if octets.is_empty() {
+        return None;
+    }
+
+    let mut network = [0u8; 4];
+    network[..octets.len()].copy_from_slice(&octets);
+    Some(format!("net {}/{}", Ipv4Addr::from(network), octets.len() * 8))
+}
+
+/// 将主机配置转换为 BPF 条件，网段使用 net，其余使用 host
+fn host_term(host: &str) -> Result<String> {
+    // 只允许地址、网段和主机名中出现的字符，避免拼接出意外的表达式
+    let valid = host
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | ':' | '-' | '_' | '/'));
+    if !valid {
+        return Err(anyhow!("主机格式无效: {}", host));
+    }
+
+    if host.contains('/') {
+        Ok(format!("net {}", host))
+    } else {
+        Ok(format!("host {}", host))
+    }
+}
+
+/// 编译过滤表达式以检查语法是否正确
+pub fn validate_bpf(filter: &str) -> Result<()> {
+    let cap = Capture::dead(Linktype::ETHERNET).map_err(|e| anyhow!("创建过滤器校验句柄失败: {}", e))?;
+    cap.compile(filter, true)
+        .map_err(|e| anyhow!("过滤表达式无效 \"{}\": {}", filter, e))?;
+    Ok(())
+}
+
+/// 获取当前的过滤器配置
+pub fn get_filter_config() -> CaptureFilterConfig {
+    settings::get_settings().filter
+}
+
+/// 校验并保存过滤器配置，返回生成的过滤表达式
+pub fn set_filter_config(config: CaptureFilterConfig) -> Result<String> {
+    let filter = config.to_bpf(&system_url_patterns())?;
+    validate_bpf(&filter)?;
+
+    settings::update_settings(|s| s.filter = config)?;
+    info!("捕获过滤器已更新: {}", filter);
+    notify_filter_changed();
+    Ok(filter)
+}
+
+/// 当前生效的过滤表达式（包括 TCP 的 IP 分片）
+pub fn current_bpf() -> Result<String> {
+    let filter = get_filter_config().to_bpf(&system_url_patterns())?;
+    Ok(format!("({}) or {}", filter, TCP_FRAGMENT_BPF))
+}
+
+/// 已注册认证系统的 URL 匹配模式
+fn system_url_patterns() -> Vec<String> {
+    crate::auth::manager::get_system_url_patterns()
+        .into_iter()
+        .map(|(_, pattern)| pattern)
+        .collect()
+}
+
+/// 通知捕获线程重新生成并设置过滤器（配置或认证系统变化时调用）
+pub fn notify_filter_changed() {
+    FILTER_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 当前过滤器版本号
+pub fn filter_generation() -> u64 {
+    FILTER_GENERATION.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_with_literal_host_and_port() {
+        assert_eq!(
+            pattern_to_bpf(r"^https://10\.1\.2\.3:8443/api/.*").as_deref(),
+            Some("tcp port 8443 and host 10.1.2.3")
+        );
+        assert_eq!(
+            pattern_to_bpf(r"https://oa\.example\.com/.*").as_deref(),
+            Some("tcp port 443 and host oa.example.com")
+        );
+        assert_eq!(
+            pattern_to_bpf(r"http:\/\/oa\.example\.com$").as_deref(),
+            Some("tcp port 80 and host oa.example.com")
+        );
+        assert_eq!(
+            pattern_to_bpf(r"https?://oa\.example\.com/.*").as_deref(),
+            Some("tcp and host oa.example.com")
+        );
+    }
+
+    #[test]
+    fn pattern_with_non_literal_port_does_not_use_scheme_default() {
+        assert_eq!(
+            pattern_to_bpf(r"https://oa\.example\.com:\d+/.*").as_deref(),
+            Some("tcp and host oa.example.com")
+        );
+        assert_eq!(
+            pattern_to_bpf(r"https://10\.1\.2\.3:[0-9]+/api").as_deref(),
+            Some("tcp and host 10.1.2.3")
+        );
+    }
+
+    #[test]
+    fn pattern_with_address_prefix() {
+        assert_eq!(
+            pattern_to_bpf(r"192\.168\.91\.1.*").as_deref(),
+            Some("tcp and net 192.168.91.0/24")
+        );
+        assert_eq!(
+            pattern_to_bpf(r"10\.0\.0\.100.*").as_deref(),
+            Some("tcp and host 10.0.0.100")
+        );
+        assert_eq!(
+            pattern_to_bpf(r"https?://10\.1\..*").as_deref(),
+            Some("tcp and net 10.1.0.0/16")
+        );
+    }
+
+    #[test]
+    fn pattern_without_literal_host() {
+        assert_eq!(pattern_to_bpf(r"https?://[^/]*business[^/]*\..*?/api/.*"), None);
+        assert_eq!(pattern_to_bpf(r"https?://.*"), None);
+        assert_eq!(pattern_to_bpf(r"https://oa\.example.*"), None);
+    }
+
+    #[test]
+    fn default_config_derives_filter_from_systems() {
+        let patterns = vec![r"https://10\.1\.2\.3:8443/api/.*".to_string()];
+        assert_eq!(
+            CaptureFilterConfig::default().to_bpf(&patterns).unwrap(),
+            "tcp port 8443 and host 10.1.2.3"
+        );
+
+        // 旧版本保存的配置没有该字段
+        let saved: CaptureFilterConfig = serde_json::from_str(r#"{"ports":[80]}"#).unwrap();
+        assert!(saved.auto_from_systems);
+    }
+
+    #[test]
+    fn unresolvable_host_falls_back_to_ports() {
+        let patterns = vec![
+            r"https://oa\.example\.invalid/.*".to_string(),
+            r"https://10\.1\.2\.3:8443/api/.*".to_string(),
+        ];
+        assert_eq!(
+            CaptureFilterConfig::default().to_bpf(&patterns).unwrap(),
+            "(tcp port 80 or tcp port 8080 or tcp port 443) or (tcp port 8443 and host 10.1.2.3)"
+        );
+    }
+
+    #[test]
+    fn base_bpf_combines_ports_and_hosts() {
+        let config = CaptureFilterConfig {
+            ports: vec![80, 8080],
+            hosts: vec!["10.0.0.1".to_string(), "10.1.0.0/16".to_string()],
+            ..CaptureFilterConfig::default()
+        };
+        assert_eq!(
+            config.base_bpf().unwrap(),
+            "(tcp port 80 or tcp port 8080) and (host 10.0.0.1 or net 10.1.0.0/16)"
+        );
+
+        let invalid = CaptureFilterConfig {
+            hosts: vec!["10.0.0.1 or port 22".to_string()],
+            ..CaptureFilterConfig::default()
+        };
+        assert!(invalid.base_bpf().is_err());
+    }
+}
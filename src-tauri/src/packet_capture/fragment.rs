@@ -0,0 +1,543 @@
+use log::{debug, warn};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::{Duration, Instant};
+
+/// 分片重组超时时间（与 Linux 默认的 ipfrag_time 相同）
+const FRAGMENT_TIMEOUT: Duration = Duration::from_secs(30);
+/// 所有未完成的数据报最多缓存的分片字节数
+const MAX_FRAGMENT_BYTES: usize = 4 * 1024 * 1024;
+/// 最多同时重组的数据报数量
+const MAX_DATAGRAMS: usize = 1024;
+/// 重组后 IP 载荷的最大长度
+const MAX_PAYLOAD_LEN: usize = 65535;
+/// 清理超时数据报的间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(5);
+
+const IPV6_HEADER_LEN: usize = 40;
+const IPV6_HOP_BY_HOP: u8 = 0;
+const IPV6_ROUTING: u8 = 43;
+const IPV6_FRAGMENT: u8 = 44;
+const IPV6_AUTH: u8 = 51;
+const IPV6_DEST_OPTIONS: u8 = 60;
+
+/// 同一数据报的分片共享的标识
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct DatagramKey {
+    src_ip: IpAddr,
+    dst_ip: IpAddr,
+    protocol: u8,
+    identification: u32,
+}
+
+/// 从数据包中读取的分片信息
+struct Fragment<'a> {
+    key: DatagramKey,
+    /// 载荷在原数据报中的偏移量（字节）
+    offset: usize,
+    more_fragments: bool,
+    /// 不可分片部分（IPv4 首部，或 IPv6 首部及分片首部之前的扩展首部）
+    header: &'a [u8],
+    /// IPv6 中指向分片首部的 Next Header 字段在 header 中的位置
+    next_header_pos: Option<usize>,
+    payload: &'a [u8],
+}
+
+/// 正在重组的数据报
+#[derive(Debug)]
+struct Datagram {
+    /// 偏移量为 0 的分片的不可分片部分，重组后的首部以它为准
+    header: Option<Vec<u8>>,
+    next_header_pos: Option<usize>,
+    /// 按偏移量排序的分片载荷
+    fragments: BTreeMap<usize, Vec<u8>>,
+    bytes: usize,
+    /// 收到最后一个分片后确定的载荷总长度
+    payload_len: Option<usize>,
+    first_seen: Instant,
+}
+
+impl Datagram {
+    fn new() -> Self {
+        Self {
+            header: None,
+            next_header_pos: None,
+            fragments: BTreeMap::new(),
+            bytes: 0,
+            payload_len: None,
+            first_seen: Instant::now(),
+        }
+    }
+
+    /// 加入一个分片，与已有分片重叠（重复的分片除外）或长度矛盾时返回 None
+    fn insert(&mut self, fragment: &Fragment) -> Option<()> {
+        let start = fragment.offset;
+        let end = start + fragment.payload.len();
+        if let Some(payload_len) = self.payload_len {
+            if end > payload_len || (!fragment.more_fragments && end != payload_len) {
+                return None;
+            }
+        }
+        if !fragment.more_fragments {
+            if self.fragments.range(end..).next().is_some()
+                || self
+                    .fragments
+                    .iter()
+                    .next_back()
+                    .is_some_and(|(offset, data)| offset + data.len() > end)
+            {
+                return None;
+            }
+            self.payload_len = Some(end);
+        }
+
+        if let Some((offset, data)) = self.fragments.range(..=start).next_back() {
+            if *offset == start && data.as_slice() == fragment.payload {
+                // 重传或在多个网卡上重复捕获的分片
+                return Some(());
+            }
+            if offset + data.len() > start {
+                return None;
+            }
+        }
+        if self.fragments.range(start..end).next().is_some() {
+            return None;
+        }
+
+        if start == 0 {
+            self.header = Some(fragment.header.to_vec());
+            self.next_header_pos = fragment.next_header_pos;
+        }
+        self.fragments.insert(start, fragment.payload.to_vec());
+        self.bytes += fragment.payload.len();
+        Some(())
+    }
+
+    /// 所有分片都已收到时返回重组后的数据包
+    fn assemble(&self, protocol: u8) -> Option<Vec<u8>> {
+        let payload_len = self.payload_len?;
+        let header = self.header.as_ref()?;
+        let mut expected = 0;
+        for (offset, data) in &self.fragments {
+            if *offset != expected {
+                return None;
+            }
+            expected += data.len();
+        }
+        if expected != payload_len {
+            return None;
+        }
+
+        let mut packet = Vec::with_capacity(header.len() + payload_len);
+        packet.extend_from_slice(header);
+        for data in self.fragments.values() {
+            packet.extend_from_slice(data);
+        }
+        rewrite_header(&mut packet, header.len(), self.next_header_pos, protocol)?;
+        Some(packet)
+    }
+}
+
+/// IPv4/IPv6 分片重组器
+#[derive(Debug)]
+pub struct IpDefragmenter {
+    datagrams: HashMap<DatagramKey, Datagram>,
+    bytes: usize,
+    last_cleanup: Instant,
+}
+
+impl Default for IpDefragmenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpDefragmenter {
+    pub fn new() -> Self {
+        Self {
+            datagrams: HashMap::new(),
+            bytes: 0,
+            last_cleanup: Instant::now(),
+        }
+    }
+
+    /// 处理一个 IP 数据包（从 IP 首部开始）
+    /// 未分片的数据包原样返回；分片在数据报收全时返回重组后的数据包，否则返回 None
+    pub fn process<'a>(&mut self, packet: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        if self.last_cleanup.elapsed() >= CLEANUP_INTERVAL {
+            self.cleanup();
+        }
+
+        let fragment = match parse_fragment(packet) {
+            Some(fragment) => fragment,
+            None => return Some(Cow::Borrowed(packet)),
+        };
+        let end = fragment.offset + fragment.payload.len();
+        // 除最后一个分片外，分片载荷长度必须是 8 的倍数
+        if end > MAX_PAYLOAD_LEN
+            || (fragment.more_fragments
+                && (fragment.payload.is_empty() || fragment.payload.len() % 8 != 0))
+        {
+            debug!("丢弃无效的 IP 分片: {:?}", fragment.key);
+            self.remove(&fragment.key);
+            return None;
+        }
+
+        // 偏移量为 0 且没有后续分片（IPv6 原子分片），无需等待
+        if fragment.offset == 0 && !fragment.more_fragments {
+            let mut datagram = Datagram::new();
+            datagram.insert(&fragment)?;
+            return datagram.assemble(fragment.key.protocol).map(Cow::Owned);
+        }
+
+        if !self.datagrams.contains_key(&fragment.key) && self.datagrams.len() >= MAX_DATAGRAMS {
+            warn!(
+                "重组中的 IP 数据报数量达到上限 {}，淘汰最早的数据报",
+                MAX_DATAGRAMS
+            );
+            self.evict_oldest();
+        }
+        while self.bytes + fragment.payload.len() > MAX_FRAGMENT_BYTES && !self.datagrams.is_empty()
+        {
+            warn!(
+                "缓存的 IP 分片超过 {} 字节，淘汰最早的数据报",
+                MAX_FRAGMENT_BYTES
+            );
+            self.evict_oldest();
+        }
+
+        let datagram = self
+            .datagrams
+            .entry(fragment.key)
+            .or_insert_with(Datagram::new);
+        let before = datagram.bytes;
+        if datagram.insert(&fragment).is_none() {
+            debug!("IP 分片重叠或长度不一致，丢弃数据报: {:?}", fragment.key);
+            self.remove(&fragment.key);
+            return None;
+        }
+        self.bytes += datagram.bytes - before;
+
+        let packet = datagram.assemble(fragment.key.protocol)?;
+        self.remove(&fragment.key);
+        debug!(
+            "重组 IP 数据报: {} -> {}，共 {} 字节",
+            fragment.key.src_ip,
+            fragment.key.dst_ip,
+            packet.len()
+        );
+        Some(Cow::Owned(packet))
+    }
+
+    /// 当前正在重组的数据报数量
+    pub fn datagram_count(&self) -> usize {
+        self.datagrams.len()
+    }
+
+    /// 清理超时未收全的数据报
+    pub fn cleanup(&mut self) {
+        let before = self.datagrams.len();
+        self.datagrams
+            .retain(|_, datagram| datagram.first_seen.elapsed() < FRAGMENT_TIMEOUT);
+        self.bytes = self.datagrams.values().map(|datagram| datagram.bytes).sum();
+        self.last_cleanup = Instant::now();
+
+        let removed = before - self.datagrams.len();
+        if removed > 0 {
+            debug!(
+                "清理了 {} 个超时的 IP 数据报，剩余 {}",
+                removed,
+                self.datagrams.len()
+            );
+        }
+    }
+
+    fn remove(&mut self, key: &DatagramKey) {
+        if let Some(datagram) = self.datagrams.remove(key) {
+            self.bytes -= datagram.bytes;
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .datagrams
+            .iter()
+            .min_by_key(|(_, datagram)| datagram.first_seen)
+            .map(|(key, _)| *key);
+        if let Some(key) = oldest {
+            self.remove(&key);
+        }
+    }
+}
+
+/// 读取数据包的分片信息，不是分片（或无法解析）时返回 None
+fn parse_fragment(packet: &[u8]) -> Option<Fragment<'_>> {
+    match packet.first()? >> 4 {
+        4 => parse_ipv4_fragment(packet),
+        6 => parse_ipv6_fragment(packet),
+        _ => None,
+    }
+}
+
+fn parse_ipv4_fragment(packet: &[u8]) -> Option<Fragment<'_>> {
+    let header_len = usize::from(packet[0] & 0x0f) * 4;
+    let total_len = usize::from(u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]));
+    if header_len < 20 || total_len < header_len || packet.len() < total_len {
+        return None;
+    }
+    let flags = u16::from_be_bytes([packet[6], packet[7]]);
+    let more_fragments = flags & 0x2000 != 0;
+    let offset = usize::from(flags & 0x1fff) * 8;
+    if !more_fragments && offset == 0 {
+        return None;
+    }
+
+    let src_ip: [u8; 4] = packet[12..16].try_into().ok()?;
+    let dst_ip: [u8; 4] = packet[16..20].try_into().ok()?;
+    Some(Fragment {
+        key: DatagramKey {
+            src_ip: IpAddr::V4(Ipv4Addr::from(src_ip)),
+            dst_ip: IpAddr::V4(Ipv4Addr::from(dst_ip)),
+            protocol: packet[9],
+            identification: u32::from(u16::from_be_bytes([packet[4], packet[5]])),
+        },
+        offset,
+        more_fragments,
+        header: &packet[..header_len],
+        next_header_pos: None,
+        // 以太网填充的字节不属于载荷
+        payload: &packet[header_len..total_len],
+    })
+}
+
+fn parse_ipv6_fragment(packet: &[u8]) -> Option<Fragment<'_>> {
+    if packet.len() < IPV6_HEADER_LEN {
+        return None;
+    }
+    let payload_len = usize::from(u16::from_be_bytes([packet[4], packet[5]]));
+    let end = IPV6_HEADER_LEN + payload_len;
+    if payload_len == 0 || packet.len() < end {
+        return None;
+    }
+
+    // 跳过分片首部之前的扩展首部
+    let mut next_header = packet[6];
+    let mut next_header_pos = 6;
+    let mut pos = IPV6_HEADER_LEN;
+    loop {
+        let len = match next_header {
+            IPV6_HOP_BY_HOP | IPV6_ROUTING | IPV6_DEST_OPTIONS => {
+                (usize::from(*packet.get(pos + 1)?) + 1) * 8
+            }
+            IPV6_AUTH => (usize::from(*packet.get(pos + 1)?) + 2) * 4,
+            IPV6_FRAGMENT => break,
+            _ => return None,
+        };
+        if pos + len > end {
+            return None;
+        }
+        next_header = packet[pos];
+        next_header_pos = pos;
+        pos += len;
+    }
+
+    let fragment_header = packet.get(pos..pos + 8)?;
+    if pos + 8 > end {
+        return None;
+    }
+    let flags = u16::from_be_bytes([fragment_header[2], fragment_header[3]]);
+    let src_ip: [u8; 16] = packet[8..24].try_into().ok()?;
+    let dst_ip: [u8; 16] = packet[24..40].try_into().ok()?;
+    Some(Fragment {
+        key: DatagramKey {
+            src_ip: IpAddr::V6(Ipv6Addr::from(src_ip)),
+            dst_ip: IpAddr::V6(Ipv6Addr::from(dst_ip)),
+            protocol: fragment_header[0],
+            identification: u32::from_be_bytes([
+                fragment_header[4],
+                fragment_header[5],
+                fragment_header[6],
+                fragment_header[7],
+            ]),
+        },
+        offset: usize::from(flags & 0xfff8),
+        more_fragments: flags & 0x1 != 0,
+        header: &packet[..pos],
+        next_header_pos: Some(next_header_pos),
+        payload: &packet[pos + 8..end],
+    })
+}
+
+/// 把重组后数据包的首部改为未分片的形式：清除分片字段并更新长度
+/// 长度超出首部字段的范围时返回 None
+fn rewrite_header(
+    packet: &mut [u8],
+    header_len: usize,
+    next_header_pos: Option<usize>,
+    protocol: u8,
+) -> Option<()> {
+    match next_header_pos {
+        // IPv6：去掉分片首部后由前一个首部指向上层协议
+        Some(pos) => {
+            packet[pos] = protocol;
+            let payload_len = u16::try_from(packet.len() - IPV6_HEADER_LEN).ok()?;
+            packet[4..6].copy_from_slice(&payload_len.to_be_bytes());
+        }
+        None => {
+            let total_len = u16::try_from(packet.len()).ok()?;
+            packet[2..4].copy_from_slice(&total_len.to_be_bytes());
+            // 保留 DF 标志，清除 MF 和片偏移
+            packet[6] &= 0x40;
+            packet[7] = 0;
+            packet[10..12].copy_from_slice(&[0, 0]);
+            let checksum = ipv4_checksum(&packet[..header_len]);
+            packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        }
+    }
+    Some(())
+}
+
+/// 计算 IPv4 首部校验和
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|word| u32::from(u16::from_be_bytes([word[0], *word.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UDP: u8 = 17;
+
+    fn ipv4_fragment(id: u16, offset: usize, more: bool, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0u8; 20];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&((20 + payload.len()) as u16).to_be_bytes());
+        packet[4..6].copy_from_slice(&id.to_be_bytes());
+        let flags = (offset / 8) as u16 | if more { 0x2000 } else { 0 };
+        packet[6..8].copy_from_slice(&flags.to_be_bytes());
+        packet[8] = 64;
+        packet[9] = UDP;
+        packet[12..16].copy_from_slice(&[192, 168, 0, 1]);
+        packet[16..20].copy_from_slice(&[192, 168, 0, 2]);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn ipv6_fragment(id: u32, offset: usize, more: bool, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0u8; IPV6_HEADER_LEN];
+        packet[0] = 0x60;
+        packet[4..6].copy_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        packet[6] = IPV6_FRAGMENT;
+        packet[7] = 64;
+        packet[8..24].copy_from_slice(&[0xfe; 16]);
+        packet[24..40].copy_from_slice(&[0xfd; 16]);
+        let flags = offset as u16 | u16::from(more);
+        packet.extend_from_slice(&[UDP, 0]);
+        packet.extend_from_slice(&flags.to_be_bytes());
+        packet.extend_from_slice(&id.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn unfragmented_packet_passes_through() {
+        let mut defragmenter = IpDefragmenter::new();
+        let packet = ipv4_fragment(1, 0, false, b"data");
+        assert!(matches!(
+            defragmenter.process(&packet),
+            Some(Cow::Borrowed(data)) if data == packet.as_slice()
+        ));
+    }
+
+    #[test]
+    fn ipv4_fragments_out_of_order() {
+        let mut defragmenter = IpDefragmenter::new();
+        let payload: Vec<u8> = (0..20).collect();
+        assert!(defragmenter
+            .process(&ipv4_fragment(7, 16, false, &payload[16..]))
+            .is_none());
+        assert!(defragmenter
+            .process(&ipv4_fragment(7, 8, true, &payload[8..16]))
+            .is_none());
+        assert_eq!(defragmenter.datagram_count(), 1);
+
+        let packet = defragmenter
+            .process(&ipv4_fragment(7, 0, true, &payload[..8]))
+            .map(Cow::into_owned)
+            .unwrap();
+        assert_eq!(defragmenter.datagram_count(), 0);
+        assert_eq!(&packet[20..], payload.as_slice());
+        assert_eq!(u16::from_be_bytes([packet[2], packet[3]]), 40);
+        assert_eq!(&packet[6..8], &[0, 0]);
+        assert_eq!(ipv4_checksum(&packet[..20]), 0);
+    }
+
+    #[test]
+    fn duplicate_fragment_ignored() {
+        let mut defragmenter = IpDefragmenter::new();
+        let first = ipv4_fragment(3, 0, true, &[1; 8]);
+        assert!(defragmenter.process(&first).is_none());
+        assert!(defragmenter.process(&first).is_none());
+        let packet = defragmenter
+            .process(&ipv4_fragment(3, 8, false, &[2; 4]))
+            .map(Cow::into_owned)
+            .unwrap();
+        assert_eq!(packet.len(), 32);
+    }
+
+    #[test]
+    fn overlapping_fragments_drop_datagram() {
+        let mut defragmenter = IpDefragmenter::new();
+        assert!(defragmenter
+            .process(&ipv4_fragment(9, 0, true, &[1; 16]))
+            .is_none());
+        assert!(defragmenter
+            .process(&ipv4_fragment(9, 8, false, &[2; 16]))
+            .is_none());
+        assert_eq!(defragmenter.datagram_count(), 0);
+    }
+
+    #[test]
+    fn invalid_fragment_length_rejected() {
+        let mut defragmenter = IpDefragmenter::new();
+        assert!(defragmenter
+            .process(&ipv4_fragment(4, 0, true, &[1; 5]))
+            .is_none());
+        assert_eq!(defragmenter.datagram_count(), 0);
+    }
+
+    #[test]
+    fn ipv6_fragments_reassembled() {
+        let mut defragmenter = IpDefragmenter::new();
+        assert!(defragmenter
+            .process(&ipv6_fragment(0x1234, 8, false, b"tail"))
+            .is_none());
+        let packet = defragmenter
+            .process(&ipv6_fragment(0x1234, 0, true, b"head1234"))
+            .map(Cow::into_owned)
+            .unwrap();
+        // 分片首部被去掉，Next Header 指向上层协议
+        assert_eq!(packet[6], UDP);
+        assert_eq!(u16::from_be_bytes([packet[4], packet[5]]), 12);
+        assert_eq!(&packet[IPV6_HEADER_LEN..], b"head1234tail");
+    }
+
+    #[test]
+    fn ipv6_atomic_fragment() {
+        let mut defragmenter = IpDefragmenter::new();
+        let packet = defragmenter
+            .process(&ipv6_fragment(1, 0, false, b"whole"))
+            .map(Cow::into_owned)
+            .unwrap();
+        assert_eq!(packet[6], UDP);
+        assert_eq!(&packet[IPV6_HEADER_LEN..], b"whole");
+    }
+}
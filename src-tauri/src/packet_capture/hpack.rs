@@ -0,0 +1,764 @@
+use once_cell::sync::OnceCell;
+use std::collections::VecDeque;
+
+/// 动态表的初始大小（SETTINGS_HEADER_TABLE_SIZE 默认值）
+const DEFAULT_TABLE_SIZE: usize = 4096;
+/// 允许的最大动态表大小，防止异常数据占用过多内存
+const MAX_TABLE_SIZE: usize = 1024 * 1024;
+/// 每个动态表条目的额外开销（RFC 7541 第 4.1 节）
+const ENTRY_OVERHEAD: usize = 32;
+/// Huffman 编码中的结束符
+const EOS: u16 = 256;
+/// 最长的 Huffman 码字长度
+const MAX_CODE_LEN: usize = 30;
+
+/// 消息头字段（名称和值），名称为小写
+pub type HeaderField = (Vec<u8>, Vec<u8>);
+
+/// 静态表（RFC 7541 附录 A），索引从 1 开始
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Huffman 编码表（RFC 7541 附录 B），按符号排列的码字和码长
+const HUFFMAN_CODES: [(u32, u8); 257] = [
+    (0x1ff8, 13),
+    (0x7fffd8, 23),
+    (0xfffffe2, 28),
+    (0xfffffe3, 28),
+    (0xfffffe4, 28),
+    (0xfffffe5, 28),
+    (0xfffffe6, 28),
+    (0xfffffe7, 28),
+    (0xfffffe8, 28),
+    (0xffffea, 24),
+    (0x3ffffffc, 30),
+    (0xfffffe9, 28),
+    (0xfffffea, 28),
+    (0x3ffffffd, 30),
+    (0xfffffeb, 28),
+    (0xfffffec, 28),
+    (0xfffffed, 28),
+    (0xfffffee, 28),
+    (0xfffffef, 28),
+    (0xffffff0, 28),
+    (0xffffff1, 28),
+    (0xffffff2, 28),
+    (0x3ffffffe, 30),
+    (0xffffff3, 28),
+    (0xffffff4, 28),
+    (0xffffff5, 28),
+    (0xffffff6, 28),
+    (0xffffff7, 28),
+    (0xffffff8, 28),
+    (0xffffff9, 28),
+    (0xffffffa, 28),
+    (0xffffffb, 28),
+    (0x14, 6),
+    (0x3f8, 10),
+    (0x3f9, 10),
+    (0xffa, 12),
+    (0x1ff9, 13),
+    (0x15, 6),
+    (0xf8, 8),
+    (0x7fa, 11),
+    (0x3fa, 10),
+    (0x3fb, 10),
+    (0xf9, 8),
+    (0x7fb, 11),
+    (0xfa, 8),
+    (0x16, 6),
+    (0x17, 6),
+    (0x18, 6),
+    (0x0, 5),
+    (0x1, 5),
+    (0x2, 5),
+    (0x19, 6),
+    (0x1a, 6),
+    (0x1b, 6),
+    (0x1c, 6),
+    (0x1d, 6),
+    (0x1e, 6),
+    (0x1f, 6),
+    (0x5c, 7),
+    (0xfb, 8),
+    (0x7ffc, 15),
+    (0x20, 6),
+    (0xffb, 12),
+    (0x3fc, 10),
+    (0x1ffa, 13),
+    (0x21, 6),
+    (0x5d, 7),
+    (0x5e, 7),
+    (0x5f, 7),
+    (0x60, 7),
+    (0x61, 7),
+    (0x62, 7),
+    (0x63, 7),
+    (0x64, 7),
+    (0x65, 7),
+    (0x66, 7),
+    (0x67, 7),
+    (0x68, 7),
+    (0x69, 7),
+    (0x6a, 7),
+    (0x6b, 7),
+    (0x6c, 7),
+    (0x6d, 7),
+    (0x6e, 7),
+    (0x6f, 7),
+    (0x70, 7),
+    (0x71, 7),
+    (0x72, 7),
+    (0xfc, 8),
+    (0x73, 7),
+    (0xfd, 8),
+    (0x1ffb, 13),
+    (0x7fff0, 19),
+    (0x1ffc, 13),
+    (0x3ffc, 14),
+    (0x22, 6),
+    (0x7ffd, 15),
+    (0x3, 5),
+    (0x23, 6),
+    (0x4, 5),
+    (0x24, 6),
+    (0x5, 5),
+    (0x25, 6),
+    (0x26, 6),
+    (0x27, 6),
+    (0x6, 5),
+    (0x74, 7),
+    (0x75, 7),
+    (0x28, 6),
+    (0x29, 6),
+    (0x2a, 6),
+    (0x7, 5),
+    (0x2b, 6),
+    (0x76, 7),
+    (0x2c, 6),
+    (0x8, 5),
+    (0x9, 5),
+    (0x2d, 6),
+    (0x77, 7),
+    (0x78, 7),
+    (0x79, 7),
+    (0x7a, 7),
+    (0x7b, 7),
+    (0x7ffe, 15),
+    (0x7fc, 11),
+    (0x3ffd, 14),
+    (0x1ffd, 13),
+    (0xffffffc, 28),
+    (0xfffe6, 20),
+    (0x3fffd2, 22),
+    (0xfffe7, 20),
+    (0xfffe8, 20),
+    (0x3fffd3, 22),
+    (0x3fffd4, 22),
+    (0x3fffd5, 22),
+    (0x7fffd9, 23),
+    (0x3fffd6, 22),
+    (0x7fffda, 23),
+    (0x7fffdb, 23),
+    (0x7fffdc, 23),
+    (0x7fffdd, 23),
+    (0x7fffde, 23),
+    (0xffffeb, 24),
+    (0x7fffdf, 23),
+    (0xffffec, 24),
+    (0xffffed, 24),
+    (0x3fffd7, 22),
+    (0x7fffe0, 23),
+    (0xffffee, 24),
+    (0x7fffe1, 23),
+    (0x7fffe2, 23),
+    (0x7fffe3, 23),
+    (0x7fffe4, 23),
+    (0x1fffdc, 21),
+    (0x3fffd8, 22),
+    (0x7fffe5, 23),
+    (0x3fffd9, 22),
+    (0x7fffe6, 23),
+    (0x7fffe7, 23),
+    (0xffffef, 24),
+    (0x3fffda, 22),
+    (0x1fffdd, 21),
+    (0xfffe9, 20),
+    (0x3fffdb, 22),
+    (0x3fffdc, 22),
+    (0x7fffe8, 23),
+    (0x7fffe9, 23),
+    (0x1fffde, 21),
+    (0x7fffea, 23),
+    (0x3fffdd, 22),
+    (0x3fffde, 22),
+    (0xfffff0, 24),
+    (0x1fffdf, 21),
+    (0x3fffdf, 22),
+    (0x7fffeb, 23),
+    (0x7fffec, 23),
+    (0x1fffe0, 21),
+    (0x1fffe1, 21),
+    (0x3fffe0, 22),
+    (0x1fffe2, 21),
+    (0x7fffed, 23),
+    (0x3fffe1, 22),
+    (0x7fffee, 23),
+    (0x7fffef, 23),
+    (0xfffea, 20),
+    (0x3fffe2, 22),
+    (0x3fffe3, 22),
+    (0x3fffe4, 22),
+    (0x7ffff0, 23),
+    (0x3fffe5, 22),
+    (0x3fffe6, 22),
+    (0x7ffff1, 23),
+    (0x3ffffe0, 26),
+    (0x3ffffe1, 26),
+    (0xfffeb, 20),
+    (0x7fff1, 19),
+    (0x3fffe7, 22),
+    (0x7ffff2, 23),
+    (0x3fffe8, 22),
+    (0x1ffffec, 25),
+    (0x3ffffe2, 26),
+    (0x3ffffe3, 26),
+    (0x3ffffe4, 26),
+    (0x7ffffde, 27),
+    (0x7ffffdf, 27),
+    (0x3ffffe5, 26),
+    (0xfffff1, 24),
+    (0x1ffffed, 25),
+    (0x7fff2, 19),
+    (0x1fffe3, 21),
+    (0x3ffffe6, 26),
+    (0x7ffffe0, 27),
+    (0x7ffffe1, 27),
+    (0x3ffffe7, 26),
+    (0x7ffffe2, 27),
+    (0xfffff2, 24),
+    (0x1fffe4, 21),
+    (0x1fffe5, 21),
+    (0x3ffffe8, 26),
+    (0x3ffffe9, 26),
+    (0xffffffd, 28),
+    (0x7ffffe3, 27),
+    (0x7ffffe4, 27),
+    (0x7ffffe5, 27),
+    (0xfffec, 20),
+    (0xfffff3, 24),
+    (0xfffed, 20),
+    (0x1fffe6, 21),
+    (0x3fffe9, 22),
+    (0x1fffe7, 21),
+    (0x1fffe8, 21),
+    (0x7ffff3, 23),
+    (0x3fffea, 22),
+    (0x3fffeb, 22),
+    (0x1ffffee, 25),
+    (0x1ffffef, 25),
+    (0xfffff4, 24),
+    (0xfffff5, 24),
+    (0x3ffffea, 26),
+    (0x7ffff4, 23),
+    (0x3ffffeb, 26),
+    (0x7ffffe6, 27),
+    (0x3ffffec, 26),
+    (0x3ffffed, 26),
+    (0x7ffffe7, 27),
+    (0x7ffffe8, 27),
+    (0x7ffffe9, 27),
+    (0x7ffffea, 27),
+    (0x7ffffeb, 27),
+    (0xffffffe, 28),
+    (0x7ffffec, 27),
+    (0x7ffffed, 27),
+    (0x7ffffee, 27),
+    (0x7ffffef, 27),
+    (0x7fffff0, 27),
+    (0x3ffffee, 26),
+    (0x3fffffff, 30),
+];
+
+// Huffman 解码表（首次使用时生成）
+static HUFFMAN_TABLE: OnceCell<HuffmanTable> = OnceCell::new();
+
+/// 范式 Huffman 码的解码表：HPACK 的码字按码长和符号顺序依次分配
+struct HuffmanTable {
+    /// 各码长的码字数量
+    counts: [u32; MAX_CODE_LEN + 1],
+    /// 按码长和符号排序的符号
+    symbols: Vec<u16>,
+}
+
+fn huffman_table() -> &'static HuffmanTable {
+    HUFFMAN_TABLE.get_or_init(|| {
+        let mut counts = [0u32; MAX_CODE_LEN + 1];
+        for &(_, len) in HUFFMAN_CODES.iter() {
+            counts[len as usize] += 1;
+        }
+        let mut symbols: Vec<u16> = (0..=EOS).collect();
+        symbols.sort_by_key(|&symbol| (HUFFMAN_CODES[symbol as usize].1, symbol));
+        HuffmanTable { counts, symbols }
+    })
+}
+
+/// 解码 Huffman 编码的字符串，格式错误时返回 None
+pub fn decode_huffman(data: &[u8]) -> Option<Vec<u8>> {
+    let table = huffman_table();
+    let mut decoded = Vec::with_capacity(data.len() * 8 / 5);
+
+    // 逐位读取，code 为当前码长下已读取的码字，first 为该码长的第一个码字
+    let (mut code, mut first, mut index, mut len) = (0u32, 0u32, 0usize, 0usize);
+    for &byte in data {
+        for shift in (0..8).rev() {
+            code |= u32::from((byte >> shift) & 1);
+            len += 1;
+            let count = table.counts[len];
+            if code < first + count {
+                let symbol = table.symbols[index + (code - first) as usize];
+                if symbol == EOS {
+                    return None;
+                }
+                decoded.push(symbol as u8);
+                (code, first, index, len) = (0, 0, 0, 0);
+            } else {
+                if len == MAX_CODE_LEN {
+                    return None;
+                }
+                index += count as usize;
+                first = (first + count) << 1;
+                code <<= 1;
+            }
+        }
+    }
+
+    // 末尾的填充必须是不超过 7 位的结束符前缀（全为 1）
+    if len > 7 || (code >> 1) != (1 << len) - 1 {
+        return None;
+    }
+    Some(decoded)
+}
+
+/// HPACK 解码器，每个连接的每个方向各有一个（RFC 7541）
+#[derive(Debug)]
+pub struct HpackDecoder {
+    /// 动态表，最新插入的条目在最前面
+    table: VecDeque<HeaderField>,
+    size: usize,
+    max_size: usize,
+}
+
+impl Default for HpackDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HpackDecoder {
+    pub fn new() -> Self {
+        Self {
+            table: VecDeque::new(),
+            size: 0,
+            max_size: DEFAULT_TABLE_SIZE,
+        }
+    }
+
+    /// 解码一个完整的消息头块，格式错误时返回 None（此后动态表状态不再可信）
+    pub fn decode(&mut self, block: &[u8]) -> Option<Vec<HeaderField>> {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        while pos < block.len() {
+            let byte = block[pos];
+            if byte & 0x80 != 0 {
+                // 索引字段
+                let index = decode_integer(block, &mut pos, 7)?;
+                fields.push(self.get(index)?);
+            } else if byte & 0x40 != 0 {
+                // 带增量索引的字面字段
+                let field = self.decode_literal(block, &mut pos, 6)?;
+                self.insert(field.clone());
+                fields.push(field);
+            } else if byte & 0x20 != 0 {
+                // 动态表大小更新
+                let max_size = decode_integer(block, &mut pos, 5)?;
+                if max_size > MAX_TABLE_SIZE {
+                    return None;
+                }
+                self.max_size = max_size;
+                self.evict(0);
+            } else {
+                // 不索引或永不索引的字面字段
+                fields.push(self.decode_literal(block, &mut pos, 4)?);
+            }
+        }
+        Some(fields)
+    }
+
+    /// 解码字面字段，名称可以引用表中的条目
+    fn decode_literal(
+        &self,
+        block: &[u8],
+        pos: &mut usize,
+        prefix_bits: u32,
+    ) -> Option<HeaderField> {
+        let index = decode_integer(block, pos, prefix_bits)?;
+        let name = if index == 0 {
+            decode_string(block, pos)?
+        } else {
+            self.get(index)?.0
+        };
+        let value = decode_string(block, pos)?;
+        Some((name, value))
+    }
+
+    /// 按索引查找静态表或动态表中的条目
+    fn get(&self, index: usize) -> Option<HeaderField> {
+        match index {
+            0 => None,
+            1..=61 => {
+                let (name, value) = STATIC_TABLE[index - 1];
+                Some((name.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            }
+            _ => self.table.get(index - STATIC_TABLE.len() - 1).cloned(),
+        }
+    }
+
+    fn insert(&mut self, field: HeaderField) {
+        let entry_size = field.0.len() + field.1.len() + ENTRY_OVERHEAD;
+        // 条目超过表大小时清空动态表且不插入
+        if entry_size > self.max_size {
+            self.table.clear();
+            self.size = 0;
+            return;
+        }
+        self.evict(entry_size);
+        self.size += entry_size;
+        self.table.push_front(field);
+    }
+
+    /// 淘汰最早的条目，直到能容纳 incoming 字节的新条目
+    fn evict(&mut self, incoming: usize) {
+        while self.size + incoming > self.max_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.size -= name.len() + value.len() + ENTRY_OVERHEAD,
+                None => break,
+            }
+        }
+    }
+}
+
+/// 解码带前缀的整数（RFC 7541 第 5.1 节）
+fn decode_integer(data: &[u8], pos: &mut usize, prefix_bits: u32) -> Option<usize> {
+    let max_prefix = (1usize << prefix_bits) - 1;
+    let mut value = usize::from(*data.get(*pos)?) & max_prefix;
+    *pos += 1;
+    if value < max_prefix {
+        return Some(value);
+    }
+
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value = value.checked_add(usize::from(byte & 0x7f) << shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+        // 合法的长度和索引不会超过 28 位
+        if shift > 28 {
+            return None;
+        }
+    }
+}
+
+/// 解码字符串，最高位表示是否使用 Huffman 编码（RFC 7541 第 5.2 节）
+fn decode_string(data: &[u8], pos: &mut usize) -> Option<Vec<u8>> {
+    let huffman = data.get(*pos)? & 0x80 != 0;
+    let len = decode_integer(data, pos, 7)?;
+    let end = pos.checked_add(len)?;
+    let raw = data.get(*pos..end)?;
+    *pos = end;
+    if huffman {
+        decode_huffman(raw)
+    } else {
+        Some(raw.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 7541 附录 C 的示例
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<HeaderField> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    fn table(decoder: &HpackDecoder) -> Vec<HeaderField> {
+        decoder.table.iter().cloned().collect()
+    }
+
+    #[test]
+    fn integer_representation() {
+        // C.1.1 - C.1.3
+        let mut pos = 0;
+        assert_eq!(decode_integer(&[0x0a], &mut pos, 5), Some(10));
+        let mut pos = 0;
+        assert_eq!(decode_integer(&[0x1f, 0x9a, 0x0a], &mut pos, 5), Some(1337));
+        assert_eq!(pos, 3);
+        let mut pos = 0;
+        assert_eq!(decode_integer(&[0x2a], &mut pos, 8), Some(42));
+    }
+
+    fn check_requests(blocks: [&str; 3]) {
+        let mut decoder = HpackDecoder::new();
+
+        // C.3.1 / C.4.1
+        assert_eq!(
+            decoder.decode(&hex(blocks[0])),
+            Some(fields(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+            ]))
+        );
+        assert_eq!(
+            table(&decoder),
+            fields(&[(":authority", "www.example.com")])
+        );
+        assert_eq!(decoder.size, 57);
+
+        // C.3.2 / C.4.2
+        assert_eq!(
+            decoder.decode(&hex(blocks[1])),
+            Some(fields(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+                ("cache-control", "no-cache"),
+            ]))
+        );
+        assert_eq!(decoder.size, 110);
+
+        // C.3.3 / C.4.3
+        assert_eq!(
+            decoder.decode(&hex(blocks[2])),
+            Some(fields(&[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/index.html"),
+                (":authority", "www.example.com"),
+                ("custom-key", "custom-value"),
+            ]))
+        );
+        assert_eq!(
+            table(&decoder),
+            fields(&[
+                ("custom-key", "custom-value"),
+                ("cache-control", "no-cache"),
+                (":authority", "www.example.com"),
+            ])
+        );
+        assert_eq!(decoder.size, 164);
+    }
+
+    #[test]
+    fn requests_without_huffman() {
+        check_requests([
+            "8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+            "8286 84be 5808 6e6f 2d63 6163 6865",
+            "8287 85bf 400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65",
+        ]);
+    }
+
+    #[test]
+    fn requests_with_huffman() {
+        check_requests([
+            "8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff",
+            "8286 84be 5886 a8eb 1064 9cbf",
+            "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf",
+        ]);
+    }
+
+    fn check_responses(blocks: [&str; 3]) {
+        // 示例中的 SETTINGS_HEADER_TABLE_SIZE 为 256，需要淘汰条目
+        let mut decoder = HpackDecoder::new();
+        decoder.max_size = 256;
+
+        // C.5.1 / C.6.1
+        assert_eq!(
+            decoder.decode(&hex(blocks[0])),
+            Some(fields(&[
+                (":status", "302"),
+                ("cache-control", "private"),
+                ("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                ("location", "https://www.example.com"),
+            ]))
+        );
+        assert_eq!(decoder.size, 222);
+
+        // C.5.2 / C.6.2：":status 302" 被淘汰
+        assert_eq!(
+            decoder.decode(&hex(blocks[1])),
+            Some(fields(&[
+                (":status", "307"),
+                ("cache-control", "private"),
+                ("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                ("location", "https://www.example.com"),
+            ]))
+        );
+        assert_eq!(decoder.size, 222);
+
+        // C.5.3 / C.6.3
+        assert_eq!(
+            decoder.decode(&hex(blocks[2])),
+            Some(fields(&[
+                (":status", "200"),
+                ("cache-control", "private"),
+                ("date", "Mon, 21 Oct 2013 20:13:22 GMT"),
+                ("location", "https://www.example.com"),
+                ("content-encoding", "gzip"),
+                (
+                    "set-cookie",
+                    "foo=ASDJKHQKBZXOQWEOPIUAXQWEOIU; max-age=3600; version=1",
+                ),
+            ]))
+        );
+        assert_eq!(
+            table(&decoder),
+            fields(&[
+                (
+                    "set-cookie",
+                    "foo=ASDJKHQKBZXOQWEOPIUAXQWEOIU; max-age=3600; version=1",
+                ),
+                ("content-encoding", "gzip"),
+                ("date", "Mon, 21 Oct 2013 20:13:22 GMT"),
+            ])
+        );
+        assert_eq!(decoder.size, 215);
+    }
+
+    #[test]
+    fn responses_without_huffman() {
+        check_responses([
+            "4803 3330 3258 0770 7269 7661 7465 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 \
+             2032 303a 3133 3a32 3120 474d 546e 1768 7474 7073 3a2f 2f77 7777 2e65 7861 6d70 \
+             6c65 2e63 6f6d",
+            "4803 3330 37c1 c0bf",
+            "88c1 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 2032 303a 3133 3a32 3220 474d \
+             54c0 5a04 677a 6970 7738 666f 6f3d 4153 444a 4b48 514b 425a 584f 5157 454f 5049 \
+             5541 5851 5745 4f49 553b 206d 6178 2d61 6765 3d33 3630 303b 2076 6572 7369 6f6e \
+             3d31",
+        ]);
+    }
+
+    #[test]
+    fn responses_with_huffman() {
+        check_responses([
+            "4882 6402 5885 aec3 771a 4b61 96d0 7abe 9410 54d4 44a8 2005 9504 0b81 66e0 82a6 \
+             2d1b ff6e 919d 29ad 1718 63c7 8f0b 97c8 e9ae 82ae 43d3",
+            "4883 640e ffc1 c0bf",
+            "88c1 6196 d07a be94 1054 d444 a820 0595 040b 8166 e084 a62d 1bff c05a 839b d9ab \
+             77ad 94e7 821d d7f2 e6c7 b335 dfdf cd5b 3960 d5af 2708 7f36 72c1 ab27 0fb5 291f \
+             9587 3160 65c0 03ed 4ee5 b106 3d50 07",
+        ]);
+    }
+
+    #[test]
+    fn invalid_huffman_padding_rejected() {
+        // 填充超过 7 位，或填充不全为 1
+        assert_eq!(decode_huffman(&[0xff, 0xff]), None);
+        assert_eq!(decode_huffman(&[0x00]), None);
+        assert_eq!(
+            decode_huffman(&hex("f1e3 c2e5 f23a 6ba0 ab90 f4ff")).as_deref(),
+            Some(&b"www.example.com"[..])
+        );
+    }
+
+    #[test]
+    fn table_size_update_evicts_entries() {
+        let mut decoder = HpackDecoder::new();
+        decoder.decode(&hex("8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d"));
+        // 动态表大小更新为 0
+        assert_eq!(decoder.decode(&[0x20]), Some(Vec::new()));
+        assert!(decoder.table.is_empty());
+        assert_eq!(decoder.decode(&[0xbe]), None);
+    }
+}
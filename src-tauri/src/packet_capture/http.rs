@@ -0,0 +1,1653 @@
+use super::body::{self, HttpBody, MAX_BODY_SIZE};
+use super::charset;
+use super::cookie;
+use super::hpack::HeaderField;
+use super::http2::{self, Http2Connection};
+use super::multipart;
+use super::reassembly::{FlowKey, StreamBuffer};
+use super::tls::{self, ClientHello, ClientHelloParse};
+use super::tls_decrypt::TlsDecryptor;
+use super::websocket::{self, Opcode, WebSocketConnection};
+use super::{HttpRequest, HttpResponse, HttpTransaction, TlsConnection, WebSocketMessage};
+use log::{debug, warn};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 每个连接最多等待响应的请求数量
+const MAX_PENDING_REQUESTS: usize = 64;
+/// 连接空闲超时时间
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(120);
+/// 清理过期连接的间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(10);
+/// 请求方法的最大长度（扩展方法也不会超过这个长度）
+const MAX_METHOD_LEN: usize = 32;
+/// 请求行中方法和请求目标的最大长度
+const MAX_REQUEST_LINE_LEN: usize = 8192;
+/// 分块编码中块大小行和尾部字段行的最大长度
+const MAX_CHUNK_LINE_LEN: usize = 8192;
+/// 消息头的最大长度，超过后不再等待消息头结束
+const MAX_HEADER_LEN: usize = 64 * 1024;
+
+// 消息序号，所有解析线程和本地代理共用，直接作为消息的唯一ID（从 1 开始）
+static MESSAGE_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// 从字节流中解析出的 HTTP 消息
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum HttpMessage {
+    /// 客户端发出的请求
+    Request(HttpRequest),
+    /// 已与请求配对的响应
+    Transaction(HttpTransaction),
+    /// HTTPS 连接的 ClientHello，有密钥日志时其后的 HTTP 消息会被解密解析
+    Tls(TlsConnection),
+    /// 升级为 WebSocket 的连接上的消息
+    WebSocket(WebSocketMessage),
+}
+
+/// 等待响应的请求
+#[derive(Debug)]
+struct PendingRequest {
+    request: HttpRequest,
+    received_at_ms: u64,
+}
+
+/// 单个 TCP 连接上的 HTTP 会话状态
+#[derive(Debug)]
+struct Connection {
+    pending: VecDeque<PendingRequest>,
+    last_seen: Instant,
+}
+
+/// HTTP/2 连接上的会话状态
+#[derive(Debug)]
+struct Http2Session {
+    frames: Http2Connection,
+    /// 按流标识等待响应的请求
+    pending: HashMap<u32, PendingRequest>,
+    /// 两个方向（服务端、客户端）开始解析时字节流的代数，字节流丢弃数据后无法继续解析
+    generations: [Option<u64>; 2],
+    last_seen: Instant,
+}
+
+impl Http2Session {
+    fn new() -> Self {
+        Self {
+            frames: Http2Connection::new(),
+            pending: HashMap::new(),
+            generations: [None; 2],
+            last_seen: Instant::now(),
+        }
+    }
+}
+
+/// WebSocket 连接上的会话状态
+#[derive(Debug)]
+struct WebSocketSession {
+    frames: WebSocketConnection,
+    /// 握手请求的 ID、主机和路径
+    connection_id: u64,
+    host: String,
+    path: String,
+    /// 两个方向（服务端、客户端）开始解析时字节流的代数
+    generations: [Option<u64>; 2],
+    last_seen: Instant,
+}
+
+/// 已解析消息头、等待消息体的消息
+#[derive(Debug)]
+enum ParsedHead {
+    Request(HttpRequest),
+    Response(HttpResponse),
+}
+
+/// 单方向上正在接收的消息，消息头和已读取的消息体已从字节流中取出
+#[derive(Debug)]
+struct PartialMessage {
+    /// 开始解析时字节流的代数，字节流丢弃数据后解析进度失效
+    generation: u64,
+    started: Instant,
+    /// 消息头，消息体超过 MAX_BODY_SIZE 提前发出消息后为 None，此后只跳过剩余的消息体
+    head: Option<ParsedHead>,
+    body: BodyReader,
+    /// 已读取的消息体
+    data: BodyBuffer,
+}
+
+/// 已读取的（解码分块编码后的）消息体，超过 MAX_BODY_SIZE 的部分只计数不保存
+#[derive(Debug, Default)]
+struct BodyBuffer {
+    data: Vec<u8>,
+    size: usize,
+}
+
+impl BodyBuffer {
+    fn push(&mut self, bytes: &[u8]) {
+        self.size += bytes.len();
+        let room = MAX_BODY_SIZE.saturating_sub(self.data.len());
+        self.data.extend_from_slice(&bytes[..room.min(bytes.len())]);
+    }
+
+    /// 消息体已超过保存上限
+    fn is_truncated(&self) -> bool {
+        self.size > self.data.len()
+    }
+}
+
+/// 消息体的读取方式（RFC 9112 第 6 节）
+#[derive(Debug)]
+enum BodyReader {
+    /// 没有消息体
+    Empty,
+    /// 由 Content-Length 指定长度，记录尚未读取的字节数
+    Length(usize),
+    /// 分块传输编码
+    Chunked(ChunkedDecoder),
+    /// 消息体持续到连接关闭
+    UntilClose,
+}
+
+/// 消息体读取进度，读取过的字节需要从字节流中取出
+enum BodyProgress {
+    /// 消息体已结束，本次读取的字节数
+    Complete(usize),
+    /// 消息体尚未结束，本次读取的字节数
+    Incomplete(usize),
+    /// 格式错误，无法确定消息边界
+    Invalid,
+}
+
+/// 开始解析新消息的结果
+#[allow(clippy::large_enum_variant)]
+enum MessageStart {
+    /// 消息头已解析，以及消息头的长度（包含空行）
+    Started(PartialMessage, usize),
+    /// 消息头尚未收全
+    Incomplete,
+    /// 不是 HTTP 消息或消息头格式错误
+    Invalid,
+}
+
+/// HTTP 解析器，按连接跟踪请求并与反方向的响应配对
+#[derive(Debug)]
+pub struct HttpDissector {
+    /// 以客户端 -> 服务端方向的四元组作为连接标识
+    connections: HashMap<FlowKey, Connection>,
+    /// 各方向上消息头已解析、消息体尚未收全的消息
+    partial: HashMap<FlowKey, PartialMessage>,
+    /// 各方向上消息头尚未收全时的查找进度：字节流代数和已查找的长度
+    header_scans: HashMap<FlowKey, (u64, usize)>,
+    /// HTTP/2 连接，以客户端 -> 服务端方向的四元组作为标识
+    http2: HashMap<FlowKey, Http2Session>,
+    /// 已完成握手的 WebSocket 连接，以客户端 -> 服务端方向的四元组作为标识
+    websockets: HashMap<FlowKey, WebSocketSession>,
+    /// 使用密钥日志解密的 TLS 连接
+    tls: TlsDecryptor,
+    /// TLS 连接各方向上解密后尚未解析的明文
+    plaintext: HashMap<FlowKey, StreamBuffer>,
+    last_cleanup: Instant,
+}
+
+impl Default for HttpDissector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpDissector {
+    pub fn new() -> Self {
+        Self {
+            connections: HashMap::new(),
+            partial: HashMap::new(),
+            header_scans: HashMap::new(),
+            http2: HashMap::new(),
+            websockets: HashMap::new(),
+            tls: TlsDecryptor::new(),
+            plaintext: HashMap::new(),
+            last_cleanup: Instant::now(),
+        }
+    }
+
+    /// 从重组后的字节流中取出所有完整的 HTTP 消息
+    /// timestamp_ms 为触发本次解析的数据包的捕获时间（毫秒）
+    pub fn process_stream(
+        &mut self,
+        key: FlowKey,
+        stream: &mut StreamBuffer,
+        timestamp_ms: u64,
+    ) -> Vec<HttpMessage> {
+        if self.last_cleanup.elapsed() >= CLEANUP_INTERVAL {
+            self.cleanup();
+        }
+
+        let mut messages = Vec::new();
+
+        // 连接以 ClientHello 开头时开始跟踪 TLS 会话；端口复用时旧会话的字节流会被替换
+        let tls_flow = self.tls.is_tls_flow(&key);
+        let expect_client_hello = if tls_flow {
+            self.tls.is_stale(&key, stream.generation())
+        } else {
+            !self.partial.contains_key(&key) && !self.is_upgraded_flow(&key)
+        };
+        if expect_client_hello && tls::is_handshake_prefix(stream.data()) {
+            match tls::parse_client_hello(stream.data()) {
+                ClientHelloParse::Complete(hello, len) => {
+                    stream.consume(len);
+                    for flow in [key, key.reverse()] {
+                        self.partial.remove(&flow);
+                        self.plaintext.remove(&flow);
+                    }
+                    self.tls.start_session(key, &hello, stream.generation());
+                    messages.push(HttpMessage::Tls(new_tls_connection(
+                        hello,
+                        &key,
+                        timestamp_ms,
+                    )));
+                    self.process_tls_stream(key, stream, timestamp_ms, &mut messages);
+                    return messages;
+                }
+                ClientHelloParse::Incomplete if !stream.is_closed() => return messages,
+                _ if tls_flow => {}
+                _ => {
+                    // 服务端的握手消息或从连接中途开始捕获的加密数据
+                    debug!("丢弃 {} 字节 TLS 数据", stream.data().len());
+                    stream.clear();
+                    return messages;
+                }
+            }
+        }
+
+        if self.tls.is_tls_flow(&key) {
+            self.process_tls_stream(key, stream, timestamp_ms, &mut messages);
+        } else {
+            self.parse_messages(key, stream, timestamp_ms, &mut messages);
+        }
+        messages
+    }
+
+    /// 解密 TLS 连接上的数据，并从解密后的明文中解析 HTTP 消息
+    fn process_tls_stream(
+        &mut self,
+        key: FlowKey,
+        stream: &mut StreamBuffer,
+        timestamp_ms: u64,
+        messages: &mut Vec<HttpMessage>,
+    ) {
+        let decrypted = self.tls.decrypt(key, stream);
+        let mut plaintext = self
+            .plaintext
+            .remove(&key)
+            .unwrap_or_else(StreamBuffer::detached);
+        plaintext.extend(&decrypted);
+        if stream.is_closed() {
+            plaintext.close();
+        }
+
+        self.parse_messages(key, &mut plaintext, timestamp_ms, messages);
+        if !plaintext.is_closed() {
+            self.plaintext.insert(key, plaintext);
+        }
+    }
+
+    /// 从字节流中取出所有完整的 HTTP 消息
+    fn parse_messages(
+        &mut self,
+        key: FlowKey,
+        stream: &mut StreamBuffer,
+        timestamp_ms: u64,
+        messages: &mut Vec<HttpMessage>,
+    ) {
+        loop {
+            // HTTP/2 连接（包括刚完成 h2c 升级的连接）按帧解析
+            if self.process_http2(key, stream, timestamp_ms, messages)
+                || self.process_websocket(key, stream, timestamp_ms, messages)
+            {
+                break;
+            }
+
+            // 继续上次未完成的消息，字节流丢弃过数据时重新开始
+            let mut partial = match self.partial.remove(&key) {
+                Some(partial) if partial.generation == stream.generation() => partial,
+                _ => {
+                    let data = stream.data();
+                    if data.is_empty() {
+                        break;
+                    }
+                    // 消息头尚未收全时从上次查找到的位置继续，不重复查找已收到的数据
+                    let scanned = match self.header_scans.remove(&key) {
+                        Some((generation, scanned)) if generation == stream.generation() => scanned,
+                        _ => 0,
+                    };
+                    match self.start_message(key, data, scanned, stream.is_closed()) {
+                        MessageStart::Started(mut partial, header_len) => {
+                            partial.generation = stream.generation();
+                            stream.consume(header_len);
+                            partial
+                        }
+                        MessageStart::Incomplete => {
+                            self.header_scans
+                                .insert(key, (stream.generation(), data.len()));
+                            break;
+                        }
+                        MessageStart::Invalid => {
+                            // 非 HTTP 数据或从连接中途开始捕获，直接丢弃
+                            debug!("丢弃 {} 字节非 HTTP 数据", data.len());
+                            stream.clear();
+                            break;
+                        }
+                    }
+                }
+            };
+
+            // 消息体边读取边从字节流中取出，大消息体不会占满字节流的缓冲区
+            match partial
+                .body
+                .advance(stream.data(), stream.is_closed(), &mut partial.data)
+            {
+                BodyProgress::Complete(len) => {
+                    stream.consume(len);
+                    self.emit_message(key, &mut partial, timestamp_ms, messages);
+                }
+                BodyProgress::Incomplete(len) => {
+                    stream.consume(len);
+                    // 消息体超过保存上限时不再等待，先发出消息，剩余的消息体读取后丢弃
+                    if partial.data.is_truncated() {
+                        self.emit_message(key, &mut partial, timestamp_ms, messages);
+                    }
+                    self.partial.insert(key, partial);
+                    break;
+                }
+                BodyProgress::Invalid => {
+                    warn!(
+                        "HTTP 消息体格式错误，丢弃连接 {}:{} -> {}:{} 上的数据",
+                        key.src_ip, key.src_port, key.dst_ip, key.dst_port
+                    );
+                    stream.clear();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// 发出消息头已解析的消息，消息体未读完时标记为截断；每个消息只发出一次
+    fn emit_message(
+        &mut self,
+        key: FlowKey,
+        partial: &mut PartialMessage,
+        timestamp_ms: u64,
+        messages: &mut Vec<HttpMessage>,
+    ) {
+        let Some(head) = partial.head.take() else {
+            return;
+        };
+        let data = std::mem::take(&mut partial.data.data);
+        let size = partial.data.size + partial.body.remaining();
+
+        match head {
+            ParsedHead::Request(mut request) => {
+                set_request_body(&mut request, data, size);
+                fill_request_network_info(&mut request, &key, timestamp_ms);
+                self.track_request(key, request.clone(), timestamp_ms);
+                messages.push(HttpMessage::Request(request));
+            }
+            ParsedHead::Response(mut response) => {
+                set_response_body(&mut response, data, size);
+                fill_response_network_info(&mut response, &key, timestamp_ms);
+                if let Some(transaction) = self.pair_response(key.reverse(), response, timestamp_ms)
+                {
+                    messages.push(HttpMessage::Transaction(transaction));
+                }
+            }
+        }
+    }
+
+    /// 连接是否已按 HTTP/2 或 WebSocket 解析
+    fn is_upgraded_flow(&self, key: &FlowKey) -> bool {
+        [*key, key.reverse()]
+            .iter()
+            .any(|flow| self.http2.contains_key(flow) || self.websockets.contains_key(flow))
+    }
+
+    /// 解析 HTTP/2 连接上的帧，不是 HTTP/2 连接时返回 false
+    fn process_http2(
+        &mut self,
+        key: FlowKey,
+        stream: &mut StreamBuffer,
+        timestamp_ms: u64,
+        messages: &mut Vec<HttpMessage>,
+    ) -> bool {
+        let (client_key, from_client) = if self.http2.contains_key(&key) {
+            (key, true)
+        } else if self.http2.contains_key(&key.reverse()) {
+            (key.reverse(), false)
+        } else if !self.partial.contains_key(&key) && http2::is_preface_prefix(stream.data()) {
+            // 以连接前言开头的是直接使用 HTTP/2 的连接（prior knowledge），
+            // 前言的请求行 "PRI * HTTP/2.0" 不能按 HTTP/1.x 解析
+            if stream.data().len() < http2::PREFACE.len() {
+                if stream.is_closed() {
+                    stream.clear();
+                }
+                return true;
+            }
+            self.http2.insert(key, Http2Session::new());
+            (key, true)
+        } else {
+            return false;
+        };
+
+        let session = self.http2.get_mut(&client_key).unwrap();
+        session.last_seen = Instant::now();
+        let generation = &mut session.generations[usize::from(from_client)];
+        let resynced = generation.is_some_and(|generation| generation != stream.generation());
+        *generation = Some(stream.generation());
+
+        let mut frames = Vec::new();
+        if resynced
+            || session
+                .frames
+                .process(from_client, stream, &mut frames)
+                .is_none()
+        {
+            warn!(
+                "HTTP/2 数据无法继续解析，丢弃连接 {}:{} -> {}:{} 上的数据",
+                key.src_ip, key.src_port, key.dst_ip, key.dst_port
+            );
+            self.http2.remove(&client_key);
+            stream.clear();
+        }
+
+        for frame_message in frames {
+            if from_client {
+                let head = http2_request_head(&frame_message.headers);
+                let Some(mut request) = parse_http_request(&head, &frame_message.body) else {
+                    debug!("HTTP/2 流 {} 上的请求缺少伪头部", frame_message.stream_id);
+                    continue;
+                };
+                fill_request_network_info(&mut request, &key, timestamp_ms);
+                if let Some(session) = self.http2.get_mut(&client_key) {
+                    if session.pending.len() < MAX_PENDING_REQUESTS {
+                        session.pending.insert(
+                            frame_message.stream_id,
+                            PendingRequest {
+                                request: request.clone(),
+                                received_at_ms: timestamp_ms,
+                            },
+                        );
+                    }
+                }
+                messages.push(HttpMessage::Request(request));
+            } else {
+                let head = http2_response_head(&frame_message.headers);
+                let Some(mut response) = parse_http_response(&head, &frame_message.body) else {
+                    debug!("HTTP/2 流 {} 上的响应缺少状态码", frame_message.stream_id);
+                    continue;
+                };
+                fill_response_network_info(&mut response, &key, timestamp_ms);
+                let pending = self
+                    .http2
+                    .get_mut(&client_key)
+                    .and_then(|session| session.pending.remove(&frame_message.stream_id));
+                match pending {
+                    Some(pending) => messages.push(HttpMessage::Transaction(new_transaction(
+                        pending,
+                        response,
+                        timestamp_ms,
+                    ))),
+                    None => debug!(
+                        "收到无法配对的 HTTP/2 响应: 流 {} 状态码 {}",
+                        frame_message.stream_id, response.status_code
+                    ),
+                }
+            }
+        }
+        true
+    }
+
+    /// 解析 WebSocket 连接上的帧，不是 WebSocket 连接时返回 false
+    fn process_websocket(
+        &mut self,
+        key: FlowKey,
+        stream: &mut StreamBuffer,
+        timestamp_ms: u64,
+        messages: &mut Vec<HttpMessage>,
+    ) -> bool {
+        let (client_key, from_client) = if self.websockets.contains_key(&key) {
+            (key, true)
+        } else if self.websockets.contains_key(&key.reverse()) {
+            (key.reverse(), false)
+        } else {
+            return false;
+        };
+
+        let session = self.websockets.get_mut(&client_key).unwrap();
+        session.last_seen = Instant::now();
+        let generation = &mut session.generations[usize::from(from_client)];
+        let resynced = generation.is_some_and(|generation| generation != stream.generation());
+        *generation = Some(stream.generation());
+
+        let mut frames = Vec::new();
+        let failed = resynced
+            || session
+                .frames
+                .process(from_client, stream, &mut frames)
+                .is_none();
+        for frame in frames {
+            let (close_code, data) = match frame.opcode {
+                // 心跳帧不发送到前端
+                Opcode::Ping | Opcode::Pong => continue,
+                // 关闭帧以 2 字节的状态码开头
+                Opcode::Close if frame.data.len() >= 2 => (
+                    Some(u16::from_be_bytes([frame.data[0], frame.data[1]])),
+                    frame.data[2..].to_vec(),
+                ),
+                _ => (None, frame.data),
+            };
+            let content_type = match frame.opcode {
+                Opcode::Binary => "application/octet-stream",
+                _ => "text/plain; charset=utf-8",
+            };
+            let mut payload = HttpBody::new(data, content_type, "");
+            payload.truncated |= frame.truncated;
+
+            let timestamp = timestamp_ms / 1000;
+            messages.push(HttpMessage::WebSocket(WebSocketMessage {
+                id: next_message_id(),
+                timestamp,
+                src_ip: key.src_ip.to_string(),
+                src_port: key.src_port,
+                dst_ip: key.dst_ip.to_string(),
+                dst_port: key.dst_port,
+                connection_id: session.connection_id,
+                host: session.host.clone(),
+                path: session.path.clone(),
+                from_client,
+                opcode: frame.opcode.as_str().to_string(),
+                close_code,
+                payload,
+            }));
+        }
+
+        if failed {
+            warn!(
+                "WebSocket 数据无法继续解析，丢弃连接 {}:{} -> {}:{} 上的数据",
+                key.src_ip, key.src_port, key.dst_ip, key.dst_port
+            );
+            self.websockets.remove(&client_key);
+            stream.clear();
+        }
+        true
+    }
+
+    /// 解析新消息的消息头，并确定消息体的读取方式
+    /// scanned 为上次已查找过消息头结尾的长度
+    fn start_message(
+        &self,
+        key: FlowKey,
+        data: &[u8],
+        scanned: usize,
+        closed: bool,
+    ) -> MessageStart {
+        let is_request = is_http_request_prefix(data);
+        if !is_request && !is_http_response_prefix(data) {
+            return MessageStart::Invalid;
+        }
+
+        let header_len = match find_header_end_from(data, scanned) {
+            Some(end) if end <= MAX_HEADER_LEN => end,
+            // 消息头过长，对端可能永远不会发送空行
+            Some(_) => return MessageStart::Invalid,
+            None if data.len() > MAX_HEADER_LEN => return MessageStart::Invalid,
+            // 连接已关闭，把剩余数据当作没有消息体的消息处理
+            None if closed && !is_request => data.len(),
+            None if closed => return MessageStart::Invalid,
+            None => return MessageStart::Incomplete,
+        };
+        let head = &data[..header_len];
+
+        let (head, body) = if is_request {
+            let request = match parse_http_request(head, &[]) {
+                Some(request) => request,
+                None => return MessageStart::Invalid,
+            };
+            match request_body_reader(&request.headers) {
+                Some(body) => (ParsedHead::Request(request), body),
+                None => return MessageStart::Invalid,
+            }
+        } else {
+            let response = match parse_http_response(head, &[]) {
+                Some(response) => response,
+                None => return MessageStart::Invalid,
+            };
+            // 响应体的读取方式取决于对应请求的方法
+            let request_method = self
+                .connections
+                .get(&key.reverse())
+                .and_then(|conn| conn.pending.front())
+                .map(|pending| pending.request.method.as_str());
+            match response_body_reader(&response, request_method) {
+                Some(body) => (ParsedHead::Response(response), body),
+                None => return MessageStart::Invalid,
+            }
+        };
+
+        MessageStart::Started(
+            PartialMessage {
+                generation: 0, // 由调用方设置
+                started: Instant::now(),
+                head: Some(head),
+                body,
+                data: BodyBuffer::default(),
+            },
+            header_len,
+        )
+    }
+
+    /// 记录等待响应的请求
+    fn track_request(&mut self, key: FlowKey, request: HttpRequest, timestamp_ms: u64) {
+        let connection = self.connections.entry(key).or_insert_with(|| Connection {
+            pending: VecDeque::new(),
+            last_seen: Instant::now(),
+        });
+        connection.last_seen = Instant::now();
+
+        if connection.pending.len() >= MAX_PENDING_REQUESTS {
+            warn!(
+                "连接 {}:{} 等待响应的请求过多，丢弃最早的请求",
+                key.src_ip, key.src_port
+            );
+            connection.pending.pop_front();
+        }
+        connection.pending.push_back(PendingRequest {
+            request,
+            received_at_ms: timestamp_ms,
+        });
+    }
+
+    /// 按顺序将响应与同一连接上最早的未响应请求配对
+    fn pair_response(
+        &mut self,
+        client_key: FlowKey,
+        response: HttpResponse,
+        timestamp_ms: u64,
+    ) -> Option<HttpTransaction> {
+        // 1xx 临时响应之后还会有最终响应
+        if (100..200).contains(&response.status_code) && response.status_code != 101 {
+            debug!("忽略临时响应: {} {}", response.status_code, response.reason);
+            return None;
+        }
+
+        let connection = match self.connections.get_mut(&client_key) {
+            Some(connection) => connection,
+            None => {
+                debug!(
+                    "收到无法配对的 HTTP 响应: {} {}",
+                    response.status_code, response.reason
+                );
+                return None;
+            }
+        };
+        connection.last_seen = Instant::now();
+
+        let pending = match connection.pending.pop_front() {
+            Some(pending) => pending,
+            None => {
+                debug!(
+                    "收到无法配对的 HTTP 响应: {} {}",
+                    response.status_code, response.reason
+                );
+                return None;
+            }
+        };
+
+        // h2c 升级：升级请求的响应在 HTTP/2 的流 1 上返回
+        if response.status_code == 101
+            && is_h2c_upgrade(&pending.request.headers)
+            && is_h2c_upgrade(&response.headers)
+        {
+            debug!(
+                "连接 {}:{} 升级为 HTTP/2",
+                client_key.src_ip, client_key.src_port
+            );
+            self.http2
+                .entry(client_key)
+                .or_insert_with(Http2Session::new)
+                .pending
+                .insert(1, pending);
+            return None;
+        }
+
+        // WebSocket 握手完成，此后连接上的数据按帧解析
+        if response.status_code == 101
+            && is_websocket_upgrade(&pending.request.headers)
+            && is_websocket_upgrade(&response.headers)
+        {
+            let extensions = find_header(&response.headers, "Sec-WebSocket-Extensions");
+            debug!(
+                "连接 {}:{} 升级为 WebSocket: {}{}",
+                client_key.src_ip, client_key.src_port, pending.request.host, pending.request.path
+            );
+            self.websockets.insert(
+                client_key,
+                WebSocketSession {
+                    frames: WebSocketConnection::new(websocket::parse_deflate_extension(
+                        &extensions,
+                    )),
+                    connection_id: pending.request.id,
+                    host: pending.request.host.clone(),
+                    path: pending.request.path.clone(),
+                    generations: [None; 2],
+                    last_seen: Instant::now(),
+                },
+            );
+        }
+
+        Some(new_transaction(pending, response, timestamp_ms))
+    }
+
+    /// 清理空闲超时的连接和长时间未完成的消息
+    fn cleanup(&mut self) {
+        self.connections
+            .retain(|_, connection| connection.last_seen.elapsed() < CONNECTION_TIMEOUT);
+        self.partial
+            .retain(|_, partial| partial.started.elapsed() < CONNECTION_TIMEOUT);
+        // 查找进度丢失时只需重新查找，直接清空即可释放已结束连接的记录
+        self.header_scans.clear();
+        self.http2
+            .retain(|_, session| session.last_seen.elapsed() < CONNECTION_TIMEOUT);
+        self.websockets
+            .retain(|_, session| session.last_seen.elapsed() < CONNECTION_TIMEOUT);
+        self.tls.cleanup();
+        let tls = &self.tls;
+        self.plaintext.retain(|key, _| tls.is_tls_flow(key));
+        self.last_cleanup = Instant::now();
+    }
+}
+
+// 将响应与请求组成事务
+fn new_transaction(
+    pending: PendingRequest,
+    mut response: HttpResponse,
+    timestamp_ms: u64,
+) -> HttpTransaction {
+    // Set-Cookie 的默认域名和路径取决于对应的请求
+    response.cookies = cookie::parse_set_cookies(&pending.request, &response);
+
+    HttpTransaction {
+        id: pending.request.id,
+        duration_ms: timestamp_ms.saturating_sub(pending.received_at_ms),
+        request: pending.request,
+        response,
+    }
+}
+
+// 检查 Upgrade 头是否要求升级到 h2c
+fn is_h2c_upgrade(headers: &[(String, String)]) -> bool {
+    has_upgrade_protocol(headers, "h2c")
+}
+
+// 检查 Upgrade 头是否要求升级到 WebSocket
+fn is_websocket_upgrade(headers: &[(String, String)]) -> bool {
+    has_upgrade_protocol(headers, "websocket")
+}
+
+fn has_upgrade_protocol(headers: &[(String, String)], protocol: &str) -> bool {
+    find_header(headers, "Upgrade")
+        .split(',')
+        .any(|item| item.trim().eq_ignore_ascii_case(protocol))
+}
+
+// 把 HTTP/2 请求的伪头部转换为请求行，以便按 HTTP/1.1 的格式解析
+fn http2_request_head(headers: &[HeaderField]) -> Vec<u8> {
+    let pseudo = |name: &[u8]| {
+        headers
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_slice())
+    };
+    let method = pseudo(b":method").unwrap_or_default();
+    let authority = pseudo(b":authority");
+    // CONNECT 请求没有 :path，请求目标为 :authority
+    let target = pseudo(b":path").or(authority).unwrap_or_default();
+
+    let mut head = Vec::new();
+    head.extend_from_slice(method);
+    head.push(b' ');
+    head.extend_from_slice(target);
+    head.extend_from_slice(b" HTTP/2\r\n");
+    if let Some(authority) = authority {
+        if !headers.iter().any(|(name, _)| name == b"host") {
+            push_header(&mut head, b"host", authority);
+        }
+    }
+    push_http2_headers(&mut head, headers);
+    head
+}
+
+// 把 HTTP/2 响应的 :status 转换为状态行
+fn http2_response_head(headers: &[HeaderField]) -> Vec<u8> {
+    let status = headers
+        .iter()
+        .find(|(name, _)| name == b":status")
+        .map(|(_, value)| value.as_slice())
+        .unwrap_or_default();
+
+    let mut head = b"HTTP/2 ".to_vec();
+    head.extend_from_slice(status);
+    head.extend_from_slice(b"\r\n");
+    push_http2_headers(&mut head, headers);
+    head
+}
+
+// 追加普通消息头字段，HTTP/2 中拆分的多个 cookie 字段合并为一个（RFC 9113 第 8.2.3 节）
+fn push_http2_headers(head: &mut Vec<u8>, headers: &[HeaderField]) {
+    let mut cookies: Vec<&[u8]> = Vec::new();
+    for (name, value) in headers {
+        if name.starts_with(b":") {
+            continue;
+        }
+        if name == b"cookie" {
+            cookies.push(value);
+        } else {
+            push_header(head, name, value);
+        }
+    }
+    if !cookies.is_empty() {
+        push_header(head, b"cookie", &cookies.join(&b"; "[..]));
+    }
+    head.extend_from_slice(b"\r\n");
+}
+
+fn push_header(head: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+    head.extend_from_slice(name);
+    head.extend_from_slice(b": ");
+    head.extend_from_slice(value);
+    head.extend_from_slice(b"\r\n");
+}
+
+// 生成消息唯一ID：单调递增，不受同一秒内的消息数量和回放文件的时间戳影响
+fn next_message_id() -> u64 {
+    MESSAGE_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+// 添加请求的网络信息
+fn fill_request_network_info(request: &mut HttpRequest, key: &FlowKey, timestamp_ms: u64) {
+    let timestamp = timestamp_ms / 1000;
+
+    request.id = next_message_id();
+    request.timestamp = timestamp;
+    request.src_ip = key.src_ip.to_string();
+    request.src_port = key.src_port;
+    request.dst_ip = key.dst_ip.to_string();
+    request.dst_port = key.dst_port;
+}
+
+// 根据 ClientHello 创建 TLS 连接记录
+pub(super) fn new_tls_connection(
+    hello: ClientHello,
+    key: &FlowKey,
+    timestamp_ms: u64,
+) -> TlsConnection {
+    let timestamp = timestamp_ms / 1000;
+    TlsConnection {
+        id: next_message_id(),
+        timestamp,
+        src_ip: key.src_ip.to_string(),
+        src_port: key.src_port,
+        dst_ip: key.dst_ip.to_string(),
+        dst_port: key.dst_port,
+        sni: hello.sni,
+        alpn: hello.alpn,
+        version: hello.version,
+    }
+}
+
+// 添加响应的网络信息
+fn fill_response_network_info(response: &mut HttpResponse, key: &FlowKey, timestamp_ms: u64) {
+    let timestamp = timestamp_ms / 1000;
+
+    response.id = next_message_id();
+    response.timestamp = timestamp;
+    response.src_ip = key.src_ip.to_string();
+    response.src_port = key.src_port;
+    response.dst_ip = key.dst_ip.to_string();
+    response.dst_port = key.dst_port;
+}
+
+impl BodyReader {
+    /// 读取消息体，data 为字节流中消息头之后尚未读取的数据，解码后的消息体写入 body
+    fn advance(&mut self, data: &[u8], closed: bool, body: &mut BodyBuffer) -> BodyProgress {
+        match self {
+            BodyReader::Empty => BodyProgress::Complete(0),
+            BodyReader::Length(remaining) => {
+                let len = data.len().min(*remaining);
+                body.push(&data[..len]);
+                *remaining -= len;
+                if *remaining == 0 {
+                    BodyProgress::Complete(len)
+                } else {
+                    BodyProgress::Incomplete(len)
+                }
+            }
+            BodyReader::Chunked(decoder) => match decoder.advance(data, body) {
+                Ok((len, true)) => BodyProgress::Complete(len),
+                Ok(_) if closed => {
+                    // 连接已关闭，保留已解码的部分
+                    debug!("分块消息未结束连接即关闭，保留已收到的 {} 字节", body.size);
+                    BodyProgress::Complete(data.len())
+                }
+                Ok((len, false)) => BodyProgress::Incomplete(len),
+                Err(()) => BodyProgress::Invalid,
+            },
+            BodyReader::UntilClose => {
+                body.push(data);
+                if closed {
+                    BodyProgress::Complete(data.len())
+                } else {
+                    BodyProgress::Incomplete(data.len())
+                }
+            }
+        }
+    }
+
+    /// 已知长度的消息体中尚未读取的字节数
+    fn remaining(&self) -> usize {
+        match self {
+            BodyReader::Length(remaining) => *remaining,
+            _ => 0,
+        }
+    }
+}
+
+/// 分块传输编码的增量解码器
+#[derive(Debug, Default)]
+struct ChunkedDecoder {
+    state: ChunkState,
+}
+
+#[derive(Debug, Default)]
+enum ChunkState {
+    /// 等待块大小行
+    #[default]
+    Size,
+    /// 块数据，剩余字节数
+    Data(usize),
+    /// 块数据之后的 CRLF
+    DataEnd,
+    /// 最后一个块之后的尾部字段
+    Trailer,
+}
+
+impl ChunkedDecoder {
+    /// 继续解码，返回读取的字节数和消息体是否已结束
+    /// 不完整的块大小行和尾部字段行不读取，等待后续数据
+    fn advance(&mut self, data: &[u8], body: &mut BodyBuffer) -> Result<(usize, bool), ()> {
+        let mut pos = 0;
+        loop {
+            let rest = &data[pos..];
+            match self.state {
+                ChunkState::Size => {
+                    let line_end = match find_crlf(rest) {
+                        Some(end) => end,
+                        None if rest.len() > MAX_CHUNK_LINE_LEN => return Err(()),
+                        None => return Ok((pos, false)),
+                    };
+                    // 忽略块扩展
+                    let line = String::from_utf8_lossy(&rest[..line_end]);
+                    let size = line.split(';').next().unwrap_or("").trim();
+                    let size = usize::from_str_radix(size, 16).map_err(|_| ())?;
+                    pos += line_end + 2;
+                    self.state = if size == 0 {
+                        ChunkState::Trailer
+                    } else {
+                        ChunkState::Data(size)
+                    };
+                }
+                ChunkState::Data(remaining) => {
+                    let available = remaining.min(rest.len());
+                    body.push(&rest[..available]);
+                    pos += available;
+                    if available < remaining {
+                        self.state = ChunkState::Data(remaining - available);
+                        return Ok((pos, false));
+                    }
+                    self.state = ChunkState::DataEnd;
+                }
+                ChunkState::DataEnd => {
+                    if rest.len() < 2 {
+                        return Ok((pos, false));
+                    }
+                    if &rest[..2] != b"\r\n" {
+                        return Err(());
+                    }
+                    pos += 2;
+                    self.state = ChunkState::Size;
+                }
+                ChunkState::Trailer => {
+                    let line_end = match find_crlf(rest) {
+                        Some(end) => end,
+                        None if rest.len() > MAX_CHUNK_LINE_LEN => return Err(()),
+                        None => return Ok((pos, false)),
+                    };
+                    pos += line_end + 2;
+                    // 空行表示消息结束
+                    if line_end == 0 {
+                        return Ok((pos, true));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// 确定请求体的读取方式，格式错误时返回 None
+fn request_body_reader(headers: &[(String, String)]) -> Option<BodyReader> {
+    if let Some(chunked) = is_chunked(headers) {
+        // 请求的传输编码最后一项必须是 chunked，否则无法确定长度
+        return chunked.then(|| BodyReader::Chunked(ChunkedDecoder::default()));
+    }
+    match content_length(headers) {
+        Ok(Some(len)) => Some(BodyReader::Length(len)),
+        // 请求没有 Content-Length 时视为没有请求体
+        Ok(None) => Some(BodyReader::Empty),
+        Err(()) => None,
+    }
+}
+
+/// 请求在字节流中的结束位置，本地代理据此只转发连接上的第一个请求
+pub(super) struct RequestEnd {
+    reader: BodyReader,
+    /// 分块编码中尚未读取的不完整部分（已经转发）
+    pending: Vec<u8>,
+    done: bool,
+}
+
+impl RequestEnd {
+    /// head 为完整的请求头，无法确定请求体长度时返回 None
+    pub(super) fn new(head: &[u8]) -> Option<Self> {
+        let head = parse_head(head)?;
+        Some(Self {
+            reader: request_body_reader(&head.headers)?,
+            pending: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// 读取请求头之后新到达的数据，返回开头属于该请求的字节数
+    pub(super) fn advance(&mut self, data: &[u8]) -> usize {
+        if self.done {
+            return 0;
+        }
+        let buffered = self.pending.len();
+        self.pending.extend_from_slice(data);
+        // 只需要消息体的长度，内容不保存
+        let mut body = BodyBuffer::default();
+        match self.reader.advance(&self.pending, false, &mut body) {
+            BodyProgress::Incomplete(len) => {
+                self.pending.drain(..len);
+                data.len()
+            }
+            BodyProgress::Complete(len) => {
+                self.done = true;
+                self.pending.clear();
+                len.saturating_sub(buffered)
+            }
+            // 格式错误的请求体原样转发给上游，由上游返回错误
+            BodyProgress::Invalid => {
+                self.done = true;
+                self.pending.clear();
+                data.len()
+            }
+        }
+    }
+}
+
+// 确定响应体的读取方式，格式错误时返回 None
+fn response_body_reader(
+    response: &HttpResponse,
+    request_method: Option<&str>,
+) -> Option<BodyReader> {
+    // HEAD 请求的响应、CONNECT 请求的 2xx 响应、1xx、204、304 没有响应体
+    let status_code = response.status_code;
+    let is_method =
+        |name: &str| request_method.is_some_and(|method| method.eq_ignore_ascii_case(name));
+    if is_method("HEAD")
+        || (is_method("CONNECT") && (200..300).contains(&status_code))
+        || (100..200).contains(&status_code)
+        || status_code == 204
+        || status_code == 304
+    {
+        return Some(BodyReader::Empty);
+    }
+
+    if let Some(chunked) = is_chunked(&response.headers) {
+        return Some(if chunked {
+            BodyReader::Chunked(ChunkedDecoder::default())
+        } else {
+            BodyReader::UntilClose
+        });
+    }
+    match content_length(&response.headers) {
+        Ok(Some(len)) => Some(BodyReader::Length(len)),
+        // 没有长度信息时，响应体一直持续到连接关闭
+        Ok(None) => Some(BodyReader::UntilClose),
+        Err(()) => None,
+    }
+}
+
+// 查找 CRLF 的位置
+fn find_crlf(data: &[u8]) -> Option<usize> {
+    data.windows(2).position(|window| window == b"\r\n")
+}
+
+// 查找消息头结束位置（包含空行）
+pub(super) fn find_header_end(data: &[u8]) -> Option<usize> {
+    find_header_end_from(data, 0)
+}
+
+// 从 scanned 处继续查找消息头结束位置，空行可能跨越已查找部分的末尾
+fn find_header_end_from(data: &[u8], scanned: usize) -> Option<usize> {
+    let start = scanned.saturating_sub(3).min(data.len());
+    data[start..]
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|pos| start + pos + 4)
+}
+
+// 检查 Transfer-Encoding，没有该头时返回 None，否则返回最后一项编码是否为 chunked
+fn is_chunked(headers: &[(String, String)]) -> Option<bool> {
+    let codings: Vec<String> = headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("Transfer-Encoding"))
+        .flat_map(|(_, value)| value.split(','))
+        .map(|coding| coding.trim().to_ascii_lowercase())
+        .filter(|coding| !coding.is_empty())
+        .collect();
+
+    if codings.is_empty() {
+        return None;
+    }
+    Some(codings.last().is_some_and(|coding| coding == "chunked"))
+}
+
+// 读取 Content-Length，多个取值不一致或格式错误时返回 Err
+fn content_length(headers: &[(String, String)]) -> Result<Option<usize>, ()> {
+    let mut length = None;
+    for (_, value) in headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+    {
+        for item in value.split(',') {
+            let item = item.trim();
+            if item.is_empty() || !item.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(());
+            }
+            let parsed: usize = item.parse().map_err(|_| ())?;
+            if length.is_some_and(|existing| existing != parsed) {
+                return Err(());
+            }
+            length = Some(parsed);
+        }
+    }
+    Ok(length)
+}
+
+/// 请求行的匹配结果
+#[derive(Debug, PartialEq, Eq)]
+enum LineMatch {
+    /// 完整的请求行开头（方法、目标和协议版本）
+    Match,
+    /// 数据尚未收全，但到目前为止符合请求行格式
+    Partial,
+    /// 不是请求行
+    NoMatch,
+}
+
+// 检查是否是 HTTP 请求
+pub fn is_http_request(data: &[u8]) -> bool {
+    match_request_line(data) == LineMatch::Match
+}
+
+// 检查数据是否是（或可能是尚未收全的）HTTP 请求开头
+fn is_http_request_prefix(data: &[u8]) -> bool {
+    match_request_line(data) != LineMatch::NoMatch
+}
+
+// 按 "方法 SP 请求目标 SP HTTP/1.x" 格式检查请求行
+// 方法可以是 RFC 9110 定义的任意方法或扩展方法（token 字符组成）
+fn match_request_line(data: &[u8]) -> LineMatch {
+    const VERSION_PREFIX: &[u8] = b"HTTP/1.";
+
+    // 方法
+    let method_len = data.iter().take_while(|&&b| is_token_char(b)).count();
+    if method_len == data.len() {
+        return if method_len <= MAX_METHOD_LEN {
+            LineMatch::Partial
+        } else {
+            LineMatch::NoMatch
+        };
+    }
+    if method_len == 0 || method_len > MAX_METHOD_LEN || data[method_len] != b' ' {
+        return LineMatch::NoMatch;
+    }
+
+    // 请求目标：可见字符，允许未编码的 UTF-8
+    let target = &data[method_len + 1..];
+    let target_len = target
+        .iter()
+        .take_while(|&&b| b > b' ' && b != 0x7f)
+        .count();
+    if method_len + 1 + target_len > MAX_REQUEST_LINE_LEN {
+        return LineMatch::NoMatch;
+    }
+    if target_len == target.len() {
+        return LineMatch::Partial;
+    }
+    if target_len == 0 || target[target_len] != b' ' {
+        return LineMatch::NoMatch;
+    }
+
+    // 协议版本
+    let version = &target[target_len + 1..];
+    if version.len() <= VERSION_PREFIX.len() {
+        return if VERSION_PREFIX.starts_with(version) {
+            LineMatch::Partial
+        } else {
+            LineMatch::NoMatch
+        };
+    }
+    if !version.starts_with(VERSION_PREFIX) || !version[VERSION_PREFIX.len()].is_ascii_digit() {
+        return LineMatch::NoMatch;
+    }
+    match version.get(VERSION_PREFIX.len() + 1) {
+        None | Some(b'\r') => LineMatch::Match,
+        Some(_) => LineMatch::NoMatch,
+    }
+}
+
+// RFC 9110 token 字符
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+// 检查是否是 HTTP 响应
+pub fn is_http_response(data: &[u8]) -> bool {
+    data.starts_with(b"HTTP/1.")
+}
+
+// 检查数据是否是（或可能是尚未收全的）HTTP 响应开头
+fn is_http_response_prefix(data: &[u8]) -> bool {
+    const PREFIX: &[u8] = b"HTTP/1.";
+
+    is_http_response(data) || (data.len() < PREFIX.len() && PREFIX.starts_with(data))
+}
+
+/// 拆分后的消息头部分
+struct MessageHead {
+    /// 请求行或状态行
+    start_line: String,
+    headers: Vec<(String, String)>,
+}
+
+// 拆分消息的首行和消息头字段
+fn parse_head(head: &[u8]) -> Option<MessageHead> {
+    // 消息头中的非 ASCII 字符可能是 UTF-8 或 GBK 编码
+    let text = charset::decode_header(head);
+    let mut lines = text.split("\r\n");
+    let start_line = lines.next()?.to_string();
+
+    let headers = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Some(MessageHead {
+        start_line,
+        headers,
+    })
+}
+
+// 查找指定的消息头
+pub(super) fn find_header(headers: &[(String, String)], name: &str) -> String {
+    headers
+        .iter()
+        .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+        .unwrap_or_default()
+}
+
+// 解析 HTTP 请求，head 为请求行和消息头，body 为已解码的请求体
+pub fn parse_http_request(head: &[u8], body: &[u8]) -> Option<HttpRequest> {
+    let MessageHead {
+        start_line,
+        headers,
+    } = parse_head(head)?;
+
+    // 解析请求行
+    let request_line_parts: Vec<&str> = start_line.split_whitespace().collect();
+    if request_line_parts.len() < 3 {
+        return None;
+    }
+
+    let method = request_line_parts[0].to_string();
+    let path = request_line_parts[1].to_string();
+    let version = request_line_parts[2].to_string();
+
+    // 提取特定的头信息
+    let host = find_header(&headers, "Host");
+    let content_type = find_header(&headers, "Content-Type");
+
+    // 拆分路径和查询字符串，查询参数的字符集通常与页面（及表单）一致
+    let declared = body::charset_param(&content_type);
+    let (route, query) = path.split_once('?').unwrap_or((&path, ""));
+    let url_path = decode_path(route, declared.as_deref());
+    let query = charset::decode_pairs(query, declared.as_deref());
+    let cookies = cookie::parse_cookie_header(&headers);
+
+    let mut request = HttpRequest {
+        id: 0,                 // 将在 HttpDissector 中设置
+        timestamp: 0,          // 将在 HttpDissector 中设置
+        src_ip: String::new(), // 将在 HttpDissector 中设置
+        src_port: 0,           // 将在 HttpDissector 中设置
+        dst_ip: String::new(), // 将在 HttpDissector 中设置
+        dst_port: 0,           // 将在 HttpDissector 中设置
+        method,
+        path,
+        url_path,
+        query,
+        version,
+        host,
+        cookies,
+        content_type,
+        headers,
+        body: HttpBody::default(),
+        form: Vec::new(),
+        json: None,
+        parts: Vec::new(),
+    };
+    set_request_body(&mut request, body.to_vec(), body.len());
+    Some(request)
+}
+
+// 路径部分按百分号编码解码（'+' 保持原样），无法解码时保留原文
+fn decode_path(route: &str, declared: Option<&str>) -> String {
+    let bytes = charset::percent_decode(route, false);
+    match charset::decode(&bytes, declared) {
+        Some(decoded) => decoded.text,
+        None => route.to_string(),
+    }
+}
+
+// 设置请求体，并解析表单和 JSON 视图；size 为请求体的实际长度，大于 data 时表示只收到了开头部分
+fn set_request_body(request: &mut HttpRequest, data: Vec<u8>, size: usize) {
+    let content_encoding = find_header(&request.headers, "Content-Encoding");
+    request.body = HttpBody::with_size(data, size, &request.content_type, &content_encoding);
+    request.form.clear();
+    request.json = None;
+    request.parts.clear();
+
+    if request.body.is_empty() {
+        return;
+    }
+    let text = request.body.text_lossy();
+
+    if body::mime_type(&request.content_type) == "multipart/form-data" {
+        request.parts = multipart::parse_multipart(
+            &request.content_type,
+            request.body.content(),
+            request.body.truncated,
+        );
+    } else if body::mime_type(&request.content_type) == "application/x-www-form-urlencoded" {
+        let declared = body::charset_param(&request.content_type);
+        request.form = charset::decode_pairs(text.trim(), declared.as_deref());
+    } else if body::is_json_type(&request.content_type) || text.trim_start().starts_with(['{', '['])
+    {
+        // 部分接口以 text/plain 等类型发送 JSON，按内容尝试解析
+        request.json = serde_json::from_str(&text).ok();
+    }
+}
+
+// 设置响应体，size 为响应体的实际长度
+fn set_response_body(response: &mut HttpResponse, data: Vec<u8>, size: usize) {
+    let content_encoding = find_header(&response.headers, "Content-Encoding");
+    response.body = HttpBody::with_size(data, size, &response.content_type, &content_encoding);
+}
+
+// 解析 HTTP 响应，head 为状态行和消息头，body 为已解码的响应体
+pub fn parse_http_response(head: &[u8], body: &[u8]) -> Option<HttpResponse> {
+    let MessageHead {
+        start_line,
+        headers,
+    } = parse_head(head)?;
+
+    // 解析状态行，原因短语可以为空或包含空格
+    let mut status_line_parts = start_line.splitn(3, ' ');
+    let version = status_line_parts.next()?.to_string();
+    let status_code = status_line_parts.next()?.trim().parse().ok()?;
+    let reason = status_line_parts.next().unwrap_or("").to_string();
+
+    let content_type = find_header(&headers, "Content-Type");
+
+    let mut response = HttpResponse {
+        id: 0,                 // 将在 HttpDissector 中设置
+        timestamp: 0,          // 将在 HttpDissector 中设置
+        src_ip: String::new(), // 将在 HttpDissector 中设置
+        src_port: 0,           // 将在 HttpDissector 中设置
+        dst_ip: String::new(), // 将在 HttpDissector 中设置
+        dst_port: 0,           // 将在 HttpDissector 中设置
+        version,
+        status_code,
+        reason,
+        content_type,
+        headers,
+        body: HttpBody::default(),
+        cookies: Vec::new(),
+    };
+    set_response_body(&mut response, body.to_vec(), body.len());
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn client_key() -> FlowKey {
+        FlowKey {
+            src_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            src_port: 50000,
+            dst_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            dst_port: 80,
+        }
+    }
+
+    /// 把数据分段送入解析器，返回解析出的请求
+    fn feed(
+        dissector: &mut HttpDissector,
+        stream: &mut StreamBuffer,
+        key: FlowKey,
+        data: &[u8],
+        segment: usize,
+    ) -> Vec<HttpRequest> {
+        let mut requests = Vec::new();
+        for piece in data.chunks(segment) {
+            stream.extend(piece);
+            for message in dissector.process_stream(key, stream, 1_000) {
+                if let HttpMessage::Request(request) = message {
+                    requests.push(request);
+                }
+            }
+        }
+        requests
+    }
+
+    #[test]
+    fn request_line_accepts_extension_methods() {
+        assert!(is_http_request(b"PROPFIND /dav HTTP/1.1\r\n"));
+        assert!(is_http_request(b"GET /?q=\xe4\xb8\xad HTTP/1.0\r\n"));
+        assert!(!is_http_request(b"GET  / HTTP/1.1\r\n"));
+        assert!(!is_http_request(b"GET / HTTP/2.0\r\n"));
+        assert!(!is_http_request(b"\x16\x03\x01\x02\x00"));
+        assert!(is_http_request_prefix(b"POS"));
+        assert!(is_http_request_prefix(b"POST /upload HT"));
+        assert!(!is_http_request_prefix(b"POST /upload HX"));
+    }
+
+    #[test]
+    fn content_length_rejects_conflicting_values() {
+        let headers = |values: &[&str]| {
+            values
+                .iter()
+                .map(|value| ("Content-Length".to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(content_length(&headers(&["12"])), Ok(Some(12)));
+        assert_eq!(content_length(&headers(&["12", "12, 12"])), Ok(Some(12)));
+        assert_eq!(content_length(&headers(&["12", "13"])), Err(()));
+        assert_eq!(content_length(&headers(&["-1"])), Err(()));
+        assert_eq!(content_length(&[]), Ok(None));
+    }
+
+    #[test]
+    fn pipelined_requests_split_across_segments() {
+        let mut dissector = HttpDissector::new();
+        let mut stream = StreamBuffer::detached();
+        let data = b"POST /login HTTP/1.1\r\nHost: a\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: 11\r\n\r\nuser=a&pw=b\
+GET /next HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        let requests = feed(&mut dissector, &mut stream, client_key(), data, 7);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/login");
+        assert_eq!(requests[0].form.len(), 2);
+        assert!(!requests[0].body.truncated);
+        assert_eq!(requests[1].path, "/next");
+        assert!(stream.data().is_empty());
+    }
+
+    #[test]
+    fn chunked_body_with_extensions_and_trailer() {
+        let mut dissector = HttpDissector::new();
+        let mut stream = StreamBuffer::detached();
+        let data = b"POST /api HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n\
+5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\n\
+GET /next HTTP/1.1\r\nHost: a\r\n\r\n";
+
+        for segment in [1, 3, data.len()] {
+            let requests = feed(&mut dissector, &mut stream, client_key(), data, segment);
+            assert_eq!(requests.len(), 2, "segment size {}", segment);
+            assert_eq!(requests[0].body.raw, b"hello world");
+            assert_eq!(requests[1].path, "/next");
+        }
+    }
+
+    #[test]
+    fn endless_header_discards_stream() {
+        let mut dissector = HttpDissector::new();
+        let mut stream = StreamBuffer::detached();
+        let mut data = b"GET / HTTP/1.1\r\n".to_vec();
+        while data.len() <= MAX_HEADER_LEN {
+            data.extend_from_slice(b"X-Padding: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\n");
+        }
+        let requests = feed(&mut dissector, &mut stream, client_key(), &data, 1460);
+        assert!(requests.is_empty());
+        assert!(stream.data().len() < 1460);
+    }
+
+    #[test]
+    fn header_end_found_across_scanned_boundary() {
+        let data = b"GET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        for scanned in 0..data.len() {
+            assert_eq!(find_header_end_from(data, scanned), Some(data.len()));
+        }
+        assert_eq!(find_header_end_from(b"GET", 10), None);
+    }
+
+    #[test]
+    fn invalid_chunk_size_discards_stream() {
+        let mut dissector = HttpDissector::new();
+        let mut stream = StreamBuffer::detached();
+        let data = b"POST /api HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n";
+        let requests = feed(&mut dissector, &mut stream, client_key(), data, data.len());
+        assert!(requests.is_empty());
+        assert!(stream.data().is_empty());
+    }
+
+    #[test]
+    fn large_body_is_emitted_truncated_and_framing_is_kept() {
+        const UPLOAD: usize = 5 * 1024 * 1024;
+        let mut dissector = HttpDissector::new();
+        let mut stream = StreamBuffer::detached();
+        let mut data = format!(
+            "POST /upload HTTP/1.1\r\nHost: a\r\nAuthorization: Bearer t\r\nContent-Length: {}\r\n\r\n",
+            UPLOAD
+        )
+        .into_bytes();
+        data.resize(data.len() + UPLOAD, b'x');
+        data.extend_from_slice(b"GET /next HTTP/1.1\r\nHost: a\r\n\r\n");
+
+        let requests = feed(&mut dissector, &mut stream, client_key(), &data, 64 * 1024);
+        assert_eq!(requests.len(), 2);
+        let upload = &requests[0];
+        assert_eq!(upload.path, "/upload");
+        assert_eq!(find_header(&upload.headers, "Authorization"), "Bearer t");
+        assert!(upload.body.truncated);
+        assert_eq!(upload.body.size, UPLOAD);
+        assert_eq!(upload.body.raw.len(), MAX_BODY_SIZE);
+        assert_eq!(requests[1].path, "/next");
+    }
+
+    #[test]
+    fn large_chunked_body_is_emitted_truncated() {
+        let mut dissector = HttpDissector::new();
+        let mut stream = StreamBuffer::detached();
+        let mut data =
+            b"POST /upload HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        let chunk = vec![b'y'; 0x10000];
+        for _ in 0..80 {
+            data.extend_from_slice(b"10000\r\n");
+            data.extend_from_slice(&chunk);
+            data.extend_from_slice(b"\r\n");
+        }
+        data.extend_from_slice(b"0\r\n\r\nGET /next HTTP/1.1\r\nHost: a\r\n\r\n");
+
+        let requests = feed(&mut dissector, &mut stream, client_key(), &data, 50_000);
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].body.truncated);
+        assert_eq!(requests[0].body.raw.len(), MAX_BODY_SIZE);
+        assert_eq!(requests[1].path, "/next");
+    }
+
+    #[test]
+    fn response_is_paired_with_request() {
+        let mut dissector = HttpDissector::new();
+        let key = client_key();
+        let mut client = StreamBuffer::detached();
+        let mut server = StreamBuffer::detached();
+
+        feed(
+            &mut dissector,
+            &mut client,
+            key,
+            b"HEAD /a HTTP/1.1\r\nHost: a\r\n\r\nGET /b HTTP/1.1\r\nHost: a\r\n\r\n",
+            100,
+        );
+        server.extend(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nHTTP/1.1 404 Not Found\r\nContent-Length: 2\r\n\r\nno");
+        let transactions: Vec<_> = dissector
+            .process_stream(key.reverse(), &mut server, 1_500)
+            .into_iter()
+            .filter_map(|message| match message {
+                HttpMessage::Transaction(transaction) => Some(transaction),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].request.path, "/a");
+        assert!(transactions[0].response.body.is_empty());
+        assert_eq!(transactions[1].request.path, "/b");
+        assert_eq!(transactions[1].response.status_code, 404);
+        assert_eq!(transactions[1].response.body.raw, b"no");
+        assert_eq!(transactions[1].duration_ms, 500);
+    }
+}
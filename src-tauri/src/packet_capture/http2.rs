@@ -0,0 +1,610 @@
+use super::body::MAX_BODY_SIZE;
+use super::hpack::{HeaderField, HpackDecoder};
+use super::reassembly::StreamBuffer;
+use log::debug;
+use std::collections::HashMap;
+
+/// 客户端连接前言（RFC 9113 第 3.4 节）
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+/// 帧头长度
+const FRAME_HEADER_LEN: usize = 9;
+
+const FRAME_DATA: u8 = 0x0;
+const FRAME_HEADERS: u8 = 0x1;
+const FRAME_RST_STREAM: u8 = 0x3;
+const FRAME_PUSH_PROMISE: u8 = 0x5;
+const FRAME_CONTINUATION: u8 = 0x9;
+
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+const FLAG_PADDED: u8 = 0x8;
+const FLAG_PRIORITY: u8 = 0x20;
+
+/// 每个方向最多同时跟踪的流数量
+const MAX_STREAMS: usize = 256;
+/// 消息头块（含 CONTINUATION 帧）的最大长度
+const MAX_HEADER_BLOCK_LEN: usize = 256 * 1024;
+
+/// 检查数据是否是（或可能是尚未收全的）HTTP/2 连接前言
+pub fn is_preface_prefix(data: &[u8]) -> bool {
+    let len = data.len().min(PREFACE.len());
+    len > 0 && data[..len] == PREFACE[..len]
+}
+
+/// HTTP/2 流上一个方向的完整消息
+#[derive(Debug)]
+pub struct Http2Message {
+    pub stream_id: u32,
+    /// 消息头字段，包括 :method、:path、:status 等伪头部
+    pub headers: Vec<HeaderField>,
+    pub body: Vec<u8>,
+}
+
+/// 单方向上正在接收的消息
+#[derive(Debug, Default)]
+struct PartialMessage {
+    headers: Vec<HeaderField>,
+    body: Vec<u8>,
+}
+
+/// 尚未收到 END_HEADERS 的消息头块
+#[derive(Debug)]
+struct HeaderBlock {
+    stream_id: u32,
+    end_stream: bool,
+    /// PUSH_PROMISE 的消息头块只用于维护 HPACK 状态
+    push_promise: bool,
+    fragment: Vec<u8>,
+}
+
+/// 连接一个方向上的解析状态
+#[derive(Debug)]
+struct Direction {
+    hpack: HpackDecoder,
+    /// 客户端方向需要先跳过连接前言
+    expect_preface: bool,
+    continuation: Option<HeaderBlock>,
+    /// 已收到消息头、尚未结束的消息
+    streams: HashMap<u32, PartialMessage>,
+}
+
+impl Direction {
+    fn new(expect_preface: bool) -> Self {
+        Self {
+            hpack: HpackDecoder::new(),
+            expect_preface,
+            continuation: None,
+            streams: HashMap::new(),
+        }
+    }
+}
+
+/// 一个 HTTP/2 连接上两个方向的帧解析状态
+#[derive(Debug)]
+pub struct Http2Connection {
+    client: Direction,
+    server: Direction,
+}
+
+impl Default for Http2Connection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Http2Connection {
+    pub fn new() -> Self {
+        Self {
+            client: Direction::new(true),
+            server: Direction::new(false),
+        }
+    }
+
+    /// 解析字节流中的完整帧，把结束的消息放入 messages
+    /// 格式错误或 HPACK 解码失败时返回 None，此后连接上的数据无法继续解析
+    pub fn process(
+        &mut self,
+        from_client: bool,
+        stream: &mut StreamBuffer,
+        messages: &mut Vec<Http2Message>,
+    ) -> Option<()> {
+        let direction = if from_client {
+            &mut self.client
+        } else {
+            &mut self.server
+        };
+
+        if direction.expect_preface {
+            let data = stream.data();
+            if data.len() < PREFACE.len() {
+                return is_preface_prefix(data).then_some(());
+            }
+            if !data.starts_with(PREFACE) {
+                return None;
+            }
+            stream.consume(PREFACE.len());
+            direction.expect_preface = false;
+        }
+
+        let mut reset = Vec::new();
+        loop {
+            let data = stream.data();
+            if data.len() < FRAME_HEADER_LEN {
+                break;
+            }
+            let len = usize::from(data[0]) << 16 | usize::from(data[1]) << 8 | usize::from(data[2]);
+            if data.len() < FRAME_HEADER_LEN + len {
+                break;
+            }
+            let frame_type = data[3];
+            let flags = data[4];
+            let stream_id = u32::from_be_bytes([data[5], data[6], data[7], data[8]]) & 0x7fff_ffff;
+            let payload = &data[FRAME_HEADER_LEN..FRAME_HEADER_LEN + len];
+
+            if frame_type == FRAME_RST_STREAM {
+                reset.push(stream_id);
+            }
+            direction.process_frame(frame_type, flags, stream_id, payload, messages)?;
+            stream.consume(FRAME_HEADER_LEN + len);
+        }
+
+        // RST_STREAM 同时结束两个方向上的消息，保留已收到的请求
+        for stream_id in reset {
+            for (side, is_client) in [(&mut self.client, true), (&mut self.server, false)] {
+                if let Some(message) = side.streams.remove(&stream_id) {
+                    if is_client {
+                        debug!("HTTP/2 流 {} 被重置，保留已收到的请求", stream_id);
+                        messages.push(Http2Message {
+                            stream_id,
+                            headers: message.headers,
+                            body: message.body,
+                        });
+                    }
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+impl Direction {
+    fn process_frame(
+        &mut self,
+        frame_type: u8,
+        flags: u8,
+        stream_id: u32,
+        payload: &[u8],
+        messages: &mut Vec<Http2Message>,
+    ) -> Option<()> {
+        // 消息头块必须由连续的 CONTINUATION 帧完成
+        if let Some(mut block) = self.continuation.take() {
+            if frame_type != FRAME_CONTINUATION || stream_id != block.stream_id {
+                return None;
+            }
+            block.fragment.extend_from_slice(payload);
+            if block.fragment.len() > MAX_HEADER_BLOCK_LEN {
+                return None;
+            }
+            if flags & FLAG_END_HEADERS == 0 {
+                self.continuation = Some(block);
+                return Some(());
+            }
+            return self.finish_header_block(block, messages);
+        }
+
+        match frame_type {
+            FRAME_DATA => {
+                let data = strip_padding(flags, payload)?;
+                if let Some(message) = self.streams.get_mut(&stream_id) {
+                    // 长时间的流式响应只保留开头部分，多保留一个字节以便标记截断
+                    let available = (MAX_BODY_SIZE + 1).saturating_sub(message.body.len());
+                    message
+                        .body
+                        .extend_from_slice(&data[..data.len().min(available)]);
+                }
+                if flags & FLAG_END_STREAM != 0 {
+                    self.finish_message(stream_id, messages);
+                }
+            }
+            FRAME_HEADERS => {
+                let mut fragment = strip_padding(flags, payload)?;
+                if flags & FLAG_PRIORITY != 0 {
+                    fragment = fragment.get(5..)?;
+                }
+                let block = HeaderBlock {
+                    stream_id,
+                    end_stream: flags & FLAG_END_STREAM != 0,
+                    push_promise: false,
+                    fragment: fragment.to_vec(),
+                };
+                if flags & FLAG_END_HEADERS == 0 {
+                    self.continuation = Some(block);
+                } else {
+                    self.finish_header_block(block, messages)?;
+                }
+            }
+            FRAME_PUSH_PROMISE => {
+                // 跳过被承诺的流标识
+                let fragment = strip_padding(flags, payload)?.get(4..)?;
+                let block = HeaderBlock {
+                    stream_id,
+                    end_stream: false,
+                    push_promise: true,
+                    fragment: fragment.to_vec(),
+                };
+                if flags & FLAG_END_HEADERS == 0 {
+                    self.continuation = Some(block);
+                } else {
+                    self.finish_header_block(block, messages)?;
+                }
+            }
+            FRAME_CONTINUATION => return None,
+            // SETTINGS、PING、WINDOW_UPDATE 等帧不影响消息内容
+            _ => {}
+        }
+        Some(())
+    }
+
+    /// 解码完整的消息头块，并记录到对应的流
+    fn finish_header_block(
+        &mut self,
+        block: HeaderBlock,
+        messages: &mut Vec<Http2Message>,
+    ) -> Option<()> {
+        let headers = self.hpack.decode(&block.fragment)?;
+        if block.push_promise {
+            return Some(());
+        }
+
+        match self.streams.get_mut(&block.stream_id) {
+            // 已有消息头时是尾部字段
+            Some(message) => message.headers.extend(headers),
+            None => {
+                // 1xx 临时响应之后还会有最终响应
+                let informational = headers
+                    .iter()
+                    .find(|(name, _)| name == b":status")
+                    .is_some_and(|(_, value)| value.first() == Some(&b'1'));
+                if informational {
+                    return Some(());
+                }
+                if self.streams.len() >= MAX_STREAMS {
+                    debug!(
+                        "HTTP/2 连接上未结束的流过多，丢弃新的流 {}",
+                        block.stream_id
+                    );
+                    return Some(());
+                }
+                self.streams.insert(
+                    block.stream_id,
+                    PartialMessage {
+                        headers,
+                        body: Vec::new(),
+                    },
+                );
+            }
+        }
+
+        if block.end_stream {
+            self.finish_message(block.stream_id, messages);
+        }
+        Some(())
+    }
+
+    fn finish_message(&mut self, stream_id: u32, messages: &mut Vec<Http2Message>) {
+        if let Some(message) = self.streams.remove(&stream_id) {
+            messages.push(Http2Message {
+                stream_id,
+                headers: message.headers,
+                body: message.body,
+            });
+        }
+    }
+}
+
+/// 去掉 DATA、HEADERS、PUSH_PROMISE 帧的填充
+fn strip_padding(flags: u8, payload: &[u8]) -> Option<&[u8]> {
+    if flags & FLAG_PADDED == 0 {
+        return Some(payload);
+    }
+    let pad_len = usize::from(*payload.first()?);
+    let end = payload.len().checked_sub(pad_len)?;
+    payload.get(1..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: bool = true;
+    const SERVER: bool = false;
+
+    fn frame(frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let len = payload.len() as u32;
+        let mut bytes = len.to_be_bytes()[1..].to_vec();
+        bytes.extend_from_slice(&[frame_type, flags]);
+        bytes.extend_from_slice(&stream_id.to_be_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// 不使用索引的字面量头部字段（名称和值都不超过 126 字节）
+    fn literal(name: &str, value: &str) -> Vec<u8> {
+        let mut bytes = vec![0x00, name.len() as u8];
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(value.len() as u8);
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    /// GET / 请求的消息头块：:method GET、:scheme http、:path / 和 host
+    fn request_block() -> Vec<u8> {
+        let mut block = vec![0x82, 0x86, 0x84];
+        block.extend(literal("host", "example.com"));
+        block
+    }
+
+    fn status_block(status: &str) -> Vec<u8> {
+        // 以静态表第 8 项 :status 为名称的字面量
+        let mut block = vec![0x08, status.len() as u8];
+        block.extend_from_slice(status.as_bytes());
+        block
+    }
+
+    fn header(headers: &[HeaderField], name: &str) -> Option<String> {
+        headers
+            .iter()
+            .find(|(field, _)| field == name.as_bytes())
+            .map(|(_, value)| String::from_utf8(value.clone()).unwrap())
+    }
+
+    fn run(
+        connection: &mut Http2Connection,
+        from_client: bool,
+        stream: &mut StreamBuffer,
+        bytes: &[u8],
+    ) -> Option<Vec<Http2Message>> {
+        stream.extend(bytes);
+        let mut messages = Vec::new();
+        connection.process(from_client, stream, &mut messages)?;
+        Some(messages)
+    }
+
+    /// 已跳过连接前言的客户端方向
+    fn client_stream(connection: &mut Http2Connection) -> StreamBuffer {
+        let mut stream = StreamBuffer::detached();
+        run(connection, CLIENT, &mut stream, PREFACE).unwrap();
+        stream
+    }
+
+    #[test]
+    fn preface_split_across_segments() {
+        let mut connection = Http2Connection::new();
+        let mut stream = StreamBuffer::detached();
+        for chunk in PREFACE[..20].chunks(7) {
+            let messages = run(&mut connection, CLIENT, &mut stream, chunk).unwrap();
+            assert!(messages.is_empty());
+            assert!(is_preface_prefix(stream.data()));
+        }
+        // 前言未收全时不消费数据
+        assert_eq!(stream.data(), &PREFACE[..20]);
+
+        let mut rest = PREFACE[20..].to_vec();
+        rest.extend(frame(
+            FRAME_HEADERS,
+            FLAG_END_HEADERS | FLAG_END_STREAM,
+            1,
+            &request_block(),
+        ));
+        let messages = run(&mut connection, CLIENT, &mut stream, &rest).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(header(&messages[0].headers, ":path").as_deref(), Some("/"));
+        assert!(stream.data().is_empty());
+    }
+
+    #[test]
+    fn invalid_preface_is_rejected() {
+        assert!(!is_preface_prefix(b""));
+        assert!(!is_preface_prefix(b"GET / HTTP/1.1\r\n"));
+        assert!(is_preface_prefix(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\nextra"));
+
+        let mut connection = Http2Connection::new();
+        let mut stream = StreamBuffer::detached();
+        assert!(run(&mut connection, CLIENT, &mut stream, b"PRI * HTTP/1.1").is_none());
+    }
+
+    #[test]
+    fn headers_with_continuation() {
+        let mut connection = Http2Connection::new();
+        let mut stream = client_stream(&mut connection);
+        let block = request_block();
+        let (first, second) = block.split_at(4);
+
+        let mut bytes = frame(FRAME_HEADERS, FLAG_END_STREAM, 1, first);
+        bytes.extend(frame(FRAME_CONTINUATION, 0, 1, &second[..3]));
+        bytes.extend(frame(FRAME_CONTINUATION, FLAG_END_HEADERS, 1, &second[3..]));
+        // 帧头和帧负载被拆开时等待后续数据
+        let messages = run(&mut connection, CLIENT, &mut stream, &bytes[..20]).unwrap();
+        assert!(messages.is_empty());
+        let messages = run(&mut connection, CLIENT, &mut stream, &bytes[20..]).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].stream_id, 1);
+        assert_eq!(
+            header(&messages[0].headers, ":method").as_deref(),
+            Some("GET")
+        );
+        assert_eq!(
+            header(&messages[0].headers, "host").as_deref(),
+            Some("example.com")
+        );
+    }
+
+    #[test]
+    fn interrupted_continuation_is_invalid() {
+        let block = request_block();
+        for next in [
+            // 消息头块中间出现其他帧
+            frame(FRAME_DATA, 0, 1, b"x"),
+            // 其他流上的 CONTINUATION
+            frame(FRAME_CONTINUATION, FLAG_END_HEADERS, 3, &block[4..]),
+        ] {
+            let mut connection = Http2Connection::new();
+            let mut stream = client_stream(&mut connection);
+            let mut bytes = frame(FRAME_HEADERS, 0, 1, &block[..4]);
+            bytes.extend(next);
+            assert!(run(&mut connection, CLIENT, &mut stream, &bytes).is_none());
+        }
+
+        // 没有前面的 HEADERS 帧
+        let mut connection = Http2Connection::new();
+        let mut stream = client_stream(&mut connection);
+        let bytes = frame(FRAME_CONTINUATION, FLAG_END_HEADERS, 1, &block);
+        assert!(run(&mut connection, CLIENT, &mut stream, &bytes).is_none());
+    }
+
+    #[test]
+    fn padding_is_stripped() {
+        assert_eq!(strip_padding(0, b"abc"), Some(&b"abc"[..]));
+        assert_eq!(
+            strip_padding(FLAG_PADDED, &[2, b'a', 0, 0]),
+            Some(&b"a"[..])
+        );
+        // 填充占满剩余负载
+        assert_eq!(strip_padding(FLAG_PADDED, &[2, 0, 0]), Some(&b""[..]));
+        // pad_len 等于或超过负载长度
+        assert_eq!(strip_padding(FLAG_PADDED, &[3, 0, 0]), None);
+        assert_eq!(strip_padding(FLAG_PADDED, &[0xff, 0]), None);
+        assert_eq!(strip_padding(FLAG_PADDED, &[]), None);
+
+        // 带填充和优先级的 HEADERS，以及带填充的 DATA
+        let mut connection = Http2Connection::new();
+        let mut stream = client_stream(&mut connection);
+        let mut payload = vec![3, 0, 0, 0, 0, 16];
+        payload.extend(request_block());
+        payload.extend([0; 3]);
+        let mut bytes = frame(
+            FRAME_HEADERS,
+            FLAG_END_HEADERS | FLAG_PADDED | FLAG_PRIORITY,
+            1,
+            &payload,
+        );
+        bytes.extend(frame(FRAME_DATA, FLAG_PADDED, 1, &[2, b'h', b'i', 0, 0]));
+        bytes.extend(frame(FRAME_DATA, FLAG_PADDED | FLAG_END_STREAM, 1, &[1, 0]));
+        let messages = run(&mut connection, CLIENT, &mut stream, &bytes).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(header(&messages[0].headers, ":path").as_deref(), Some("/"));
+        assert_eq!(messages[0].body, b"hi");
+    }
+
+    #[test]
+    fn padding_covering_whole_payload_is_invalid() {
+        for frame_type in [FRAME_DATA, FRAME_HEADERS] {
+            let mut connection = Http2Connection::new();
+            let mut stream = client_stream(&mut connection);
+            let mut bytes = frame(FRAME_HEADERS, FLAG_END_HEADERS, 1, &request_block());
+            // pad_len == payload.len()，填充长度没有算上 pad_len 字节本身
+            bytes.extend(frame(
+                frame_type,
+                FLAG_PADDED | FLAG_END_HEADERS,
+                1,
+                &[4, 0, 0, 0],
+            ));
+            assert!(run(&mut connection, CLIENT, &mut stream, &bytes).is_none());
+        }
+    }
+
+    #[test]
+    fn reset_stream_keeps_partial_request() {
+        let mut connection = Http2Connection::new();
+        let mut client = client_stream(&mut connection);
+        let mut server = StreamBuffer::detached();
+
+        let mut bytes = frame(FRAME_HEADERS, FLAG_END_HEADERS, 1, &request_block());
+        bytes.extend(frame(FRAME_DATA, 0, 1, b"part"));
+        let messages = run(&mut connection, CLIENT, &mut client, &bytes).unwrap();
+        assert!(messages.is_empty());
+        let bytes = frame(FRAME_HEADERS, FLAG_END_HEADERS, 1, &status_block("200"));
+        assert!(run(&mut connection, SERVER, &mut server, &bytes)
+            .unwrap()
+            .is_empty());
+
+        // 服务端重置流：保留请求，丢弃未结束的响应
+        let bytes = frame(FRAME_RST_STREAM, 0, 1, &[0, 0, 0, 8]);
+        let messages = run(&mut connection, SERVER, &mut server, &bytes).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].stream_id, 1);
+        assert_eq!(
+            header(&messages[0].headers, ":method").as_deref(),
+            Some("GET")
+        );
+        assert_eq!(messages[0].body, b"part");
+
+        // 流已结束，后续 DATA 帧被忽略
+        let bytes = frame(FRAME_DATA, FLAG_END_STREAM, 1, b"late");
+        assert!(run(&mut connection, CLIENT, &mut client, &bytes)
+            .unwrap()
+            .is_empty());
+        assert!(run(&mut connection, SERVER, &mut server, &bytes)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn informational_response_before_final_headers() {
+        let mut connection = Http2Connection::new();
+        let mut server = StreamBuffer::detached();
+
+        let mut bytes = frame(FRAME_HEADERS, FLAG_END_HEADERS, 1, &status_block("100"));
+        bytes.extend(frame(
+            FRAME_HEADERS,
+            FLAG_END_HEADERS,
+            1,
+            &status_block("103"),
+        ));
+        bytes.extend(frame(
+            FRAME_HEADERS,
+            FLAG_END_HEADERS,
+            1,
+            &status_block("200"),
+        ));
+        bytes.extend(frame(FRAME_DATA, 0, 1, b"ok"));
+        // 尾部字段
+        bytes.extend(frame(
+            FRAME_HEADERS,
+            FLAG_END_HEADERS | FLAG_END_STREAM,
+            1,
+            &literal("grpc-status", "0"),
+        ));
+        let messages = run(&mut connection, SERVER, &mut server, &bytes).unwrap();
+        assert_eq!(messages.len(), 1);
+        let headers = &messages[0].headers;
+        assert_eq!(
+            headers
+                .iter()
+                .filter(|(name, _)| name == b":status")
+                .count(),
+            1
+        );
+        assert_eq!(header(headers, ":status").as_deref(), Some("200"));
+        assert_eq!(header(headers, "grpc-status").as_deref(), Some("0"));
+        assert_eq!(messages[0].body, b"ok");
+    }
+
+    #[test]
+    fn data_body_truncated_at_max_body_size() {
+        let mut connection = Http2Connection::new();
+        let mut server = StreamBuffer::detached();
+        let chunk = vec![b'a'; MAX_BODY_SIZE / 2 + 10];
+
+        let mut bytes = frame(FRAME_HEADERS, FLAG_END_HEADERS, 1, &status_block("200"));
+        for _ in 0..3 {
+            bytes.extend(frame(FRAME_DATA, 0, 1, &chunk));
+        }
+        bytes.extend(frame(FRAME_DATA, FLAG_END_STREAM, 1, b"end"));
+        let messages = run(&mut connection, SERVER, &mut server, &bytes).unwrap();
+        assert_eq!(messages.len(), 1);
+        // 多保留一个字节用于标记截断
+        assert_eq!(messages[0].body.len(), MAX_BODY_SIZE + 1);
+        assert!(server.data().is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use super::settings;
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// 没有配置密钥日志文件时读取的环境变量，浏览器使用同一个变量写入密钥
+const KEY_LOG_ENV: &str = "SSLKEYLOGFILE";
+/// 后台线程检查密钥日志文件新内容的间隔
+const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+// 当前加载的密钥日志（首次使用时按设置加载，并启动后台读取线程）
+static KEY_LOG: OnceCell<KeyLogState> = OnceCell::new();
+
+/// 一个 TLS 会话的密钥
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionSecrets {
+    /// TLS 1.2 主密钥（CLIENT_RANDOM）
+    pub master_secret: Option<Vec<u8>>,
+    /// TLS 1.3 握手阶段的流量密钥
+    pub client_handshake_traffic_secret: Option<Vec<u8>>,
+    pub server_handshake_traffic_secret: Option<Vec<u8>>,
+    /// TLS 1.3 应用数据的流量密钥
+    pub client_traffic_secret_0: Option<Vec<u8>>,
+    pub server_traffic_secret_0: Option<Vec<u8>>,
+}
+
+/// NSS 密钥日志（SSLKEYLOGFILE 格式），按 ClientHello 中的客户端随机数索引
+#[derive(Debug, Clone, Default)]
+pub struct KeyLog {
+    sessions: HashMap<[u8; 32], SessionSecrets>,
+}
+
+impl KeyLog {
+    /// 解析密钥日志内容，忽略注释和无法识别的行
+    pub fn parse(content: &str) -> Self {
+        let mut log = Self::default();
+        log.extend(content);
+        log
+    }
+
+    /// 追加解析新的内容
+    pub fn extend(&mut self, content: &str) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (label, client_random, secret) = match (fields.next(), fields.next(), fields.next())
+            {
+                (Some(label), Some(client_random), Some(secret)) => (label, client_random, secret),
+                _ => continue,
+            };
+            let client_random: [u8; 32] =
+                match decode_hex(client_random).and_then(|random| random.try_into().ok()) {
+                    Some(random) => random,
+                    None => continue,
+                };
+            let secret = match decode_hex(secret) {
+                Some(secret) => secret,
+                None => continue,
+            };
+
+            let secrets = self.sessions.entry(client_random).or_default();
+            match label {
+                "CLIENT_RANDOM" => secrets.master_secret = Some(secret),
+                "CLIENT_HANDSHAKE_TRAFFIC_SECRET" => {
+                    secrets.client_handshake_traffic_secret = Some(secret)
+                }
+                "SERVER_HANDSHAKE_TRAFFIC_SECRET" => {
+                    secrets.server_handshake_traffic_secret = Some(secret)
+                }
+                "CLIENT_TRAFFIC_SECRET_0" => secrets.client_traffic_secret_0 = Some(secret),
+                "SERVER_TRAFFIC_SECRET_0" => secrets.server_traffic_secret_0 = Some(secret),
+                _ => {}
+            }
+        }
+        self.sessions
+            .retain(|_, secrets| *secrets != SessionSecrets::default());
+    }
+
+    pub fn get(&self, client_random: &[u8; 32]) -> Option<&SessionSecrets> {
+        self.sessions.get(client_random)
+    }
+
+    /// 包含密钥的会话数量
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
+
+/// 密钥日志文件及其读取进度，浏览器会不断向文件追加新的会话
+#[derive(Debug, Default)]
+struct KeyLogFile {
+    path: Option<PathBuf>,
+    log: Arc<KeyLog>,
+    /// 已解析到的字节数（只解析完整的行）
+    offset: u64,
+}
+
+impl KeyLogFile {
+    fn open(path: Option<PathBuf>) -> Self {
+        let mut file = Self {
+            path,
+            ..Self::default()
+        };
+        if let Err(e) = file.reload() {
+            warn!("读取 TLS 密钥日志失败: {}", e);
+        }
+        file
+    }
+
+    /// 读取文件中新追加的内容，文件变短时（被清空或替换）重新读取，返回密钥是否有变化
+    fn reload(&mut self) -> Result<bool> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+
+        let mut file = File::open(path)
+            .map_err(|e| anyhow!("无法打开密钥日志文件 {}: {}", path.display(), e))?;
+        let len = file.metadata()?.len();
+        let mut changed = false;
+        if len < self.offset {
+            debug!("密钥日志文件变短，重新读取: {}", path.display());
+            self.log = Arc::default();
+            self.offset = 0;
+            changed = true;
+        }
+        if len == self.offset {
+            return Ok(changed);
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut appended = Vec::new();
+        file.read_to_end(&mut appended)?;
+        // 最后一行可能还没写完，留到下次读取
+        let complete = match appended.iter().rposition(|&b| b == b'\n') {
+            Some(end) => end + 1,
+            None => return Ok(changed),
+        };
+        // 查找线程仍持有旧快照时复制一份再追加
+        Arc::make_mut(&mut self.log).extend(&String::from_utf8_lossy(&appended[..complete]));
+        self.offset += complete as u64;
+        debug!(
+            "已读取密钥日志 {}，共 {} 个会话",
+            path.display(),
+            self.log.len()
+        );
+        Ok(true)
+    }
+
+    /// 供查找使用的快照，没有配置文件时为 None
+    fn snapshot(&self) -> Option<Arc<KeyLog>> {
+        self.path.as_ref().map(|_| Arc::clone(&self.log))
+    }
+}
+
+/// 密钥日志的读取状态和查找使用的快照
+///
+/// 文件只在后台线程和修改设置时读取，解析线程查找密钥时只复制快照的 Arc，不会等待文件 I/O。
+struct KeyLogState {
+    file: Mutex<KeyLogFile>,
+    snapshot: Mutex<Option<Arc<KeyLog>>>,
+    /// 唤醒后台线程立即检查文件（找不到密钥时，浏览器可能刚刚写入）
+    reload: SyncSender<()>,
+}
+
+impl KeyLogState {
+    /// 读取文件新追加的内容，有变化时更新快照
+    fn refresh(&self) {
+        let mut file = self.file.lock().unwrap();
+        match file.reload() {
+            Ok(true) => *self.snapshot.lock().unwrap() = file.snapshot(),
+            Ok(false) => {}
+            Err(e) => debug!("重新读取 TLS 密钥日志失败: {}", e),
+        }
+    }
+
+    fn replace(&self, file: KeyLogFile) {
+        let mut current = self.file.lock().unwrap();
+        *current = file;
+        *self.snapshot.lock().unwrap() = current.snapshot();
+    }
+}
+
+fn key_log() -> &'static KeyLogState {
+    KEY_LOG.get_or_init(|| {
+        let file = KeyLogFile::open(key_log_path().map(PathBuf::from));
+        let (reload, requests) = mpsc::sync_channel(1);
+        thread::spawn(move || reload_loop(requests));
+        KeyLogState {
+            snapshot: Mutex::new(file.snapshot()),
+            file: Mutex::new(file),
+            reload,
+        }
+    })
+}
+
+/// 后台线程：定期或被唤醒时读取密钥日志文件的新内容
+fn reload_loop(requests: Receiver<()>) {
+    loop {
+        match requests.recv_timeout(RELOAD_INTERVAL) {
+            Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        if let Some(state) = KEY_LOG.get() {
+            state.refresh();
+        }
+    }
+}
+
+/// 当前使用的密钥日志文件：设置中的路径，没有时使用 SSLKEYLOGFILE 环境变量
+pub fn key_log_path() -> Option<String> {
+    settings::get_settings()
+        .key_log_file
+        .or_else(|| std::env::var(KEY_LOG_ENV).ok())
+        .filter(|path| !path.trim().is_empty())
+}
+
+/// 设置并加载密钥日志文件，path 为空时恢复使用环境变量，返回包含密钥的会话数量
+pub fn set_key_log_file(path: Option<String>) -> Result<usize> {
+    let path = path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty());
+    if let Some(path) = &path {
+        if !Path::new(path).is_file() {
+            return Err(anyhow!("密钥日志文件不存在: {}", path));
+        }
+    }
+
+    settings::update_settings(|s| s.key_log_file = path)?;
+    let file = KeyLogFile::open(key_log_path().map(PathBuf::from));
+    let sessions = file.log.len();
+    match &file.path {
+        Some(path) => info!(
+            "已加载 TLS 密钥日志 {}，共 {} 个会话",
+            path.display(),
+            sessions
+        ),
+        None => info!("未配置 TLS 密钥日志，HTTPS 流量将无法解密"),
+    }
+    key_log().replace(file);
+    Ok(sessions)
+}
+
+/// 是否配置了密钥日志文件，没有时不需要跟踪解密状态
+pub fn is_enabled() -> bool {
+    key_log().snapshot.lock().unwrap().is_some()
+}
+
+/// 按客户端随机数查找会话密钥，找不到时唤醒后台线程读取文件新追加的内容
+pub fn lookup(client_random: &[u8; 32]) -> Option<SessionSecrets> {
+    let state = key_log();
+    let snapshot = state.snapshot.lock().unwrap().clone()?;
+    let secrets = snapshot.get(client_random).cloned();
+    if secrets.is_none() {
+        // 后台线程已有待处理的唤醒时忽略
+        let _ = state.reload.try_send(());
+    }
+    secrets
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
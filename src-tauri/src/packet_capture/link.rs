@@ -0,0 +1,145 @@
+use pcap::Linktype;
+
+// pcap_datalink 返回的 DLT 值（部分类型在抓包文件中的 LINKTYPE 值不同）
+const DLT_NULL: i32 = 0;
+const DLT_EN10MB: i32 = 1;
+const DLT_RAW: i32 = 12;
+const LINKTYPE_RAW: i32 = 101;
+const DLT_LOOP: i32 = 108;
+const DLT_LINUX_SLL: i32 = 113;
+const DLT_IPV4: i32 = 228;
+const DLT_IPV6: i32 = 229;
+const DLT_LINUX_SLL2: i32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+const ETHERTYPE_QINQ_OLD: u16 = 0x9100;
+
+/// 捕获句柄的链路层类型，决定如何从数据包中取出 IP 数据包
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkType {
+    /// 以太网，可带 802.1Q/802.1ad VLAN 标签
+    Ethernet,
+    /// 没有链路层首部的 IP 数据包（tun/VPN 网卡）
+    Raw,
+    /// BSD/macOS 回环接口和 Npcap 回环网卡，以 4 字节协议族开头
+    Null,
+    /// Linux cooked capture（any 设备）
+    LinuxSll,
+    /// Linux cooked capture v2
+    LinuxSll2,
+}
+
+impl LinkType {
+    /// 根据 pcap 返回的链路层类型选择解码方式，不支持时返回 None
+    pub fn from_pcap(linktype: Linktype) -> Option<Self> {
+        match linktype.0 {
+            DLT_EN10MB => Some(LinkType::Ethernet),
+            DLT_RAW | LINKTYPE_RAW | DLT_IPV4 | DLT_IPV6 => Some(LinkType::Raw),
+            DLT_NULL | DLT_LOOP => Some(LinkType::Null),
+            DLT_LINUX_SLL => Some(LinkType::LinuxSll),
+            DLT_LINUX_SLL2 => Some(LinkType::LinuxSll2),
+            _ => None,
+        }
+    }
+
+    /// 去掉链路层首部，返回其中的 IPv4/IPv6 数据包，其他协议返回 None
+    pub fn ip_packet(self, frame: &[u8]) -> Option<&[u8]> {
+        match self {
+            LinkType::Ethernet => ether_payload(frame, read_u16(frame, 12)?, 14),
+            LinkType::Raw => ip_payload(frame),
+            // 协议族的字节序和取值因系统而异，直接按 IP 版本号判断
+            LinkType::Null => ip_payload(frame.get(4..)?),
+            LinkType::LinuxSll => ether_payload(frame, read_u16(frame, 14)?, 16),
+            LinkType::LinuxSll2 => ether_payload(frame, read_u16(frame, 0)?, 20),
+        }
+    }
+
+    /// 生成实际设置到捕获句柄上的过滤表达式
+    /// 以太网上带 VLAN 标签的数据包需要用 vlan 关键字调整偏移量后再匹配一次
+    pub fn capture_filter(self, bpf: &str) -> String {
+        match self {
+            LinkType::Ethernet => format!("({0}) or (vlan and ({0}))", bpf),
+            _ => bpf.to_string(),
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *data.get(offset)?,
+        *data.get(offset + 1)?,
+    ]))
+}
+
+/// 按以太网类型取出 IP 数据包，跳过 VLAN 标签
+/// offset 为以太网类型字段之后的位置
+fn ether_payload(frame: &[u8], mut ether_type: u16, mut offset: usize) -> Option<&[u8]> {
+    loop {
+        match ether_type {
+            ETHERTYPE_VLAN | ETHERTYPE_QINQ | ETHERTYPE_QINQ_OLD => {
+                // 标签控制信息之后是内层的以太网类型
+                ether_type = read_u16(frame, offset + 2)?;
+                offset += 4;
+            }
+            // 和其他链路类型一样检查版本号，空的负载也在这里排除
+            ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => return ip_payload(frame.get(offset..)?),
+            _ => return None,
+        }
+    }
+}
+
+/// 检查 IP 版本号，只保留 IPv4/IPv6 数据包
+fn ip_payload(packet: &[u8]) -> Option<&[u8]> {
+    match packet.first()? >> 4 {
+        4 | 6 => Some(packet),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ethernet(ether_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ether_type.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn ethernet_ip_payload() {
+        let frame = ethernet(ETHERTYPE_IPV4, &[0x45, 0, 0, 20]);
+        assert_eq!(
+            LinkType::Ethernet.ip_packet(&frame),
+            Some(&[0x45, 0, 0, 20][..])
+        );
+    }
+
+    #[test]
+    fn ethernet_vlan_payload() {
+        let mut inner = vec![0, 1];
+        inner.extend_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+        inner.push(0x60);
+        let frame = ethernet(ETHERTYPE_VLAN, &inner);
+        assert_eq!(LinkType::Ethernet.ip_packet(&frame), Some(&[0x60][..]));
+    }
+
+    #[test]
+    fn empty_or_invalid_payload_rejected() {
+        assert_eq!(
+            LinkType::Ethernet.ip_packet(&ethernet(ETHERTYPE_IPV4, &[])),
+            None
+        );
+        assert_eq!(
+            LinkType::Ethernet.ip_packet(&ethernet(ETHERTYPE_IPV4, &[0x15])),
+            None
+        );
+        assert_eq!(LinkType::LinuxSll2.ip_packet(&[0x08, 0x00]), None);
+        assert_eq!(LinkType::Null.ip_packet(&[2, 0, 0, 0]), None);
+        assert_eq!(LinkType::Raw.ip_packet(&[]), None);
+    }
+}
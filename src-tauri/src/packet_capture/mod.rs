@@ -0,0 +1,1332 @@
+pub mod body;
+pub mod ca;
+pub mod charset;
+pub mod compression;
+pub mod cookie;
+pub mod device;
+pub mod filter;
+pub mod fragment;
+pub mod hpack;
+pub mod http;
+pub mod http2;
+pub mod keylog;
+pub mod link;
+pub mod multipart;
+pub mod permissions;
+pub mod pipeline;
+pub mod proxy;
+pub mod reassembly;
+pub mod settings;
+pub mod tls;
+pub mod tls_decrypt;
+pub mod websocket;
+
+use anyhow::{anyhow, Result};
+use body::HttpBody;
+use etherparse::{InternetSlice, SlicedPacket, TransportSlice};
+use fragment::IpDefragmenter;
+use log::{debug, error, info, warn};
+use once_cell::sync::OnceCell;
+use pcap::Capture;
+use http::{HttpDissector, HttpMessage};
+use link::LinkType;
+use pipeline::{Dispatch, PacketDispatcher};
+use reassembly::{FlowKey, TcpReassembler, TcpSegment};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::ipc::Channel;
+
+// 每个解析线程等待解析的数据包队列容量
+const PACKET_QUEUE_CAPACITY: usize = 4096;
+// 捕获统计的推送间隔
+const STATUS_PUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// 运行状态控制
+static CAPTURE_RUNNING: OnceCell<Arc<AtomicBool>> = OnceCell::new();
+static CAPTURE_THREAD: OnceCell<Arc<Mutex<Option<thread::JoinHandle<()>>>>> = OnceCell::new();
+static CAPTURE_STATUS: OnceCell<Arc<Mutex<CaptureStatus>>> = OnceCell::new();
+static APP_HANDLE: OnceCell<tauri::AppHandle> = OnceCell::new();
+static STATUS_CHANNEL: OnceCell<Arc<Mutex<Option<Channel<CaptureStatus>>>>> = OnceCell::new();
+static HTTP_CHANNEL: OnceCell<Arc<Mutex<Option<Channel<HttpRequest>>>>> = OnceCell::new();
+static TRANSACTION_CHANNEL: OnceCell<Arc<Mutex<Option<Channel<HttpTransaction>>>>> = OnceCell::new();
+static TLS_CHANNEL: OnceCell<Arc<Mutex<Option<Channel<TlsConnection>>>>> = OnceCell::new();
+static WEBSOCKET_CHANNEL: OnceCell<Arc<Mutex<Option<Channel<WebSocketMessage>>>>> = OnceCell::new();
+
+// 捕获状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureStatus {
+    pub running: bool,
+    pub message: String,
+    pub device_name: String,
+    pub start_time: u64,
+    /// 各网卡（或抓包文件）的捕获统计
+    pub interfaces: Vec<InterfaceStatus>,
+    /// 解析线程的统计
+    pub stats: CaptureStats,
+}
+
+// 单个网卡的捕获统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterfaceStatus {
+    pub name: String,
+    pub running: bool,
+    pub packets: u64,
+    pub bytes: u64,
+    /// pcap 统计：内核收到的数据包数（各平台计数方式略有不同，回放文件时为 0）
+    pub received: u64,
+    /// pcap 统计：因缓冲区已满被内核丢弃的数据包数
+    pub dropped: u64,
+    /// pcap 统计：被网卡或驱动丢弃的数据包数
+    pub if_dropped: u64,
+    /// 因解析队列已满被丢弃的数据包数（解析跟不上捕获速度）
+    pub queue_dropped: u64,
+    pub error: Option<String>,
+}
+
+// 各解析线程的汇总统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureStats {
+    /// 成功解析出 IP 数据包的数量
+    pub packets_parsed: u64,
+    /// 无法解析的数据包数量（非 IP 协议或首部损坏）
+    pub packets_failed: u64,
+    /// 解析出的 HTTP 请求数量
+    pub http_requests: u64,
+    /// 最近一个统计周期内每秒处理的字节数
+    pub bytes_per_sec: f64,
+    /// 当前跟踪的 TCP 流数量（单方向计数）
+    pub flows: usize,
+    /// 正在重组、尚未收全分片的 IP 数据报数量
+    pub fragments: usize,
+    /// 解析线程数
+    pub workers: usize,
+    /// 本次捕获中因认证队列已满未交给认证系统的请求数
+    pub auth_dropped: u64,
+}
+
+// HTTP 请求结构
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpRequest {
+    pub id: u64,
+    pub timestamp: u64,
+    pub src_ip: String,
+    pub src_port: u16,
+    pub dst_ip: String,
+    pub dst_port: u16,
+    pub method: String,
+    /// 原始请求目标（包含查询字符串）
+    pub path: String,
+    /// 解码后的路径部分（不含查询字符串）
+    pub url_path: String,
+    /// 解码后的查询参数（支持 UTF-8 和 GBK/GB18030 编码）
+    pub query: Vec<(String, String)>,
+    pub version: String,
+    pub host: String,
+    /// Cookie 头中的名称和值
+    pub cookies: Vec<(String, String)>,
+    pub content_type: String,
+    pub headers: Vec<(String, String)>,
+    pub body: HttpBody,
+    /// application/x-www-form-urlencoded 请求体中的字段
+    pub form: Vec<(String, String)>,
+    /// JSON 请求体，不是 JSON 或解析失败时为 None
+    pub json: Option<serde_json::Value>,
+    /// multipart/form-data 请求体的各个部分
+    pub parts: Vec<multipart::MultipartPart>,
+}
+
+impl HttpRequest {
+    /// 查询参数的值（同名参数取第一个）
+    pub fn query_value(&self, name: &str) -> Option<&str> {
+        find_pair(&self.query, name)
+    }
+
+    /// 表单字段的值（同名字段取第一个）
+    pub fn form_value(&self, name: &str) -> Option<&str> {
+        find_pair(&self.form, name)
+    }
+
+    /// Cookie 的值（同名 Cookie 取第一个）
+    pub fn cookie_value(&self, name: &str) -> Option<&str> {
+        find_pair(&self.cookies, name)
+    }
+
+    /// 按 JSON Pointer（如 "/data/token"）读取 JSON 请求体中的字段
+    pub fn json_value(&self, pointer: &str) -> Option<&serde_json::Value> {
+        self.json.as_ref()?.pointer(pointer)
+    }
+}
+
+fn find_pair<'a>(pairs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+// HTTP 响应结构
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub id: u64,
+    pub timestamp: u64,
+    pub src_ip: String,
+    pub src_port: u16,
+    pub dst_ip: String,
+    pub dst_port: u16,
+    pub version: String,
+    pub status_code: u16,
+    pub reason: String,
+    pub content_type: String,
+    pub headers: Vec<(String, String)>,
+    pub body: HttpBody,
+    /// Set-Cookie 设置的 Cookie，与请求配对后才会解析
+    pub cookies: Vec<cookie::Cookie>,
+}
+
+// HTTP 事务（请求与对应的响应）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpTransaction {
+    /// 与请求的 id 相同
+    pub id: u64,
+    pub request: HttpRequest,
+    pub response: HttpResponse,
+    /// 从捕获到请求到捕获到响应的耗时（毫秒）
+    pub duration_ms: u64,
+}
+
+// TLS 连接（来自客户端的 ClientHello）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConnection {
+    pub id: u64,
+    pub timestamp: u64,
+    pub src_ip: String,
+    pub src_port: u16,
+    pub dst_ip: String,
+    pub dst_port: u16,
+    /// 服务器名称指示（SNI），客户端未发送时为 None
+    pub sni: Option<String>,
+    /// 客户端通过 ALPN 提供的应用层协议
+    pub alpn: Vec<String>,
+    /// 客户端支持的最高 TLS 版本
+    pub version: String,
+}
+
+// WebSocket 消息（握手完成后连接上的一条完整消息）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketMessage {
+    pub id: u64,
+    pub timestamp: u64,
+    pub src_ip: String,
+    pub src_port: u16,
+    pub dst_ip: String,
+    pub dst_port: u16,
+    /// 握手请求的 ID，同一连接上的消息相同
+    pub connection_id: u64,
+    /// 握手请求的主机和路径（包括查询字符串）
+    pub host: String,
+    pub path: String,
+    /// 是否由客户端发出
+    pub from_client: bool,
+    /// 消息类型：text、binary、close
+    pub opcode: String,
+    /// 关闭帧中的状态码
+    pub close_code: Option<u16>,
+    /// 消息内容（分片已合并、已解压），关闭帧为关闭原因
+    pub payload: HttpBody,
+}
+
+// 网络设备结构
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkDevice {
+    pub name: String,
+    pub description: String,
+    pub is_loopback: bool,
+    pub is_up: bool,
+    pub is_running: bool,
+    pub addresses: Vec<String>,
+}
+
+// 捕获到的原始数据包（从读取线程发送到解析线程）
+struct CapturedPacket {
+    timestamp_ms: u64,
+    /// 数据源的链路层类型
+    link_type: LinkType,
+    data: Vec<u8>,
+}
+
+// 捕获数据源
+#[derive(Debug, Clone)]
+enum CaptureSource {
+    /// 从网卡实时捕获，devices 为网卡名称或 "auto"，可同时捕获多个网卡
+    Live { devices: Vec<String> },
+    /// 回放 pcap/pcapng 文件，speed 为回放倍速，None 表示不等待、尽快处理
+    File { path: String, speed: Option<f64> },
+}
+
+// 初始化 AppHandle 以便发送事件
+pub fn init_app_handle(app_handle: tauri::AppHandle) -> Result<()> {
+    APP_HANDLE
+        .set(app_handle)
+        .map_err(|_| anyhow!("已经初始化过 AppHandle"))?;
+    Ok(())
+}
+
+// 设置状态通道
+pub fn set_status_channel(channel: Channel<CaptureStatus>) -> Result<()> {
+    if let Some(channels) = STATUS_CHANNEL.get() {
+        let mut guard = channels.lock().unwrap();
+        *guard = Some(channel);
+        Ok(())
+    } else {
+        let channels = Arc::new(Mutex::new(Some(channel)));
+        STATUS_CHANNEL
+            .set(channels)
+            .map_err(|_| anyhow!("已经初始化过状态通道"))?;
+        Ok(())
+    }
+}
+
+// 设置 HTTP 请求通道
+pub fn set_http_channel(channel: Channel<HttpRequest>) -> Result<()> {
+    if let Some(channels) = HTTP_CHANNEL.get() {
+        let mut guard = channels.lock().unwrap();
+        *guard = Some(channel);
+        Ok(())
+    } else {
+        let channels = Arc::new(Mutex::new(Some(channel)));
+        HTTP_CHANNEL
+            .set(channels)
+            .map_err(|_| anyhow!("已经初始化过 HTTP 请求通道"))?;
+        Ok(())
+    }
+}
+
+// 设置 HTTP 事务通道
+pub fn set_transaction_channel(channel: Channel<HttpTransaction>) -> Result<()> {
+    if let Some(channels) = TRANSACTION_CHANNEL.get() {
+        let mut guard = channels.lock().unwrap();
+        *guard = Some(channel);
+        Ok(())
+    } else {
+        let channels = Arc::new(Mutex::new(Some(channel)));
+        TRANSACTION_CHANNEL
+            .set(channels)
+            .map_err(|_| anyhow!("已经初始化过 HTTP 事务通道"))?;
+        Ok(())
+    }
+}
+
+// 设置 TLS 连接通道
+pub fn set_tls_channel(channel: Channel<TlsConnection>) -> Result<()> {
+    if let Some(channels) = TLS_CHANNEL.get() {
+        let mut guard = channels.lock().unwrap();
+        *guard = Some(channel);
+        Ok(())
+    } else {
+        let channels = Arc::new(Mutex::new(Some(channel)));
+        TLS_CHANNEL
+            .set(channels)
+            .map_err(|_| anyhow!("已经初始化过 TLS 连接通道"))?;
+        Ok(())
+    }
+}
+
+// 设置 WebSocket 消息通道
+pub fn set_websocket_channel(channel: Channel<WebSocketMessage>) -> Result<()> {
+    if let Some(channels) = WEBSOCKET_CHANNEL.get() {
+        let mut guard = channels.lock().unwrap();
+        *guard = Some(channel);
+        Ok(())
+    } else {
+        let channels = Arc::new(Mutex::new(Some(channel)));
+        WEBSOCKET_CHANNEL
+            .set(channels)
+            .map_err(|_| anyhow!("已经初始化过 WebSocket 消息通道"))?;
+        Ok(())
+    }
+}
+
+// 开始实时捕获
+// devices 为网卡名称或 "auto"，可同时捕获多个网卡；不传时使用上次的选择，首次使用时自动选择
+pub fn init_packet_capture(devices: Option<Vec<String>>) -> Result<()> {
+    let devices: Vec<String> = devices
+        .unwrap_or_default()
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let devices = if devices.is_empty() {
+        let saved = settings::get_settings().devices;
+        if saved.is_empty() {
+            vec![device::AUTO_DEVICE.to_string()]
+        } else {
+            saved
+        }
+    } else {
+        // 记住用户的选择
+        if let Err(e) = settings::update_settings(|s| s.devices = devices.clone()) {
+            error!("保存网卡选择失败: {}", e);
+        }
+        devices
+    };
+
+    // 启动前先编译过滤器，尽早把配置错误返回给前端
+    filter::validate_bpf(&filter::current_bpf()?)?;
+
+    start_capture_session(CaptureSource::Live { devices })
+}
+
+// 回放抓包文件，走与实时捕获相同的解析和认证流程
+pub fn init_file_capture(path: String, speed: Option<f64>) -> Result<()> {
+    if !std::path::Path::new(&path).is_file() {
+        return Err(anyhow!("抓包文件不存在: {}", path));
+    }
+    if let Some(speed) = speed {
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(anyhow!("回放倍速必须大于0: {}", speed));
+        }
+    }
+    filter::validate_bpf(&filter::current_bpf()?)?;
+
+    start_capture_session(CaptureSource::File { path, speed })
+}
+
+fn start_capture_session(source: CaptureSource) -> Result<()> {
+    // 如果已经在运行，先停止
+    if let Some(status) = CAPTURE_STATUS.get() {
+        let status_guard = status.lock().unwrap();
+        if status_guard.running {
+            drop(status_guard); // 释放锁
+            info!("检测到捕获正在运行，先停止...");
+            stop_packet_capture()?;
+            // 等待一段时间确保完全停止
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    }
+
+    // 初始化或获取运行状态标志
+    let running = if let Some(existing_running) = CAPTURE_RUNNING.get() {
+        // 重置现有的运行标志
+        existing_running.store(true, Ordering::Relaxed);
+        existing_running.clone()
+    } else {
+        // 首次初始化
+        let running = Arc::new(AtomicBool::new(true));
+        CAPTURE_RUNNING
+            .set(running.clone())
+            .map_err(|_| anyhow!("已经初始化过运行状态标志"))?;
+        running
+    };
+
+    // 初始化或获取线程句柄
+    let thread_handle = if let Some(existing_handle) = CAPTURE_THREAD.get() {
+        existing_handle.clone()
+    } else {
+        let thread_handle = Arc::new(Mutex::new(None));
+        CAPTURE_THREAD
+            .set(thread_handle.clone())
+            .map_err(|_| anyhow!("已经初始化过线程句柄"))?;
+        thread_handle
+    };
+        
+    // 初始化或更新捕获状态
+    let status = if let Some(existing_status) = CAPTURE_STATUS.get() {
+        let mut status_guard = existing_status.lock().unwrap();
+        status_guard.running = true;
+        status_guard.message = "正在初始化...".to_string();
+        status_guard.device_name = "未知".to_string();
+        status_guard.interfaces.clear();
+        status_guard.stats = CaptureStats::default();
+        status_guard.start_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        existing_status.clone()
+    } else {
+        let status = Arc::new(Mutex::new(CaptureStatus {
+            running: true,
+            message: "正在初始化...".to_string(),
+            device_name: "未知".to_string(),
+            start_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            interfaces: Vec::new(),
+            stats: CaptureStats::default(),
+        }));
+        CAPTURE_STATUS
+            .set(status.clone())
+            .map_err(|_| anyhow!("已经初始化过捕获状态"))?;
+        status
+    };
+        
+    // 初始化通道存储（如果尚未初始化）
+    if STATUS_CHANNEL.get().is_none() {
+        STATUS_CHANNEL
+            .set(Arc::new(Mutex::new(None)))
+            .map_err(|_| anyhow!("已经初始化过状态通道存储"))?;
+    }
+    
+    if HTTP_CHANNEL.get().is_none() {
+        HTTP_CHANNEL
+            .set(Arc::new(Mutex::new(None)))
+            .map_err(|_| anyhow!("已经初始化过 HTTP 请求通道存储"))?;
+    }
+
+    if TRANSACTION_CHANNEL.get().is_none() {
+        TRANSACTION_CHANNEL
+            .set(Arc::new(Mutex::new(None)))
+            .map_err(|_| anyhow!("已经初始化过 HTTP 事务通道存储"))?;
+    }
+
+    // 清理旧的线程句柄（如果存在）
+    {
+        let mut handle_guard = thread_handle.lock().unwrap();
+        if let Some(old_thread) = handle_guard.take() {
+            if old_thread.is_finished() {
+                let _ = old_thread.join();
+                info!("清理了旧的捕获线程");
+            }
+        }
+    }
+
+    // 启动捕获线程
+    let running_clone = running.clone();
+    let status_clone = status.clone();
+    let capture_thread = thread::spawn(move || {
+        if let Err(e) = start_capture(running_clone, status_clone, source) {
+            error!("数据包捕获出错: {}", e);
+            if let Some(status) = CAPTURE_STATUS.get() {
+                let mut status_guard = status.lock().unwrap();
+                status_guard.running = false;
+                status_guard.message = format!("捕获失败: {}", e);
+            }
+            // 发送状态更新
+            send_status_update();
+        }
+    });
+
+    // 保存线程句柄
+    *thread_handle.lock().unwrap() = Some(capture_thread);
+    info!("数据包捕获线程已启动");
+    
+    // 发送初始状态更新
+    send_status_update();
+    Ok(())
+}
+
+fn start_capture(
+    running: Arc<AtomicBool>,
+    status: Arc<Mutex<CaptureStatus>>,
+    source: CaptureSource,
+) -> Result<()> {
+    info!("开始初始化数据包捕获...");
+    
+    // 更新状态
+    {
+        let mut status_guard = status.lock().unwrap();
+        status_guard.message = "正在初始化网络捕获...".to_string();
+        status_guard.interfaces.clear();
+    }
+    send_status_update();
+
+    // 读取线程只负责捕获，按地址把数据包分发到各解析线程的有界队列
+    let workers = pipeline::worker_count();
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..workers)
+        .map(|_| mpsc::sync_channel(PACKET_QUEUE_CAPACITY))
+        .unzip();
+    let is_replay = matches!(source, CaptureSource::File { .. });
+    // 回放文件时队列满了就等待，实时捕获时丢弃并计数，避免读取线程阻塞导致内核丢包
+    let dispatcher = PacketDispatcher::new(senders, is_replay);
+    let readers = match source {
+        CaptureSource::Live { devices } => open_live_readers(&running, &status, &devices, &dispatcher),
+        CaptureSource::File { path, speed } => open_file_reader(&running, &status, &path, speed, &dispatcher),
+    };
+    let readers = match readers {
+        Ok(readers) => readers,
+        Err(err) => {
+            {
+                let mut status_guard = status.lock().unwrap();
+                status_guard.running = false;
+                status_guard.message = err.to_string();
+            }
+            send_status_update();
+            return Err(err);
+        }
+    };
+    // 只保留读取线程持有的发送端，全部结束后队列自动关闭
+    drop(dispatcher);
+
+    // 每个解析线程独占自己的分片重组、流重组和 HTTP 会话状态，统计写入各自的位置
+    let worker_stats = Arc::new(Mutex::new(vec![CaptureStats::default(); workers]));
+    let dissectors: Vec<_> = receivers
+        .into_iter()
+        .enumerate()
+        .map(|(index, receiver)| {
+            let worker_stats = worker_stats.clone();
+            thread::spawn(move || run_dissection_loop(receiver, &worker_stats, index))
+        })
+        .collect();
+
+    // 更新状态为运行中
+    {
+        let mut status_guard = status.lock().unwrap();
+        status_guard.running = true;
+        status_guard.message = if is_replay {
+            "正在回放抓包文件...".to_string()
+        } else {
+            "正在捕获 HTTP 请求...".to_string()
+        };
+    }
+    send_status_update();
+    
+    info!("开始捕获 HTTP 请求数据包 ({} 个解析线程)...", workers);
+
+    // 定期汇总各解析线程的统计并推送，直到读取线程结束、解析线程处理完队列中的数据包
+    let auth_dropped_start = pipeline::auth_dropped();
+    let update_stats = || {
+        let stats = merge_stats(&worker_stats.lock().unwrap(), pipeline::auth_dropped(), auth_dropped_start);
+        status.lock().unwrap().stats = stats;
+    };
+    let mut last_status_push = Instant::now();
+    while !dissectors.iter().all(|dissector| dissector.is_finished()) {
+        thread::sleep(Duration::from_millis(100));
+        if last_status_push.elapsed() >= STATUS_PUSH_INTERVAL {
+            update_stats();
+            send_status_update();
+            last_status_push = Instant::now();
+        }
+    }
+    for dissector in dissectors {
+        let _ = dissector.join();
+    }
+    update_stats();
+
+    // 读取线程返回是否读完了所有数据包（仅文件回放有意义），需要等待所有线程结束
+    let results: Vec<bool> = readers
+        .into_iter()
+        .map(|reader| reader.join().unwrap_or(false))
+        .collect();
+    let completed = results.iter().all(|done| *done);
+
+    // 更新状态为已停止
+    {
+        let mut status_guard = status.lock().unwrap();
+        status_guard.running = false;
+        status_guard.message = if is_replay && completed {
+            "抓包文件回放完成".to_string()
+        } else {
+            "数据包捕获已停止".to_string()
+        };
+    }
+    send_status_update();
+
+    running.store(false, Ordering::Relaxed);
+    info!("数据包捕获已停止");
+    Ok(())
+}
+
+// 打开所有选择的网卡，每个网卡启动一个读取线程
+fn open_live_readers(
+    running: &Arc<AtomicBool>,
+    status: &Arc<Mutex<CaptureStatus>>,
+    device_specs: &[String],
+    dispatcher: &PacketDispatcher,
+) -> Result<Vec<thread::JoinHandle<bool>>> {
+    // 使用配置的过滤器，只捕获 HTTP 流量
+    let bpf = filter::current_bpf()?;
+    info!("使用捕获过滤器: {}", bpf);
+
+    let mut readers = Vec::new();
+    let mut errors = Vec::new();
+    let mut opened: Vec<String> = Vec::new();
+
+    for spec in device_specs {
+        // 按名称或自动选择网络设备
+        let device = match device::resolve_device(spec) {
+            Ok(device) => device,
+            Err(e) => {
+                error!("选择网络设备 {} 失败: {}", spec, e);
+                errors.push(format!("{}: {}", spec, e));
+                continue;
+            }
+        };
+
+        // "auto" 可能与显式指定的网卡重复
+        if opened.contains(&device.name) {
+            continue;
+        }
+
+        info!("使用网络设备: {}", device.name);
+        let index = add_interface_status(status, &device.name);
+
+        let cap = Capture::from_device(device.clone())
+            .map_err(|e| anyhow!("创建捕获句柄失败: {}. {}", e, permissions::PERMISSION_HINT))
+            .and_then(|cap| {
+                cap.promisc(true)
+                    .timeout(1000)
+                    .immediate_mode(true)
+                    .open()
+                    .map_err(|e| anyhow!("打开网络设备失败: {}. {}", e, permissions::PERMISSION_HINT))
+            })
+            .and_then(|mut cap| {
+                let link_type = supported_link_type(&cap)?;
+                cap.filter(&link_type.capture_filter(&bpf), true)
+                    .map_err(|e| anyhow!("设置过滤器失败: {}", e))?;
+                Ok((cap, link_type))
+            });
+
+        match cap {
+            Ok((cap, link_type)) => {
+                info!("网络设备 {} 的链路层类型: {:?}", device.name, link_type);
+                readers.push(spawn_reader(cap, link_type, index, None, running.clone(), status.clone(), dispatcher.clone()));
+                opened.push(device.name);
+            }
+            Err(e) => {
+                error!("网络设备 {} 无法捕获: {}", device.name, e);
+                status.lock().unwrap().interfaces[index].error = Some(e.to_string());
+                errors.push(format!("{}: {}", device.name, e));
+            }
+        }
+    }
+
+    if readers.is_empty() {
+        return Err(anyhow!("没有可用于捕获的网络设备: {}", errors.join("; ")));
+    }
+
+    // 更新状态
+    {
+        let mut status_guard = status.lock().unwrap();
+        status_guard.device_name = opened.join(", ");
+    }
+    send_status_update();
+
+    Ok(readers)
+}
+
+// 打开抓包文件并启动读取线程
+fn open_file_reader(
+    running: &Arc<AtomicBool>,
+    status: &Arc<Mutex<CaptureStatus>>,
+    path: &str,
+    speed: Option<f64>,
+    dispatcher: &PacketDispatcher,
+) -> Result<Vec<thread::JoinHandle<bool>>> {
+    info!("回放抓包文件: {} (倍速: {:?})", path, speed);
+
+    let mut cap = Capture::from_file(path).map_err(|e| anyhow!("打开抓包文件失败: {}", e))?;
+
+    // 与实时捕获使用相同的过滤器
+    let link_type = supported_link_type(&cap)?;
+    let bpf = filter::current_bpf()?;
+    cap.filter(&link_type.capture_filter(&bpf), true)
+        .map_err(|e| anyhow!("设置过滤器失败: {}", e))?;
+
+    let index = add_interface_status(status, path);
+    {
+        let mut status_guard = status.lock().unwrap();
+        status_guard.device_name = format!("文件: {}", path);
+    }
+    send_status_update();
+
+    Ok(vec![spawn_reader(cap, link_type, index, speed, running.clone(), status.clone(), dispatcher.clone())])
+}
+
+// 检查捕获句柄的链路层类型是否支持解析
+fn supported_link_type<T: pcap::Activated + ?Sized>(cap: &Capture<T>) -> Result<LinkType> {
+    let linktype = cap.get_datalink();
+    LinkType::from_pcap(linktype).ok_or_else(|| {
+        let name = linktype.get_name().unwrap_or_else(|_| linktype.0.to_string());
+        anyhow!("不支持的链路层类型: {}", name)
+    })
+}
+
+// 在捕获状态中登记一个数据源，返回其下标
+fn add_interface_status(status: &Arc<Mutex<CaptureStatus>>, name: &str) -> usize {
+    let mut status_guard = status.lock().unwrap();
+    status_guard.interfaces.push(InterfaceStatus {
+        name: name.to_string(),
+        running: false,
+        packets: 0,
+        bytes: 0,
+        received: 0,
+        dropped: 0,
+        if_dropped: 0,
+        queue_dropped: 0,
+        error: None,
+    });
+    status_guard.interfaces.len() - 1
+}
+
+// 启动读取线程：从捕获句柄读取数据包并分发到解析队列
+// speed 仅用于文件回放，按数据包时间戳以指定倍速等待；线程返回是否读完了所有数据包
+fn spawn_reader<T: pcap::Activated + ?Sized + 'static>(
+    mut cap: Capture<T>,
+    link_type: LinkType,
+    index: usize,
+    speed: Option<f64>,
+    running: Arc<AtomicBool>,
+    status: Arc<Mutex<CaptureStatus>>,
+    dispatcher: PacketDispatcher,
+) -> thread::JoinHandle<bool> {
+    thread::spawn(move || {
+        let mut packets: u64 = 0;
+        let mut bytes: u64 = 0;
+        let mut queue_dropped: u64 = 0;
+        let mut last_flush = Instant::now();
+        let mut completed = false;
+
+        // 将本线程的统计写入捕获状态，pcap_stats 在回放文件时为 None
+        let flush = |packets: u64, bytes: u64, queue_dropped: u64, running: bool, pcap_stats: Option<pcap::Stat>| {
+            let mut status_guard = status.lock().unwrap();
+            if let Some(interface) = status_guard.interfaces.get_mut(index) {
+                interface.packets = packets;
+                interface.bytes = bytes;
+                interface.queue_dropped = queue_dropped;
+                interface.running = running;
+                if let Some(pcap_stats) = pcap_stats {
+                    interface.received = u64::from(pcap_stats.received);
+                    interface.dropped = u64::from(pcap_stats.dropped);
+                    interface.if_dropped = u64::from(pcap_stats.if_dropped);
+                }
+            }
+        };
+        flush(0, 0, 0, true, None);
+
+        // 回放节奏控制：第一个数据包的时间戳和对应的实际时间
+        let mut replay_origin: Option<(u64, Instant)> = None;
+        let mut filter_generation = filter::filter_generation();
+
+        while running.load(Ordering::Relaxed) {
+            // 过滤器配置或认证系统变化时重新设置过滤器
+            let generation = filter::filter_generation();
+            if generation != filter_generation {
+                filter_generation = generation;
+                match filter::current_bpf().and_then(|bpf| {
+                    let bpf = link_type.capture_filter(&bpf);
+                    cap.filter(&bpf, true)
+                        .map_err(|e| anyhow!("设置过滤器失败: {}", e))?;
+                    Ok(bpf)
+                }) {
+                    Ok(bpf) => info!("已重新设置过滤器: {}", bpf),
+                    Err(e) => warn!("重新设置过滤器失败，继续使用原过滤器: {}", e),
+                }
+            }
+
+            match cap.next_packet() {
+                Ok(packet) => {
+                    let timestamp_ms = packet.header.ts.tv_sec as u64 * 1000
+                        + packet.header.ts.tv_usec as u64 / 1000;
+                    packets += 1;
+                    bytes += packet.header.len as u64;
+
+                    if let Some(speed) = speed {
+                        let (first_ms, started) = *replay_origin.get_or_insert((timestamp_ms, Instant::now()));
+                        let offset = Duration::from_millis(timestamp_ms.saturating_sub(first_ms));
+                        let target = offset.div_f64(speed);
+                        // 分段休眠，以便及时响应停止请求
+                        while running.load(Ordering::Relaxed) && started.elapsed() < target {
+                            thread::sleep((target - started.elapsed()).min(Duration::from_millis(100)));
+                        }
+                    }
+
+                    let captured = CapturedPacket {
+                        timestamp_ms,
+                        link_type,
+                        data: packet.data.to_vec(),
+                    };
+                    match dispatcher.dispatch(captured) {
+                        Dispatch::Queued => {}
+                        Dispatch::Dropped => queue_dropped += 1,
+                        // 解析线程已退出
+                        Dispatch::Closed => break,
+                    }
+                },
+                Err(pcap::Error::TimeoutExpired) => {}, // 超时是正常的
+                Err(pcap::Error::NoMorePackets) => {
+                    // 文件已读完
+                    completed = true;
+                    break;
+                },
+                Err(e) => {
+                    error!("捕获数据包错误: {:?}", e);
+                    if !running.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }
+
+            if last_flush.elapsed() >= STATUS_PUSH_INTERVAL {
+                flush(packets, bytes, queue_dropped, true, cap.stats().ok());
+                last_flush = Instant::now();
+            }
+        }
+
+        flush(packets, bytes, queue_dropped, false, cap.stats().ok());
+        completed
+    })
+}
+
+// 解析线程：从队列中取出数据包进行重组和 HTTP 解析，直到所有读取线程结束
+// 统计定期写入 worker_stats[index]，由捕获线程汇总推送
+fn run_dissection_loop(receiver: Receiver<CapturedPacket>, worker_stats: &Mutex<Vec<CaptureStats>>, index: usize) {
+    // IP 分片重组、TCP 流重组和 HTTP 会话状态，由解析线程独占
+    let mut defragmenter = IpDefragmenter::new();
+    let mut reassembler = TcpReassembler::new();
+    let mut dissector = HttpDissector::new();
+    let mut stats = CaptureStats::default();
+    let mut interval_bytes: u64 = 0;
+    let mut last_status_push = Instant::now();
+
+    loop {
+        match receiver.recv_timeout(STATUS_PUSH_INTERVAL) {
+            Ok(packet) => {
+                interval_bytes += packet.data.len() as u64;
+                dissect_packet(&packet, &mut defragmenter, &mut reassembler, &mut dissector, &mut stats);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        // 定期更新本线程的统计
+        if last_status_push.elapsed() >= STATUS_PUSH_INTERVAL {
+            stats.bytes_per_sec = interval_bytes as f64 / last_status_push.elapsed().as_secs_f64();
+            stats.flows = reassembler.flow_count();
+            stats.fragments = defragmenter.datagram_count();
+            interval_bytes = 0;
+            worker_stats.lock().unwrap()[index] = stats.clone();
+            last_status_push = Instant::now();
+        }
+    }
+
+    // 捕获结束后不再有吞吐量
+    stats.bytes_per_sec = 0.0;
+    stats.flows = reassembler.flow_count();
+    stats.fragments = defragmenter.datagram_count();
+    worker_stats.lock().unwrap()[index] = stats;
+}
+
+// 汇总各解析线程的统计，认证丢弃数是全局计数，减去本次捕获开始时的值
+fn merge_stats(worker_stats: &[CaptureStats], auth_dropped: u64, auth_dropped_start: u64) -> CaptureStats {
+    let mut stats = CaptureStats {
+        workers: worker_stats.len(),
+        auth_dropped: auth_dropped.saturating_sub(auth_dropped_start),
+        ..CaptureStats::default()
+    };
+    for worker in worker_stats {
+        stats.packets_parsed += worker.packets_parsed;
+        stats.packets_failed += worker.packets_failed;
+        stats.http_requests += worker.http_requests;
+        stats.bytes_per_sec += worker.bytes_per_sec;
+        stats.flows += worker.flows;
+        stats.fragments += worker.fragments;
+    }
+    stats
+}
+
+// 去掉链路层首部并重组 IP 分片，得到完整的 IP 数据包后再解析
+fn dissect_packet(
+    packet: &CapturedPacket,
+    defragmenter: &mut IpDefragmenter,
+    reassembler: &mut TcpReassembler,
+    dissector: &mut HttpDissector,
+    stats: &mut CaptureStats,
+) {
+    let ip_packet = match packet.link_type.ip_packet(&packet.data) {
+        Some(ip_packet) => ip_packet,
+        None => {
+            stats.packets_failed += 1;
+            return;
+        }
+    };
+    // 分片尚未收全时等待后续分片
+    let ip_packet = match defragmenter.process(ip_packet) {
+        Some(ip_packet) => ip_packet,
+        None => return,
+    };
+    match SlicedPacket::from_ip(&ip_packet) {
+        Ok(sliced) => {
+            stats.packets_parsed += 1;
+            process_packet(sliced, packet.timestamp_ms, reassembler, dissector, stats);
+        }
+        Err(e) => {
+            stats.packets_failed += 1;
+            debug!("解析数据包错误: {:?}", e);
+        }
+    }
+}
+
+fn process_packet(
+    sliced: SlicedPacket,
+    timestamp_ms: u64,
+    reassembler: &mut TcpReassembler,
+    dissector: &mut HttpDissector,
+    stats: &mut CaptureStats,
+) {
+    // 提取 IP 地址信息
+    let (src_ip, dst_ip) = match sliced.ip {
+        Some(InternetSlice::Ipv4(ipv4, _)) => (
+            IpAddr::V4(ipv4.source_addr()),
+            IpAddr::V4(ipv4.destination_addr()),
+        ),
+        Some(InternetSlice::Ipv6(ipv6, _)) => (
+            IpAddr::V6(ipv6.source_addr()),
+            IpAddr::V6(ipv6.destination_addr()),
+        ),
+        None => return,
+    };
+
+    // 提取端口和 TCP 段信息
+    let (key, segment) = match sliced.transport {
+        Some(TransportSlice::Tcp(tcp)) => (
+            FlowKey {
+                src_ip,
+                src_port: tcp.source_port(),
+                dst_ip,
+                dst_port: tcp.destination_port(),
+            },
+            TcpSegment {
+                seq: tcp.sequence_number(),
+                syn: tcp.syn(),
+                fin: tcp.fin(),
+                rst: tcp.rst(),
+                payload: sliced.payload,
+            },
+        ),
+        _ => return,
+    };
+
+    // 按序列号重组，只有出现新的连续数据时才尝试解析
+    let stream = match reassembler.process_segment(key, &segment) {
+        Some(stream) => stream,
+        None => return,
+    };
+
+    for message in dissector.process_stream(key, stream, timestamp_ms) {
+        if matches!(message, HttpMessage::Request(_)) {
+            stats.http_requests += 1;
+        }
+        handle_http_message(message);
+    }
+}
+
+// 处理解析出的消息（抓包和本地代理共用）
+fn handle_http_message(message: HttpMessage) {
+    match message {
+        HttpMessage::Request(http_request) => handle_http_request(http_request),
+        HttpMessage::Transaction(transaction) => handle_http_transaction(transaction),
+        HttpMessage::Tls(connection) => handle_tls_connection(connection),
+        HttpMessage::WebSocket(message) => handle_websocket_message(message),
+    }
+}
+
+// 处理解析出的 HTTP 请求
+fn handle_http_request(http_request: HttpRequest) {
+    debug!("捕获 HTTP 请求: {}:{} -> {}:{} {} {}",
+           http_request.src_ip, http_request.src_port, http_request.dst_ip, http_request.dst_port,
+           http_request.method, http_request.path);
+
+    // 缓存消息体和上传的文件，供前端查看和保存
+    body::remember(http_request.id, &http_request.body);
+    if !http_request.parts.is_empty() {
+        multipart::remember(http_request.id, &http_request.parts);
+    }
+
+    // 🔐 交给认证处理线程，TokenManager 加锁和各系统的匹配不占用解析线程
+    pipeline::submit_auth_request(&http_request);
+
+    // 发送 HTTP 请求到前端
+    send_http_request(http_request);
+}
+
+// 处理与请求配对完成的 HTTP 响应
+fn handle_http_transaction(transaction: HttpTransaction) {
+    debug!("捕获 HTTP 响应: {} {} -> {} {} ({}ms)",
+          transaction.request.method, transaction.request.path,
+          transaction.response.status_code, transaction.response.reason,
+          transaction.duration_ms);
+
+    // 记录服务端设置的 Cookie，供查询会话 Cookie 使用，响应时间同时推进 Cookie 库的时钟
+    cookie::remember(&transaction.response.cookies, transaction.response.timestamp);
+    body::remember(transaction.response.id, &transaction.response.body);
+
+    // 发送 HTTP 事务到前端
+    send_http_transaction(transaction);
+}
+
+// 处理 HTTPS 连接的 ClientHello
+fn handle_tls_connection(connection: TlsConnection) {
+    debug!("捕获 TLS 连接: {}:{} -> {}:{} (SNI: {}, {}, ALPN: {:?})",
+          connection.src_ip, connection.src_port, connection.dst_ip, connection.dst_port,
+          connection.sni.as_deref().unwrap_or("无"), connection.version, connection.alpn);
+
+    // 记录服务端的 SNI，供构建 URL 时使用
+    tls::remember_endpoint(&connection);
+
+    // 发送 TLS 连接到前端
+    send_tls_connection(connection);
+}
+
+// 处理 WebSocket 消息
+fn handle_websocket_message(message: WebSocketMessage) {
+    debug!("捕获 WebSocket 消息: {}{} ({}, {}, {} 字节)",
+          message.host, message.path,
+          if message.from_client { "客户端" } else { "服务端" },
+          message.opcode, message.payload.size);
+    body::remember(message.id, &message.payload);
+
+    // 发送 WebSocket 消息到前端
+    send_websocket_message(message);
+}
+
+pub fn stop_packet_capture() -> Result<()> {
+    info!("正在停止数据包捕获...");
+    
+    // 设置运行标志为 false，通知捕获线程停止
+    if let Some(running) = CAPTURE_RUNNING.get() {
+        running.store(false, Ordering::Relaxed);
+        info!("已发送停止数据包捕获的信号");
+    }
+
+    // 等待线程结束，给一个合理的超时时间
+    if let Some(handle) = CAPTURE_THREAD.get() {
+        let mut guard = handle.lock().unwrap();
+        if let Some(thread) = guard.take() {
+            // 释放锁，允许线程正常执行
+            drop(guard);
+            
+            // 等待线程结束，最多等待3秒
+            let mut attempts = 0;
+            while !thread.is_finished() && attempts < 30 {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                attempts += 1;
+            }
+            
+            if thread.is_finished() {
+                let _ = thread.join();
+                info!("数据包捕获线程已正常结束");
+            } else {
+                info!("等待线程结束超时，强制继续");
+                // 注意：这里不调用 join，因为线程可能还在运行
+            }
+        }
+    }
+    
+    // 更新状态
+    if let Some(status) = CAPTURE_STATUS.get() {
+        let mut status_guard = status.lock().unwrap();
+        status_guard.running = false;
+        status_guard.message = "数据包捕获已停止".to_string();
+    }
+    send_status_update();
+    
+    info!("数据包捕获停止完成");
+    Ok(())
+}
+
+// 获取捕获状态
+pub fn get_capture_status() -> CaptureStatus {
+    if let Some(status) = CAPTURE_STATUS.get() {
+        let status_guard = status.lock().unwrap();
+        status_guard.clone()
+    } else {
+        CaptureStatus {
+            running: false,
+            message: "捕获未初始化".to_string(),
+            device_name: "未知".to_string(),
+            start_time: 0,
+            interfaces: Vec::new(),
+            stats: CaptureStats::default(),
+        }
+    }
+}
+
+// 通过 Channel 发送状态更新
+fn send_status_update() {
+    if let Some(channels) = STATUS_CHANNEL.get() {
+        let guard = channels.lock().unwrap();
+        if let Some(channel) = &*guard {
+            let status = get_capture_status();
+            debug!("通过 Channel 发送状态更新: {:?}", status);
+            if let Err(e) = channel.send(status) {
+                error!("发送状态更新失败: {}", e);
+            }
+        }
+    }
+}
+
+// 通过 Channel 发送 HTTP 请求
+fn send_http_request(request: HttpRequest) {
+    if let Some(channels) = HTTP_CHANNEL.get() {
+        let guard = channels.lock().unwrap();
+        if let Some(channel) = &*guard {
+            debug!("通过 Channel 发送 HTTP 请求: {:?}", request);
+            if let Err(e) = channel.send(request) {
+                error!("发送 HTTP 请求失败: {}", e);
+            }
+        }
+    }
+}
+
+// 通过 Channel 发送 HTTP 事务
+fn send_http_transaction(transaction: HttpTransaction) {
+    if let Some(channels) = TRANSACTION_CHANNEL.get() {
+        let guard = channels.lock().unwrap();
+        if let Some(channel) = &*guard {
+            debug!("通过 Channel 发送 HTTP 事务: {:?}", transaction);
+            if let Err(e) = channel.send(transaction) {
+                error!("发送 HTTP 事务失败: {}", e);
+            }
+        }
+    }
+}
+
+// 通过 Channel 发送 TLS 连接
+fn send_tls_connection(connection: TlsConnection) {
+    if let Some(channels) = TLS_CHANNEL.get() {
+        let guard = channels.lock().unwrap();
+        if let Some(channel) = &*guard {
+            debug!("通过 Channel 发送 TLS 连接: {:?}", connection);
+            if let Err(e) = channel.send(connection) {
+                error!("发送 TLS 连接失败: {}", e);
+            }
+        }
+    }
+}
+
+// 通过 Channel 发送 WebSocket 消息
+fn send_websocket_message(message: WebSocketMessage) {
+    if let Some(channels) = WEBSOCKET_CHANNEL.get() {
+        let guard = channels.lock().unwrap();
+        if let Some(channel) = &*guard {
+            debug!("通过 Channel 发送 WebSocket 消息: {:?}", message);
+            if let Err(e) = channel.send(message) {
+                error!("发送 WebSocket 消息失败: {}", e);
+            }
+        }
+    }
+}
+
+// 获取网络设备列表
+pub fn get_network_devices() -> Result<Vec<NetworkDevice>> {
+    let list = match pcap::Device::list() {
+        Ok(list) => list,
+        Err(e) => {
+            return Err(anyhow!("获取网络设备列表失败: {}", e));
+        }
+    };
+
+    let mut devices = Vec::new();
+    for device in list {
+        let description = device.desc.unwrap_or_else(|| "无描述".to_string());
+        let is_loopback = device.flags.is_loopback();
+        let is_up = device.flags.is_up();
+        let is_running = device.flags.is_running();
+        let addresses = device.addresses.iter()
+            .map(|addr| addr.addr.to_string())
+            .collect();
+
+        let network_device = NetworkDevice {
+            name: device.name.clone(),
+            description,
+            is_loopback,
+            is_up,
+            is_running,
+            addresses,
+        };
+
+        devices.push(network_device);
+    }
+
+    info!("找到 {} 个网络设备", devices.len());
+    Ok(devices)
+}
+
+// 获取默认使用的网卡：上次选择的网卡，未选择时返回自动选择的结果
+pub fn get_default_capture_devices() -> Result<Vec<String>> {
+    let list = pcap::Device::list().map_err(|e| anyhow!("获取网络设备列表失败: {}", e))?;
+
+    let mut devices = Vec::new();
+    for name in settings::get_settings().devices {
+        if name.eq_ignore_ascii_case(device::AUTO_DEVICE) {
+            devices.extend(device::auto_select(&list).map(|device| device.name));
+        } else if list.iter().any(|device| device.name == name) {
+            devices.push(name);
+        }
+    }
+
+    if devices.is_empty() {
+        devices.extend(device::auto_select(&list).map(|device| device.name));
+    }
+    devices.dedup();
+    Ok(devices)
+}
+
+/// 检查是否具备抓包所需的系统权限（macOS上是ChmodBPF，Windows上是Npcap，Linux上是网络能力）
+pub fn has_packet_capture_prerequisites() -> bool {
+    permissions::check_capture_permissions().can_capture
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worker(packets_parsed: u64, http_requests: u64, bytes_per_sec: f64, flows: usize) -> CaptureStats {
+        CaptureStats {
+            packets_parsed,
+            packets_failed: 1,
+            http_requests,
+            bytes_per_sec,
+            flows,
+            fragments: 2,
+            ..CaptureStats::default()
+        }
+    }
+
+    #[test]
+    fn merge_stats_sums_workers() {
+        // 多个网卡的数据包按流分配到各解析线程
+        let stats = merge_stats(&[worker(100, 5, 1000.0, 3), worker(50, 2, 500.5, 4), worker(0, 0, 0.0, 0)], 7, 4);
+        assert_eq!(stats.workers, 3);
+        assert_eq!(stats.packets_parsed, 150);
+        assert_eq!(stats.packets_failed, 3);
+        assert_eq!(stats.http_requests, 7);
+        assert_eq!(stats.bytes_per_sec, 1500.5);
+        assert_eq!(stats.flows, 7);
+        assert_eq!(stats.fragments, 6);
+        assert_eq!(stats.auth_dropped, 3);
+
+        let empty = merge_stats(&[], 0, 0);
+        assert_eq!(empty.workers, 0);
+        assert_eq!(empty.packets_parsed, 0);
+    }
+
+    #[test]
+    fn merge_stats_resets_across_restarts() {
+        // 第一次捕获期间认证队列丢弃了 5 个请求
+        let first = merge_stats(&[worker(100, 5, 1000.0, 3)], 5, 0);
+        assert_eq!(first.auth_dropped, 5);
+
+        // 重新开始捕获：解析线程的统计重新创建，认证丢弃数从当前的全局计数开始
+        let start = 5;
+        let second = merge_stats(&vec![CaptureStats::default(); 2], 5, start);
+        assert_eq!(second.workers, 2);
+        assert_eq!(second.packets_parsed, 0);
+        assert_eq!(second.http_requests, 0);
+        assert_eq!(second.auth_dropped, 0);
+
+        let second = merge_stats(&[worker(10, 1, 10.0, 1), CaptureStats::default()], 8, start);
+        assert_eq!(second.packets_parsed, 10);
+        assert_eq!(second.auth_dropped, 3);
+
+        // 全局计数小于起始值时不下溢
+        assert_eq!(merge_stats(&[], 2, start).auth_dropped, 0);
+    }
+}
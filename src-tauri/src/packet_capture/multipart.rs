@@ -0,0 +1,423 @@
+use super::body;
+use super::charset;
+use super::http::find_header;
+use super::APP_HANDLE;
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+/// 最多缓存多少个包含 multipart 数据的请求，供保存文件使用
+const MAX_CACHED_REQUESTS: usize = 64;
+
+/// 缓存的请求：(请求 ID, 各部分)
+type CachedParts = (u64, Vec<CachedPart>);
+
+/// 缓存的部分信息，数据在保存时从缓存的消息体中读取
+struct CachedPart {
+    range: Range<usize>,
+    truncated: bool,
+    filename: String,
+}
+
+// 最近捕获的 multipart 请求
+static PART_CACHE: OnceCell<Mutex<VecDeque<CachedParts>>> = OnceCell::new();
+
+/// multipart/form-data 中的一个部分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultipartPart {
+    /// 表单字段名
+    pub name: String,
+    /// 上传文件名，普通字段为 None
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub headers: Vec<(String, String)>,
+    /// 数据长度
+    pub size: usize,
+    /// 请求体被截断或缺少结束分隔符，只有开头部分的数据
+    pub truncated: bool,
+    /// 原始数据，不通过 IPC 传输，前端通过 save_part 按需保存
+    #[serde(skip)]
+    pub data: Vec<u8>,
+    /// 数据在消息体（解压后的内容）中的位置
+    #[serde(skip)]
+    pub range: Range<usize>,
+    /// 普通字段的文本值，文件或无法解码时为 None
+    pub text: Option<String>,
+}
+
+/// 解析 multipart/form-data 消息体，不是 multipart 或缺少 boundary 时返回空列表
+/// body_truncated 表示消息体被截断，此时没有结束分隔符的最后一个部分不完整
+pub fn parse_multipart(
+    content_type: &str,
+    data: &[u8],
+    body_truncated: bool,
+) -> Vec<MultipartPart> {
+    if body::mime_type(content_type) != "multipart/form-data" {
+        return Vec::new();
+    }
+    let boundary = match header_param(content_type, "boundary") {
+        Some(boundary) => boundary,
+        None => {
+            debug!("multipart 请求缺少 boundary: {}", content_type);
+            return Vec::new();
+        }
+    };
+    let charset = body::charset_param(content_type);
+
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = Vec::new();
+
+    let mut pos = match find(data, &delimiter, 0) {
+        Some(start) => start + delimiter.len(),
+        None => return parts,
+    };
+    let mut closed = false;
+    loop {
+        // 结束分隔符
+        if data[pos..].starts_with(b"--") {
+            closed = true;
+            break;
+        }
+        let header_start = match find(data, b"\r\n", pos) {
+            Some(line_end) => line_end + 2,
+            None => break,
+        };
+        let (header_end, body_start) = if data[header_start..].starts_with(b"\r\n") {
+            // 没有消息头的部分
+            (header_start, header_start + 2)
+        } else {
+            match find(data, b"\r\n\r\n", header_start) {
+                Some(end) => (end, end + 4),
+                None => break,
+            }
+        };
+
+        // 数据到下一个分隔符为止；消息体被截断时取到末尾
+        let mut next_delimiter = b"\r\n".to_vec();
+        next_delimiter.extend_from_slice(&delimiter);
+        let (body_end, next) = match find(data, &next_delimiter, body_start) {
+            Some(end) => (end, Some(end + next_delimiter.len())),
+            None => (data.len(), None),
+        };
+
+        let headers = parse_part_headers(&data[header_start..header_end]);
+        let mut part = build_part(headers, data, body_start..body_end, charset.as_deref());
+        part.truncated = next.is_none();
+        parts.push(part);
+
+        match next {
+            Some(next) => pos = next,
+            None => break,
+        }
+    }
+
+    // 消息体被截断时，最后一个部分之后的数据可能已丢失
+    if body_truncated && !closed {
+        if let Some(last) = parts.last_mut() {
+            last.truncated = true;
+        }
+    }
+    parts
+}
+
+/// 缓存请求中 multipart 各部分的位置，以便之后从缓存的消息体中取出并保存到文件
+pub fn remember(request_id: u64, parts: &[MultipartPart]) {
+    let parts = parts
+        .iter()
+        .enumerate()
+        .map(|(index, part)| CachedPart {
+            range: part.range.clone(),
+            truncated: part.truncated,
+            filename: safe_filename(part, index),
+        })
+        .collect();
+    let cache = PART_CACHE.get_or_init(|| Mutex::new(VecDeque::new()));
+    let mut cache = cache.lock().unwrap();
+    if cache.len() >= MAX_CACHED_REQUESTS {
+        cache.pop_front();
+    }
+    cache.push_back((request_id, parts));
+}
+
+/// 将请求中的某个部分保存到下载目录，返回实际保存的路径
+///
+/// 保存位置由后端决定，文件名来自上传的文件名（去掉路径和非法字符），不接受前端传入的路径。
+pub fn save_part(request_id: u64, index: usize) -> Result<String> {
+    let (range, truncated, filename) = PART_CACHE
+        .get()
+        .and_then(|cache| {
+            cache
+                .lock()
+                .unwrap()
+                .iter()
+                .rev()
+                .find(|(id, _)| *id == request_id)
+                .and_then(|(_, parts)| parts.get(index))
+                .map(|part| (part.range.clone(), part.truncated, part.filename.clone()))
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "未找到请求 {} 的第 {} 个部分，可能已过期",
+                request_id,
+                index
+            )
+        })?;
+    if truncated {
+        return Err(anyhow!(
+            "第 {} 个部分不完整（请求体超过 {} 字节被截断或数据缺失），无法保存为完整文件",
+            index,
+            body::MAX_BODY_SIZE
+        ));
+    }
+
+    let app_handle = APP_HANDLE
+        .get()
+        .ok_or_else(|| anyhow!("AppHandle 未初始化"))?;
+    let dir = app_handle
+        .path()
+        .download_dir()
+        .map_err(|e| anyhow!("获取下载目录失败: {}", e))?;
+    let path = unique_path(&dir, &filename);
+
+    // 数据从缓存的消息体中读取，不单独保存一份
+    let data = body::with_cached_body(request_id, |body| {
+        body.content().get(range).map(<[u8]>::to_vec)
+    })?
+    .ok_or_else(|| anyhow!("请求 {} 的消息体与第 {} 个部分不符", request_id, index))?;
+    std::fs::write(&path, &data).map_err(|e| anyhow!("保存文件失败: {}", e))?;
+    info!(
+        "已保存 multipart 数据 {} 字节到 {}",
+        data.len(),
+        path.display()
+    );
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// range 为该部分的数据在消息体中的位置
+fn build_part(
+    headers: Vec<(String, String)>,
+    body: &[u8],
+    range: Range<usize>,
+    charset: Option<&str>,
+) -> MultipartPart {
+    let data = body[range.clone()].to_vec();
+    let disposition = find_header(&headers, "Content-Disposition");
+    let name = header_param(&disposition, "name").unwrap_or_default();
+    let filename = disposition_filename(&disposition);
+    let content_type = find_header(&headers, "Content-Type");
+
+    // 普通字段按部分自身或请求声明的字符集解码
+    let text = if filename.is_none() {
+        let declared = body::charset_param(&content_type);
+        charset::decode(&data, declared.as_deref().or(charset)).map(|decoded| decoded.text)
+    } else {
+        None
+    };
+
+    MultipartPart {
+        name,
+        filename,
+        content_type,
+        headers,
+        size: data.len(),
+        truncated: false,
+        data,
+        range,
+        text,
+    }
+}
+
+fn parse_part_headers(data: &[u8]) -> Vec<(String, String)> {
+    // 文件名中的中文可能是 UTF-8 或 GBK 编码
+    charset::decode_header(data)
+        .split("\r\n")
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// 读取 `a; name="value"; b=c` 形式头部中的参数，支持带引号的值
+fn header_param(value: &str, name: &str) -> Option<String> {
+    split_params(value)
+        .into_iter()
+        .skip(1)
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| unquote(&value))
+}
+
+/// 读取文件名，优先使用 RFC 5987 编码的 filename*
+fn disposition_filename(disposition: &str) -> Option<String> {
+    if let Some(extended) = header_param(disposition, "filename*") {
+        // 格式为 charset'language'percent-encoded
+        let mut fields = extended.splitn(3, '\'');
+        if let (Some(label), Some(_), Some(encoded)) = (fields.next(), fields.next(), fields.next())
+        {
+            let bytes = charset::percent_decode(encoded, false);
+            if let Some(decoded) = charset::decode(&bytes, Some(label)) {
+                return Some(decoded.text);
+            }
+        }
+    }
+    header_param(disposition, "filename")
+}
+
+/// 按分号拆分参数，忽略引号内的分号
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
+
+/// 生成安全的文件名：去掉路径和非法字符
+fn safe_filename(part: &MultipartPart, index: usize) -> String {
+    let original = part.filename.as_deref().unwrap_or(&part.name);
+    // 浏览器可能上传完整路径（旧版 IE）
+    let base = original.rsplit(['/', '\\']).next().unwrap_or("");
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').to_string();
+    if cleaned.is_empty() {
+        format!("part-{}", index)
+    } else {
+        cleaned
+    }
+}
+
+/// 文件已存在时在文件名后追加序号
+fn unique_path(dir: &Path, filename: &str) -> PathBuf {
+    let candidate = dir.join(filename);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let (stem, extension) = match filename.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (filename, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .unwrap_or(candidate)
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if from > data.len() {
+        return None;
+    }
+    data[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| from + pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPE: &str = "multipart/form-data; boundary=XyZ";
+
+    #[test]
+    fn parses_complete_parts() {
+        let data = b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nhello\r\n\
+--XyZ\r\nContent-Disposition: form-data; name=\"f\"; filename=\"x.bin\"\r\nContent-Type: application/octet-stream\r\n\r\n\x00\x01\r\n\
+--XyZ--\r\n";
+        let parts = parse_multipart(CONTENT_TYPE, data, false);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "a");
+        assert_eq!(parts[0].text.as_deref(), Some("hello"));
+        assert_eq!(parts[1].filename.as_deref(), Some("x.bin"));
+        assert_eq!(parts[1].data, vec![0, 1]);
+        assert_eq!(&data[parts[1].range.clone()], &[0, 1]);
+        assert!(parts.iter().all(|part| !part.truncated));
+    }
+
+    #[test]
+    fn safe_filename_strips_directories() {
+        let part = |filename: &str| MultipartPart {
+            name: "file".to_string(),
+            filename: Some(filename.to_string()),
+            content_type: String::new(),
+            headers: Vec::new(),
+            size: 0,
+            truncated: false,
+            data: Vec::new(),
+            range: 0..0,
+            text: None,
+        };
+        assert_eq!(safe_filename(&part("../../.bashrc"), 0), "bashrc");
+        assert_eq!(safe_filename(&part("/etc/passwd"), 0), "passwd");
+        assert_eq!(safe_filename(&part(r"C:\Users\a\报告.pdf"), 0), "报告.pdf");
+        assert_eq!(safe_filename(&part(".."), 3), "part-3");
+        assert_eq!(safe_filename(&part("a<b>:c?.txt"), 0), "abc.txt");
+    }
+
+    #[test]
+    fn serialized_part_omits_data() {
+        let data = b"--XyZ\r\nContent-Disposition: form-data; name=\"f\"; filename=\"x.bin\"\r\n\r\n\x00\x01\r\n--XyZ--\r\n";
+        let parts = parse_multipart(CONTENT_TYPE, data, false);
+        let json = serde_json::to_value(&parts[0]).unwrap();
+        assert!(json.get("data").is_none());
+        assert!(json.get("range").is_none());
+        assert_eq!(json["size"], 2);
+        assert_eq!(json["filename"], "x.bin");
+    }
+
+    #[test]
+    fn missing_closing_delimiter_marks_last_part_truncated() {
+        let data = b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nhello\r\n\
+--XyZ\r\nContent-Disposition: form-data; name=\"f\"; filename=\"x.bin\"\r\n\r\npartial";
+        let parts = parse_multipart(CONTENT_TYPE, data, false);
+        assert_eq!(parts.len(), 2);
+        assert!(!parts[0].truncated);
+        assert!(parts[1].truncated);
+        assert_eq!(parts[1].data, b"partial");
+    }
+
+    #[test]
+    fn truncated_body_marks_last_part_truncated() {
+        // 截断恰好落在分隔符之后，最后一个部分看起来完整
+        let data = b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nhello\r\n--XyZ";
+        let parts = parse_multipart(CONTENT_TYPE, data, true);
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].truncated);
+
+        let closed =
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nhello\r\n--XyZ--\r\n";
+        let parts = parse_multipart(CONTENT_TYPE, closed, true);
+        assert!(!parts[0].truncated);
+    }
+}
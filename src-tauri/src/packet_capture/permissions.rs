@@ -0,0 +1,372 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// 打开网卡失败时附带的提示
+#[cfg(target_os = "linux")]
+pub const PERMISSION_HINT: &str =
+    "请确保可执行文件已设置 cap_net_raw,cap_net_admin 能力或以 root 身份运行";
+#[cfg(target_os = "windows")]
+pub const PERMISSION_HINT: &str = "请确保已安装Npcap";
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub const PERMISSION_HINT: &str = "请确保已安装ChmodBPF";
+
+/// CAP_NET_ADMIN 和 CAP_NET_RAW 在能力位图中的位置
+#[cfg(target_os = "linux")]
+const CAP_NET_ADMIN: u32 = 12;
+#[cfg(target_os = "linux")]
+const CAP_NET_RAW: u32 = 13;
+
+/// 抓包权限检查中的一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// 抓包权限检查报告，供系统权限页面展示缺少的权限和修复方法
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturePermissionReport {
+    /// linux / macos / windows
+    pub platform: String,
+    pub can_capture: bool,
+    pub checks: Vec<PermissionCheck>,
+    /// 修复权限的命令，按顺序执行
+    pub fix_commands: Vec<String>,
+    /// 检查结论
+    pub message: String,
+}
+
+impl PermissionCheck {
+    fn new(name: &str, passed: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Linux：root 用户，或进程拥有 CAP_NET_RAW 和 CAP_NET_ADMIN 能力
+/// libpcap 在 Linux 上使用数据包套接字，不需要 /dev 下的设备权限
+#[cfg(target_os = "linux")]
+pub fn check_capture_permissions() -> CapturePermissionReport {
+    info!("检查 Linux 抓包权限...");
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let mut checks = Vec::new();
+
+    let euid = effective_uid(&status);
+    let is_root = euid == Some(0);
+    checks.push(PermissionCheck::new(
+        "root 用户",
+        is_root,
+        match euid {
+            Some(0) => "以 root 身份运行，拥有全部抓包权限".to_string(),
+            Some(uid) => format!("当前有效用户 ID 为 {}，需要依靠进程能力抓包", uid),
+            None => "无法读取 /proc/self/status".to_string(),
+        },
+    ));
+
+    let effective = effective_caps(&status);
+    let has_cap = |cap: u32| effective & (1 << cap) != 0;
+    let net_raw = has_cap(CAP_NET_RAW);
+    let net_admin = has_cap(CAP_NET_ADMIN);
+    checks.push(PermissionCheck::new(
+        "CAP_NET_RAW",
+        net_raw,
+        if net_raw {
+            "进程拥有 CAP_NET_RAW，可以打开数据包套接字"
+        } else {
+            "进程没有 CAP_NET_RAW，无法打开数据包套接字"
+        },
+    ));
+    checks.push(PermissionCheck::new(
+        "CAP_NET_ADMIN",
+        net_admin,
+        if net_admin {
+            "进程拥有 CAP_NET_ADMIN，可以开启混杂模式"
+        } else {
+            "进程没有 CAP_NET_ADMIN，无法开启混杂模式"
+        },
+    ));
+
+    // 进程能力来自可执行文件上的文件能力，用 getcap 查看
+    let exe = std::env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    let file_caps = std::process::Command::new("getcap")
+        .arg(&exe)
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+    let binary_has_caps = file_caps.as_deref().is_some_and(|caps| {
+        let names = file_capabilities(caps, &exe);
+        names.contains(&"cap_net_raw") && names.contains(&"cap_net_admin")
+    });
+    checks.push(PermissionCheck::new(
+        "可执行文件能力",
+        binary_has_caps,
+        match &file_caps {
+            None => "未找到 getcap 命令（通常在 libcap2-bin 或 libcap 软件包中）".to_string(),
+            Some(_) if binary_has_caps && !(net_raw && net_admin) => format!(
+                "{} 已设置能力，但进程没有获得（文件系统可能以 nosuid 挂载，或进程设置了 no_new_privs）",
+                exe
+            ),
+            Some(_) if binary_has_caps => format!("{} 已设置 cap_net_raw,cap_net_admin", exe),
+            Some(caps) if caps.trim().is_empty() => format!("{} 没有设置文件能力", exe),
+            Some(caps) => format!("{} 的文件能力不完整: {}", exe, caps.trim()),
+        },
+    ));
+
+    let can_capture = is_root || (net_raw && net_admin);
+    let mut fix_commands = Vec::new();
+    if !can_capture {
+        fix_commands.push(format!(
+            "sudo setcap cap_net_raw,cap_net_admin=eip '{}'",
+            exe
+        ));
+    }
+    let message = if can_capture {
+        "已具备抓包权限".to_string()
+    } else {
+        "缺少抓包权限：请为可执行文件设置网络能力后重启应用，或以 root 身份运行".to_string()
+    };
+    report("linux", can_capture, checks, fix_commands, message)
+}
+
+/// 读取 /proc/self/status 中的字段值
+#[cfg(target_os = "linux")]
+fn proc_status_field<'a>(status: &'a str, name: &str) -> Option<&'a str> {
+    status.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key == name).then_some(value.trim())
+    })
+}
+
+/// 有效用户 ID，Uid 行依次是真实、有效、保存和文件系统 UID
+#[cfg(target_os = "linux")]
+fn effective_uid(status: &str) -> Option<u32> {
+    proc_status_field(status, "Uid")
+        .and_then(|uids| uids.split_whitespace().nth(1))
+        .and_then(|uid| uid.parse().ok())
+}
+
+/// CapEff 行的十六进制能力位图，无法解析时视为没有任何能力
+#[cfg(target_os = "linux")]
+fn effective_caps(status: &str) -> u64 {
+    proc_status_field(status, "CapEff")
+        .and_then(|caps| u64::from_str_radix(caps, 16).ok())
+        .unwrap_or(0)
+}
+
+/// getcap 输出中列出的能力名称
+///
+/// 新版 libcap 输出 `<path> cap_net_admin,cap_net_raw=eip`，
+/// 旧版输出 `<path> = cap_net_admin,cap_net_raw+eip`，先去掉路径以免路径中的文字被当作能力。
+#[cfg(target_os = "linux")]
+fn file_capabilities<'a>(output: &'a str, exe: &str) -> Vec<&'a str> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix(exe))
+        .flat_map(|caps| caps.split(|c: char| c == ',' || c.is_whitespace() || "=+-".contains(c)))
+        .filter(|name| name.starts_with("cap_"))
+        .collect()
+}
+
+/// macOS：需要读取 /dev/bpf* 设备，通常由 ChmodBPF 服务在开机时授权
+#[cfg(target_os = "macos")]
+pub fn check_capture_permissions() -> CapturePermissionReport {
+    use std::path::Path;
+
+    info!("检查 macOS 抓包权限...");
+    let mut checks = Vec::new();
+
+    let chmodbpf = Path::new("/Library/LaunchDaemons/org.wireshark.ChmodBPF.plist").exists();
+    checks.push(PermissionCheck::new(
+        "ChmodBPF 服务",
+        chmodbpf,
+        if chmodbpf {
+            "已安装 ChmodBPF 服务"
+        } else {
+            "未安装 ChmodBPF 服务"
+        },
+    ));
+
+    // 打开后立即关闭，不占用 BPF 设备
+    let bpf_readable = std::fs::File::open("/dev/bpf0").is_ok();
+    checks.push(PermissionCheck::new(
+        "/dev/bpf* 读取权限",
+        bpf_readable,
+        if bpf_readable {
+            "当前用户可以读取 BPF 设备"
+        } else {
+            "当前用户无法读取 /dev/bpf*，安装 ChmodBPF 后需要重新登录或重启"
+        },
+    ));
+
+    let can_capture = chmodbpf || bpf_readable;
+    let mut fix_commands = Vec::new();
+    if !chmodbpf {
+        fix_commands.push("sudo installer -pkg 'pkg/Install ChmodBPF.pkg' -target /".to_string());
+    }
+    let message = if can_capture {
+        "已具备抓包权限".to_string()
+    } else {
+        "缺少网络捕获权限，请安装 ChmodBPF 权限包".to_string()
+    };
+    report("macos", can_capture, checks, fix_commands, message)
+}
+
+/// Windows：需要安装 Npcap
+#[cfg(target_os = "windows")]
+pub fn check_capture_permissions() -> CapturePermissionReport {
+    use std::path::Path;
+
+    info!("检查 Windows 上 Npcap 是否已安装...");
+    let npcap = Path::new("C:\\Windows\\System32\\Npcap").exists();
+    let checks = vec![PermissionCheck::new(
+        "Npcap",
+        npcap,
+        if npcap {
+            "检测到 Npcap 安装目录"
+        } else {
+            "未安装 Npcap，请从 https://npcap.com 下载安装"
+        },
+    )];
+
+    let message = if npcap {
+        "已具备抓包权限".to_string()
+    } else {
+        "缺少网络捕获驱动，请安装 Npcap".to_string()
+    };
+    report("windows", npcap, checks, Vec::new(), message)
+}
+
+/// 其他平台不支持检查
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn check_capture_permissions() -> CapturePermissionReport {
+    report(
+        std::env::consts::OS,
+        false,
+        Vec::new(),
+        Vec::new(),
+        "当前平台不支持抓包权限检查".to_string(),
+    )
+}
+
+fn report(
+    platform: &str,
+    can_capture: bool,
+    checks: Vec<PermissionCheck>,
+    fix_commands: Vec<String>,
+    message: String,
+) -> CapturePermissionReport {
+    for check in checks.iter().filter(|check| !check.passed) {
+        info!("抓包权限检查未通过 [{}]: {}", check.name, check.detail);
+    }
+    CapturePermissionReport {
+        platform: platform.to_string(),
+        can_capture,
+        checks,
+        fix_commands,
+        message,
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    const EXE: &str = "/opt/oa-capture/oa-capture";
+
+    fn status(uid: &str, cap_eff: &str) -> String {
+        format!(
+            "Name:\toa-capture\nUid:\t{}\nGid:\t1000\t1000\t1000\t1000\n\
+             CapInh:\t0000000000000000\nCapPrm:\t{}\nCapEff:\t{}\n",
+            uid, cap_eff, cap_eff
+        )
+    }
+
+    /// getcap 输出中的能力名称
+    fn getcap(output: &str, exe: &str) -> Vec<String> {
+        file_capabilities(output, exe)
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn has_cap(status: &str, cap: u32) -> bool {
+        effective_caps(status) & (1 << cap) != 0
+    }
+
+    #[test]
+    fn cap_eff_mask_from_proc_status() {
+        // setcap cap_net_raw,cap_net_admin=eip 后的普通用户进程
+        let granted = status("1000\t1000\t1000\t1000", "0000000000003000");
+        assert_eq!(effective_uid(&granted), Some(1000));
+        assert_eq!(effective_caps(&granted), 0x3000);
+        assert!(has_cap(&granted, CAP_NET_RAW));
+        assert!(has_cap(&granted, CAP_NET_ADMIN));
+
+        // 只有 CAP_NET_RAW
+        let raw_only = status("1000\t1000\t1000\t1000", "0000000000002000");
+        assert!(has_cap(&raw_only, CAP_NET_RAW));
+        assert!(!has_cap(&raw_only, CAP_NET_ADMIN));
+
+        // root 拥有全部能力；有效 UID 取第二列
+        let root = status("1000\t0\t0\t0", "000001ffffffffff");
+        assert_eq!(effective_uid(&root), Some(0));
+        assert!(has_cap(&root, CAP_NET_RAW) && has_cap(&root, CAP_NET_ADMIN));
+
+        let none = status("1000\t1000\t1000\t1000", "0000000000000000");
+        assert!(!has_cap(&none, CAP_NET_RAW));
+        assert!(!has_cap(&none, CAP_NET_ADMIN));
+    }
+
+    #[test]
+    fn malformed_proc_status() {
+        assert_eq!(effective_uid(""), None);
+        assert_eq!(effective_caps(""), 0);
+        assert_eq!(effective_caps("CapEff:\tzzzz\n"), 0);
+        assert_eq!(effective_caps("CapEff:\n"), 0);
+        // 超过 64 位的位图
+        assert_eq!(effective_caps("CapEff:\t1ffffffffffffffffff\n"), 0);
+        // 字段名必须完全一致
+        assert_eq!(effective_caps("CapEffX:\t3000\n"), 0);
+        assert_eq!(effective_uid("Uid:\t1000\n"), None);
+        assert_eq!(effective_uid("Uid:\tabc\tdef\n"), None);
+    }
+
+    #[test]
+    fn getcap_output_formats() {
+        // libcap 2.41 之后的格式
+        let caps = getcap(&format!("{} cap_net_admin,cap_net_raw=eip\n", EXE), EXE);
+        assert_eq!(caps, ["cap_net_admin", "cap_net_raw"]);
+
+        // 旧版格式
+        let caps = getcap(&format!("{} = cap_net_admin,cap_net_raw+eip\n", EXE), EXE);
+        assert_eq!(caps, ["cap_net_admin", "cap_net_raw"]);
+
+        // 每项能力单独设置标志
+        let caps = getcap(&format!("{} cap_net_raw+ep cap_net_admin+ep", EXE), EXE);
+        assert_eq!(caps, ["cap_net_raw", "cap_net_admin"]);
+    }
+
+    #[test]
+    fn getcap_missing_capabilities() {
+        // 没有设置文件能力时 getcap 没有输出
+        assert!(file_capabilities("", EXE).is_empty());
+
+        let caps = getcap(&format!("{} cap_net_raw=ep\n", EXE), EXE);
+        assert_eq!(caps, ["cap_net_raw"]);
+
+        // 路径中的文字不算作能力
+        let exe = "/home/cap_net_raw/cap_net_admin/oa-capture";
+        let caps = getcap(&format!("{} cap_sys_ptrace=ep", exe), exe);
+        assert_eq!(caps, ["cap_sys_ptrace"]);
+
+        // 错误信息或其他文件的输出
+        assert!(file_capabilities("Failed to get capabilities of file", EXE).is_empty());
+        assert!(file_capabilities("/usr/bin/ping cap_net_raw=ep", EXE).is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+use super::{CapturedPacket, HttpRequest};
+use log::{debug, error, warn};
+use once_cell::sync::OnceCell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread;
+
+/// 最多启动的解析线程数
+const MAX_DISSECTION_WORKERS: usize = 4;
+/// 等待认证系统处理的请求队列容量
+const AUTH_QUEUE_CAPACITY: usize = 1024;
+
+// 认证处理线程的请求队列，首次使用时启动线程，抓包和本地代理共用
+static AUTH_QUEUE: OnceCell<SyncSender<HttpRequest>> = OnceCell::new();
+// 因认证队列已满而未交给认证系统的请求数（应用启动以来）
+static AUTH_DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// 解析线程数：保留一半的 CPU 给读取线程和界面
+pub fn worker_count() -> usize {
+    thread::available_parallelism()
+        .map(|cpus| cpus.get() / 2)
+        .unwrap_or(1)
+        .clamp(1, MAX_DISSECTION_WORKERS)
+}
+
+/// 数据包的分发结果
+pub(super) enum Dispatch {
+    Queued,
+    /// 解析队列已满，数据包被丢弃
+    Dropped,
+    /// 解析线程已退出
+    Closed,
+}
+
+/// 把读取线程捕获的数据包分发到各解析线程的有界队列
+///
+/// 同一对 IP 地址之间的数据包（包括两个方向和 IP 分片）总是进入同一个解析线程，
+/// 保证分片重组、TCP 流重组和请求响应配对只需要线程内的状态。
+#[derive(Clone)]
+pub(super) struct PacketDispatcher {
+    senders: Vec<SyncSender<CapturedPacket>>,
+    /// 队列已满时阻塞等待（回放文件），否则丢弃数据包（实时捕获，避免读取线程跟不上内核）
+    blocking: bool,
+}
+
+impl PacketDispatcher {
+    pub(super) fn new(senders: Vec<SyncSender<CapturedPacket>>, blocking: bool) -> Self {
+        Self { senders, blocking }
+    }
+
+    pub(super) fn dispatch(&self, packet: CapturedPacket) -> Dispatch {
+        let sender = &self.senders[shard(&packet, self.senders.len())];
+        if self.blocking {
+            return match sender.send(packet) {
+                Ok(()) => Dispatch::Queued,
+                Err(_) => Dispatch::Closed,
+            };
+        }
+        match sender.try_send(packet) {
+            Ok(()) => Dispatch::Queued,
+            Err(TrySendError::Full(_)) => Dispatch::Dropped,
+            Err(TrySendError::Disconnected(_)) => Dispatch::Closed,
+        }
+    }
+}
+
+/// 按源地址和目的地址选择解析线程，地址排序后再计算，使两个方向结果相同
+/// 非 IP 数据包都交给第一个解析线程
+fn shard(packet: &CapturedPacket, workers: usize) -> usize {
+    if workers <= 1 {
+        return 0;
+    }
+    let Some(ip_packet) = packet.link_type.ip_packet(&packet.data) else {
+        return 0;
+    };
+    let Some(&first) = ip_packet.first() else {
+        return 0;
+    };
+    let addresses = match first >> 4 {
+        4 => ip_packet.get(12..16).zip(ip_packet.get(16..20)),
+        _ => ip_packet.get(8..24).zip(ip_packet.get(24..40)),
+    };
+    let Some((src, dst)) = addresses else {
+        return 0;
+    };
+
+    let mut hasher = DefaultHasher::new();
+    src.min(dst).hash(&mut hasher);
+    src.max(dst).hash(&mut hasher);
+    (hasher.finish() % workers as u64) as usize
+}
+
+/// 把请求交给认证处理线程，队列已满时丢弃并计数，不阻塞解析线程
+pub fn submit_auth_request(request: &HttpRequest) {
+    let queue = AUTH_QUEUE.get_or_init(spawn_auth_consumer);
+    match queue.try_send(request.clone()) {
+        Ok(()) => {}
+        Err(TrySendError::Full(request)) => {
+            let dropped = AUTH_DROPPED.fetch_add(1, Ordering::Relaxed) + 1;
+            debug!(
+                "认证队列已满，丢弃请求: {} {}",
+                request.method, request.path
+            );
+            // 持续积压时只偶尔输出警告
+            if dropped.is_power_of_two() {
+                warn!("认证系统处理过慢，已有 {} 个请求未能交给认证系统", dropped);
+            }
+        }
+        Err(TrySendError::Disconnected(_)) => error!("认证处理线程已退出，无法处理请求"),
+    }
+}
+
+/// 因认证队列已满而丢弃的请求总数
+pub fn auth_dropped() -> u64 {
+    AUTH_DROPPED.load(Ordering::Relaxed)
+}
+
+/// 启动认证处理线程：依次把请求交给认证系统，TokenManager 的锁和各系统的匹配都在这里进行
+fn spawn_auth_consumer() -> SyncSender<HttpRequest> {
+    let (sender, receiver) = mpsc::sync_channel::<HttpRequest>(AUTH_QUEUE_CAPACITY);
+    thread::spawn(move || {
+        for request in receiver {
+            if let Err(e) = crate::auth::process_http_request(&request) {
+                debug!(
+                    "认证系统处理请求 {} {} 失败: {}",
+                    request.method, request.path, e
+                );
+            }
+        }
+    });
+    sender
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_capture::link::LinkType;
+
+    fn packet(data: Vec<u8>) -> CapturedPacket {
+        CapturedPacket {
+            timestamp_ms: 0,
+            link_type: LinkType::Raw,
+            data,
+        }
+    }
+
+    fn ipv4(src: [u8; 4], dst: [u8; 4]) -> Vec<u8> {
+        let mut data = vec![0x45; 12];
+        data.extend_from_slice(&src);
+        data.extend_from_slice(&dst);
+        data
+    }
+
+    #[test]
+    fn both_directions_use_same_worker() {
+        let a = [10, 0, 0, 1];
+        let b = [192, 168, 1, 20];
+        for workers in 2..=4 {
+            assert_eq!(
+                shard(&packet(ipv4(a, b)), workers),
+                shard(&packet(ipv4(b, a)), workers)
+            );
+        }
+    }
+
+    #[test]
+    fn short_or_empty_packets_go_to_first_worker() {
+        assert_eq!(shard(&packet(Vec::new()), 4), 0);
+        assert_eq!(shard(&packet(vec![0x45, 0, 0]), 4), 0);
+        assert_eq!(shard(&packet(vec![0x60; 20]), 4), 0);
+    }
+}
@@ -0,0 +1,1050 @@
+use super::ca;
+use super::http::{self, HttpDissector, HttpMessage};
+use super::reassembly::{FlowKey, StreamBuffer};
+use super::settings;
+use super::tls::{self, ClientHello, ClientHelloParse};
+use anyhow::{anyhow, Result};
+use log::{debug, error, info, warn};
+use once_cell::sync::OnceCell;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, ServerConnection,
+    SignatureScheme, StreamOwned,
+};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 默认监听端口
+pub const DEFAULT_PORT: u16 = 8899;
+/// 请求头的最大长度
+const MAX_HEAD_LEN: usize = 64 * 1024;
+/// 最多同时处理的客户端连接数量
+const MAX_CONNECTIONS: usize = 256;
+/// 连接上游服务器和 TLS 握手的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// 转发数据时轮询两端的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// 检查停止信号的间隔
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+/// 连接空闲超时时间
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+/// 读写缓冲区大小
+const BUFFER_SIZE: usize = 16 * 1024;
+/// 转发给上游时去掉的逐跳头部
+const HOP_BY_HOP_HEADERS: [&str; 4] = [
+    "connection",
+    "proxy-connection",
+    "proxy-authorization",
+    "keep-alive",
+];
+
+// 当前运行的代理
+static PROXY: OnceCell<Mutex<Option<ProxyServer>>> = OnceCell::new();
+
+/// 本地代理配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    /// 监听端口（只监听 127.0.0.1）
+    pub port: u16,
+    /// 是否校验上游服务器的证书，内网系统使用自签名证书时需要关闭
+    pub verify_upstream: bool,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            verify_upstream: true,
+        }
+    }
+}
+
+/// 本地代理状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyStatus {
+    pub running: bool,
+    pub port: u16,
+    pub verify_upstream: bool,
+    /// 根证书路径，安装为受信任的根证书后才能解析 HTTPS 请求
+    pub ca_cert_path: Option<String>,
+    /// 正在处理的客户端连接数量
+    pub connections: usize,
+    pub message: String,
+}
+
+/// 正在运行的代理
+struct ProxyServer {
+    config: ProxyConfig,
+    context: Arc<ProxyContext>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+/// 监听线程和连接线程共享的状态
+struct ProxyContext {
+    running: AtomicBool,
+    port: u16,
+    client_config: Arc<ClientConfig>,
+    connections: AtomicUsize,
+}
+
+/// 连接数量计数，连接线程结束时自动减一
+struct ConnectionGuard<'a>(&'a AtomicUsize);
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn proxy() -> &'static Mutex<Option<ProxyServer>> {
+    PROXY.get_or_init(|| Mutex::new(None))
+}
+
+/// 启动本地代理，config 为空时使用上次保存的配置
+pub fn start_proxy(config: Option<ProxyConfig>) -> Result<ProxyStatus> {
+    let config = match config {
+        Some(config) => {
+            // 记住用户的配置
+            if let Err(e) = settings::update_settings(|s| s.proxy = config.clone()) {
+                error!("保存代理配置失败: {}", e);
+            }
+            config
+        }
+        None => settings::get_settings().proxy,
+    };
+    if config.port == 0 {
+        return Err(anyhow!("代理端口无效: {}", config.port));
+    }
+
+    // 如果已经在运行，先停止
+    stop_proxy()?;
+
+    let authority = ca::authority()?;
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, config.port))
+        .map_err(|e| anyhow!("无法监听端口 {}: {}", config.port, e))?;
+    listener.set_nonblocking(true)?;
+
+    let context = Arc::new(ProxyContext {
+        running: AtomicBool::new(true),
+        port: config.port,
+        client_config: client_config(config.verify_upstream)?,
+        connections: AtomicUsize::new(0),
+    });
+    let listener_context = context.clone();
+    let thread = thread::spawn(move || run_listener(listener, listener_context));
+
+    info!(
+        "本地代理已启动: 127.0.0.1:{}，根证书: {}",
+        config.port,
+        authority.cert_path().display()
+    );
+    *proxy().lock().unwrap() = Some(ProxyServer {
+        config,
+        context,
+        thread: Some(thread),
+    });
+    Ok(get_proxy_status())
+}
+
+/// 停止本地代理，正在转发的连接会在下一次轮询时结束
+pub fn stop_proxy() -> Result<()> {
+    let server = proxy().lock().unwrap().take();
+    if let Some(mut server) = server {
+        server.context.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = server.thread.take() {
+            let _ = thread.join();
+        }
+        info!("本地代理已停止");
+    }
+    Ok(())
+}
+
+/// 获取本地代理状态
+pub fn get_proxy_status() -> ProxyStatus {
+    let ca_cert_path = ca::authority()
+        .map(|authority| authority.cert_path().display().to_string())
+        .ok();
+    match &*proxy().lock().unwrap() {
+        Some(server) => ProxyStatus {
+            running: true,
+            port: server.config.port,
+            verify_upstream: server.config.verify_upstream,
+            ca_cert_path,
+            connections: server.context.connections.load(Ordering::Relaxed),
+            message: format!("代理运行中: 127.0.0.1:{}", server.config.port),
+        },
+        None => {
+            let config = settings::get_settings().proxy;
+            ProxyStatus {
+                running: false,
+                port: config.port,
+                verify_upstream: config.verify_upstream,
+                ca_cert_path,
+                connections: 0,
+                message: "代理未启动".to_string(),
+            }
+        }
+    }
+}
+
+/// 连接上游 HTTPS 服务器使用的 TLS 配置
+fn client_config(verify_upstream: bool) -> Result<Arc<ClientConfig>> {
+    let mut config = if verify_upstream {
+        let native = rustls_native_certs::load_native_certs();
+        for e in &native.errors {
+            warn!("加载系统根证书出错: {}", e);
+        }
+        let mut roots = RootCertStore::empty();
+        let (added, ignored) = roots.add_parsable_certificates(native.certs);
+        debug!("已加载 {} 个系统根证书，忽略 {} 个", added, ignored);
+        if roots.is_empty() {
+            return Err(anyhow!("没有可用的系统根证书，无法校验上游服务器证书"));
+        }
+        ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth()
+    } else {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+            .with_no_client_auth()
+    };
+    // 与客户端一侧保持一致，只使用 HTTP/1.1
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// 不校验上游服务器证书（仍然校验握手签名）
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// 监听循环：为每个客户端连接启动一个转发线程，直到代理停止
+fn run_listener(listener: TcpListener, context: Arc<ProxyContext>) {
+    while context.running.load(Ordering::Relaxed) {
+        let (client, peer) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+            Err(e) => {
+                warn!("接受代理连接失败: {}", e);
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+        };
+
+        if context.connections.load(Ordering::Relaxed) >= MAX_CONNECTIONS {
+            warn!(
+                "代理连接数量达到上限 {}，拒绝来自 {} 的连接",
+                MAX_CONNECTIONS, peer
+            );
+            continue;
+        }
+        context.connections.fetch_add(1, Ordering::Relaxed);
+
+        let context = context.clone();
+        thread::spawn(move || {
+            let _guard = ConnectionGuard(&context.connections);
+            if let Err(e) = handle_client(client, peer, &context) {
+                debug!("代理连接 {} 结束: {}", peer, e);
+            }
+        });
+    }
+    debug!("代理监听线程已退出");
+}
+
+/// 处理一个客户端连接：CONNECT 隧道或普通的 HTTP 代理请求
+fn handle_client(mut client: TcpStream, peer: SocketAddr, context: &ProxyContext) -> Result<()> {
+    // 部分系统上接受的连接会继承监听套接字的非阻塞模式
+    client.set_nonblocking(false)?;
+    client.set_nodelay(true)?;
+    client.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+
+    let (head, rest) = read_request_head(&mut client)?;
+    let (method, target) = parse_request_line(&head).ok_or_else(|| anyhow!("无效的代理请求"))?;
+    debug!("代理请求: {} {} (来自 {})", method, target, peer);
+
+    if method.eq_ignore_ascii_case("CONNECT") {
+        let (host, port) = split_host_port(&target, 443)
+            .ok_or_else(|| anyhow!("无效的 CONNECT 目标: {}", target))?;
+        let upstream = match connect_upstream(&host, port) {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                send_error(&mut client, "502 Bad Gateway", &e.to_string());
+                return Err(e);
+            }
+        };
+        client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+        return handle_tunnel(client, upstream, peer, &host, context);
+    }
+
+    let (host, port, path) = match parse_absolute_uri(&target) {
+        Some(parsed) => parsed,
+        None => {
+            // 直接访问代理本身（如 http://127.0.0.1:8899/），返回根证书供安装
+            return serve_ca_certificate(&mut client);
+        }
+    };
+    if port == context.port && is_local_host(&host) {
+        return serve_ca_certificate(&mut client);
+    }
+
+    let upstream = match connect_upstream(&host, port) {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            send_error(&mut client, "502 Bad Gateway", &e.to_string());
+            return Err(e);
+        }
+    };
+    let key = flow_key(peer, &upstream)?;
+
+    // 每个连接只转发一个请求，后续请求由浏览器重新建立连接，以便按请求选择上游服务器
+    let mut single = match SingleRequest::new(&head) {
+        Some(single) => single,
+        None => {
+            send_error(&mut client, "400 Bad Request", "无法确定请求体的长度");
+            return Err(anyhow!("无法确定请求体的长度: {} {}", method, target));
+        }
+    };
+    let mut first = rewrite_request_head(&head, &path);
+    let len = single.request_bytes(&rest);
+    first.extend_from_slice(&rest[..len]);
+    relay(
+        Box::new(client),
+        Box::new(upstream),
+        key,
+        first,
+        Some(single),
+        context,
+    )
+}
+
+/// 处理 CONNECT 隧道：TLS 连接使用本地根证书解密，其他协议直接按明文转发
+fn handle_tunnel(
+    client: TcpStream,
+    upstream: TcpStream,
+    peer: SocketAddr,
+    host: &str,
+    context: &ProxyContext,
+) -> Result<()> {
+    let key = flow_key(peer, &upstream)?;
+    let hello = match peek_client_hello(&client)? {
+        Some(hello) => hello,
+        None => {
+            return relay(
+                Box::new(client),
+                Box::new(upstream),
+                key,
+                Vec::new(),
+                None,
+                context,
+            )
+        }
+    };
+
+    let server_name = hello.sni.clone().unwrap_or_else(|| host.to_string());
+    // 先与上游完成握手，失败时直接断开客户端，浏览器会显示连接错误
+    let upstream = connect_tls(upstream, &server_name, context)?;
+    let client = accept_tls(client, &server_name)?;
+    super::handle_http_message(HttpMessage::Tls(http::new_tls_connection(
+        hello,
+        &key,
+        now_ms(),
+    )));
+
+    relay(
+        Box::new(client),
+        Box::new(upstream),
+        key,
+        Vec::new(),
+        None,
+        context,
+    )
+}
+
+/// 读取请求头，返回请求头和已读取的请求体开头部分
+fn read_request_head(client: &mut TcpStream) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = client.read(&mut buf)?;
+        if n == 0 {
+            return Err(anyhow!("客户端在发送请求头之前关闭了连接"));
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(end) = http::find_header_end(&data) {
+            let rest = data.split_off(end);
+            return Ok((data, rest));
+        }
+        if data.len() > MAX_HEAD_LEN {
+            return Err(anyhow!("请求头过长"));
+        }
+    }
+}
+
+/// 解析请求行中的方法和请求目标
+fn parse_request_line(head: &[u8]) -> Option<(String, String)> {
+    let line = head.split(|&b| b == b'\n').next()?;
+    let line = std::str::from_utf8(line).ok()?.trim_end_matches('\r');
+    let mut parts = line.split(' ');
+    let method = parts.next().filter(|method| !method.is_empty())?;
+    let target = parts.next().filter(|target| !target.is_empty())?;
+    Some((method.to_string(), target.to_string()))
+}
+
+/// 解析 http:// 开头的绝对 URI，返回主机、端口和路径
+fn parse_absolute_uri(target: &str) -> Option<(String, u16, String)> {
+    let scheme_end = target.find("://")?;
+    if !target[..scheme_end].eq_ignore_ascii_case("http") {
+        return None;
+    }
+    let rest = &target[scheme_end + 3..];
+    let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let authority = &rest[..authority_end];
+    // 去掉用户信息
+    let authority = authority.rsplit('@').next().unwrap_or(authority);
+    let (host, port) = split_host_port(authority, 80)?;
+
+    let path = match &rest[authority_end..] {
+        "" => "/".to_string(),
+        path if path.starts_with('?') => format!("/{}", path),
+        path => path.to_string(),
+    };
+    Some((host, port, path))
+}
+
+/// 拆分 host:port，IPv6 地址需要使用方括号
+fn split_host_port(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        let end = rest.find(']')?;
+        let port = match &rest[end + 1..] {
+            "" => None,
+            port => Some(port.strip_prefix(':')?),
+        };
+        (&rest[..end], port)
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let port = match port {
+        Some(port) => port.parse().ok().filter(|&port| port != 0)?,
+        None => default_port,
+    };
+    Some((host.to_string(), port))
+}
+
+fn is_local_host(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified())
+}
+
+/// 将请求行改为 origin-form，去掉逐跳头部，并要求上游在响应后关闭连接
+fn rewrite_request_head(head: &[u8], path: &str) -> Vec<u8> {
+    let mut lines = head_lines(head);
+    let mut rewritten = Vec::with_capacity(head.len());
+
+    if let Some(request_line) = lines.next() {
+        let mut parts = request_line.splitn(3, |&b| b == b' ');
+        let method = parts.next().unwrap_or_default();
+        let version = parts.nth(1).unwrap_or(b"HTTP/1.1");
+        rewritten.extend_from_slice(method);
+        rewritten.push(b' ');
+        rewritten.extend_from_slice(path.as_bytes());
+        rewritten.push(b' ');
+        rewritten.extend_from_slice(version);
+        rewritten.extend_from_slice(b"\r\n");
+    }
+
+    push_end_to_end_headers(lines, &mut rewritten);
+    rewritten
+}
+
+/// 在响应头中声明 Connection: close，客户端之后不会在这个连接上发送新的请求
+fn rewrite_response_head(head: &[u8]) -> Vec<u8> {
+    let mut lines = head_lines(head);
+    let mut rewritten = Vec::with_capacity(head.len());
+    if let Some(status_line) = lines.next() {
+        rewritten.extend_from_slice(status_line);
+        rewritten.extend_from_slice(b"\r\n");
+    }
+    push_end_to_end_headers(lines, &mut rewritten);
+    rewritten
+}
+
+fn head_lines(head: &[u8]) -> impl Iterator<Item = &[u8]> {
+    head.split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// 去掉逐跳头部后写入其余的头部，并以 Connection: close 结束消息头
+fn push_end_to_end_headers<'a>(lines: impl Iterator<Item = &'a [u8]>, rewritten: &mut Vec<u8>) {
+    for line in lines.filter(|line| !line.is_empty()) {
+        let name = line.split(|&b| b == b':').next().unwrap_or_default();
+        let hop_by_hop = HOP_BY_HOP_HEADERS
+            .iter()
+            .any(|header| name.trim_ascii().eq_ignore_ascii_case(header.as_bytes()));
+        if !hop_by_hop {
+            rewritten.extend_from_slice(line);
+            rewritten.extend_from_slice(b"\r\n");
+        }
+    }
+    rewritten.extend_from_slice(b"Connection: close\r\n\r\n");
+}
+
+/// 普通 HTTP 代理连接上唯一转发的请求
+///
+/// 上游连接是按第一个请求的目标建立的，之后客户端在同一连接上发送的数据不再转发；
+/// 响应头中的 Connection: close 告诉客户端为下一个请求重新建立连接。
+struct SingleRequest {
+    request: http::RequestEnd,
+    /// 尚未收到完整的最终响应头时缓存的响应数据
+    response_head: Vec<u8>,
+    /// 最终响应头已经改写并转发，之后的数据原样转发
+    response_started: bool,
+}
+
+impl SingleRequest {
+    fn new(request_head: &[u8]) -> Option<Self> {
+        Some(Self {
+            request: http::RequestEnd::new(request_head)?,
+            response_head: Vec::new(),
+            response_started: false,
+        })
+    }
+
+    /// 客户端发送的数据中属于该请求的字节数
+    fn request_bytes(&mut self, data: &[u8]) -> usize {
+        self.request.advance(data)
+    }
+
+    /// 需要转发给客户端的响应数据，最终响应头到达之前先缓存
+    fn response_bytes(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.response_started {
+            return data.to_vec();
+        }
+        self.response_head.extend_from_slice(data);
+
+        let mut forward = Vec::new();
+        while let Some(end) = http::find_header_end(&self.response_head) {
+            let head: Vec<u8> = self.response_head.drain(..end).collect();
+            // 100 Continue 等临时响应之后还有最终响应；101 切换协议时不能改写
+            match status_code(&head) {
+                Some(code) if (100..200).contains(&code) && code != 101 => {
+                    forward.extend_from_slice(&head);
+                    continue;
+                }
+                Some(101) => forward.extend_from_slice(&head),
+                _ => forward.extend(rewrite_response_head(&head)),
+            }
+            self.response_started = true;
+            break;
+        }
+        if !self.response_started && self.response_head.len() > MAX_HEAD_LEN {
+            debug!("响应头过长，不再改写");
+            self.response_started = true;
+        }
+        if self.response_started {
+            forward.append(&mut self.response_head);
+        }
+        forward
+    }
+
+    /// 上游关闭连接时仍未转发的响应数据
+    fn finish_response(&mut self) -> Vec<u8> {
+        self.response_started = true;
+        std::mem::take(&mut self.response_head)
+    }
+}
+
+/// 响应状态行中的状态码
+fn status_code(head: &[u8]) -> Option<u16> {
+    let status_line = head_lines(head).next()?;
+    let code = status_line.split(|&b| b == b' ').nth(1)?;
+    std::str::from_utf8(code).ok()?.parse().ok()
+}
+
+fn connect_upstream(host: &str, port: u16) -> Result<TcpStream> {
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| anyhow!("无法解析 {}: {}", host, e))?
+        .collect();
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => anyhow!("无法连接 {}:{}: {}", host, port, e),
+        None => anyhow!("无法解析 {}", host),
+    })
+}
+
+/// 代理连接的四元组：客户端 -> 上游服务器
+fn flow_key(peer: SocketAddr, upstream: &TcpStream) -> Result<FlowKey> {
+    let server = upstream.peer_addr()?;
+    Ok(FlowKey {
+        src_ip: peer.ip(),
+        src_port: peer.port(),
+        dst_ip: server.ip(),
+        dst_port: server.port(),
+    })
+}
+
+/// 查看客户端发送的第一个 TLS 记录，不是 TLS 握手时返回 None
+fn peek_client_hello(client: &TcpStream) -> Result<Option<ClientHello>> {
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    let started = Instant::now();
+    loop {
+        let n = client.peek(&mut buf)?;
+        if n == 0 {
+            return Err(anyhow!("客户端关闭了连接"));
+        }
+        if !tls::is_handshake_prefix(&buf[..n]) {
+            return Ok(None);
+        }
+        match tls::parse_client_hello(&buf[..n]) {
+            ClientHelloParse::Complete(hello, _) => return Ok(Some(hello)),
+            ClientHelloParse::Incomplete if started.elapsed() < CONNECT_TIMEOUT => {
+                // 数据尚未收全，缓冲区不够时扩大后重试
+                if n == buf.len() {
+                    buf.resize(buf.len() * 2, 0);
+                } else {
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+            _ => return Err(anyhow!("无效的 TLS ClientHello")),
+        }
+    }
+}
+
+/// 与上游服务器完成 TLS 握手
+fn connect_tls(
+    mut upstream: TcpStream,
+    server_name: &str,
+    context: &ProxyContext,
+) -> Result<StreamOwned<ClientConnection, TcpStream>> {
+    let name = ServerName::try_from(server_name.to_string())
+        .map_err(|e| anyhow!("无效的服务器名称 {}: {}", server_name, e))?;
+    let mut connection = ClientConnection::new(context.client_config.clone(), name)?;
+    upstream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    while connection.is_handshaking() {
+        connection
+            .complete_io(&mut upstream)
+            .map_err(|e| anyhow!("与上游服务器 {} 握手失败: {}", server_name, e))?;
+    }
+    Ok(StreamOwned::new(connection, upstream))
+}
+
+/// 使用本地根证书签发的证书与客户端完成 TLS 握手
+fn accept_tls(
+    mut client: TcpStream,
+    server_name: &str,
+) -> Result<StreamOwned<ServerConnection, TcpStream>> {
+    let config = ca::authority()?.server_config(server_name)?;
+    let mut connection = ServerConnection::new(config)?;
+    while connection.is_handshaking() {
+        connection
+            .complete_io(&mut client)
+            .map_err(|e| anyhow!("与客户端握手失败（是否已安装并信任本地根证书？）: {}", e))?;
+    }
+    Ok(StreamOwned::new(connection, client))
+}
+
+/// 代理两端的连接：TCP 或 TLS
+trait ProxyStream: Read + Write + Send {
+    fn socket(&self) -> &TcpStream;
+
+    /// 关闭写入方向，告诉对端不会再发送数据，仍然可以继续读取
+    fn shutdown_write(&mut self);
+
+    /// 结束连接，TLS 连接先发送 close_notify
+    fn close(&mut self);
+}
+
+impl ProxyStream for TcpStream {
+    fn socket(&self) -> &TcpStream {
+        self
+    }
+
+    fn shutdown_write(&mut self) {
+        let _ = self.shutdown(Shutdown::Write);
+    }
+
+    fn close(&mut self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+impl ProxyStream for StreamOwned<ServerConnection, TcpStream> {
+    fn socket(&self) -> &TcpStream {
+        &self.sock
+    }
+
+    fn shutdown_write(&mut self) {
+        self.conn.send_close_notify();
+        let _ = self.conn.complete_io(&mut self.sock);
+        let _ = self.sock.shutdown(Shutdown::Write);
+    }
+
+    fn close(&mut self) {
+        self.conn.send_close_notify();
+        let _ = self.conn.complete_io(&mut self.sock);
+        let _ = self.sock.shutdown(Shutdown::Both);
+    }
+}
+
+impl ProxyStream for StreamOwned<ClientConnection, TcpStream> {
+    fn socket(&self) -> &TcpStream {
+        &self.sock
+    }
+
+    fn shutdown_write(&mut self) {
+        self.conn.send_close_notify();
+        let _ = self.conn.complete_io(&mut self.sock);
+        let _ = self.sock.shutdown(Shutdown::Write);
+    }
+
+    fn close(&mut self) {
+        self.conn.send_close_notify();
+        let _ = self.conn.complete_io(&mut self.sock);
+        let _ = self.sock.shutdown(Shutdown::Both);
+    }
+}
+
+/// 代理连接上的 HTTP 解析状态，两个方向的明文都送入 HTTP 解析器
+struct Recorder {
+    key: FlowKey,
+    dissector: HttpDissector,
+    requests: StreamBuffer,
+    responses: StreamBuffer,
+}
+
+impl Recorder {
+    fn new(key: FlowKey) -> Self {
+        Self {
+            key,
+            dissector: HttpDissector::new(),
+            requests: StreamBuffer::detached(),
+            responses: StreamBuffer::detached(),
+        }
+    }
+
+    /// 记录转发的数据，from_client 表示客户端发往上游的方向
+    fn record(&mut self, from_client: bool, data: &[u8]) {
+        let (stream, key) = if from_client {
+            self.requests.extend(data);
+            (&mut self.requests, self.key)
+        } else {
+            self.responses.extend(data);
+            (&mut self.responses, self.key.reverse())
+        };
+        for message in self.dissector.process_stream(key, stream, now_ms()) {
+            super::handle_http_message(message);
+        }
+    }
+
+    /// 连接结束，处理以连接关闭为结束标志的消息
+    fn finish(&mut self) {
+        self.requests.close();
+        self.responses.close();
+        self.record(true, &[]);
+        self.record(false, &[]);
+    }
+}
+
+/// 在客户端和上游之间双向转发数据，同时解析其中的 HTTP 消息
+/// single 不为空时只转发连接上的第一个请求（普通 HTTP 代理请求）
+fn relay(
+    mut client: Box<dyn ProxyStream>,
+    mut upstream: Box<dyn ProxyStream>,
+    key: FlowKey,
+    first: Vec<u8>,
+    mut single: Option<SingleRequest>,
+    context: &ProxyContext,
+) -> Result<()> {
+    client.socket().set_read_timeout(Some(POLL_INTERVAL))?;
+    upstream.socket().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let mut recorder = Recorder::new(key);
+    if !first.is_empty() {
+        upstream.write_all(&first)?;
+        upstream.flush()?;
+        recorder.record(true, &first);
+    }
+
+    let result = pump(
+        &mut *client,
+        &mut *upstream,
+        &mut recorder,
+        single.as_mut(),
+        context,
+    );
+    recorder.finish();
+    client.close();
+    upstream.close();
+    result
+}
+
+fn pump(
+    client: &mut dyn ProxyStream,
+    upstream: &mut dyn ProxyStream,
+    recorder: &mut Recorder,
+    mut single: Option<&mut SingleRequest>,
+    context: &ProxyContext,
+) -> Result<()> {
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    let mut last_activity = Instant::now();
+    let mut client_open = true;
+
+    while context.running.load(Ordering::Relaxed) {
+        let mut active = false;
+
+        if client_open {
+            match read_available(client, &mut buf)? {
+                // 客户端只关闭了写入方向时仍在等待响应，继续转发直到上游关闭连接
+                Some(0) => {
+                    client_open = false;
+                    upstream.shutdown_write();
+                    active = true;
+                }
+                Some(n) => {
+                    let len = match single.as_deref_mut() {
+                        Some(single) => single.request_bytes(&buf[..n]),
+                        None => n,
+                    };
+                    if len < n {
+                        debug!(
+                            "丢弃客户端在同一代理连接上发送的后续请求（{} 字节）",
+                            n - len
+                        );
+                    }
+                    if len > 0 {
+                        upstream.write_all(&buf[..len])?;
+                        upstream.flush()?;
+                        recorder.record(true, &buf[..len]);
+                    }
+                    active = true;
+                }
+                None => {}
+            }
+        }
+
+        match read_available(upstream, &mut buf)? {
+            Some(0) => {
+                if let Some(single) = single.as_deref_mut() {
+                    client.write_all(&single.finish_response())?;
+                    client.flush()?;
+                }
+                break;
+            }
+            Some(n) => {
+                match single.as_deref_mut() {
+                    Some(single) => client.write_all(&single.response_bytes(&buf[..n]))?,
+                    None => client.write_all(&buf[..n])?,
+                }
+                client.flush()?;
+                recorder.record(false, &buf[..n]);
+                active = true;
+            }
+            None => {}
+        }
+
+        if active {
+            last_activity = Instant::now();
+        } else if last_activity.elapsed() >= IDLE_TIMEOUT {
+            debug!("代理连接空闲超时");
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// 读取已到达的数据，轮询超时返回 None，连接关闭返回 Some(0)
+fn read_available(stream: &mut dyn ProxyStream, buf: &mut [u8]) -> Result<Option<usize>> {
+    match stream.read(buf) {
+        Ok(n) => Ok(Some(n)),
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            Ok(None)
+        }
+        // 对端未发送 close_notify 就关闭了 TLS 连接
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(Some(0)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 返回本地根证书，便于在浏览器中直接下载安装
+fn serve_ca_certificate(client: &mut TcpStream) -> Result<()> {
+    let pem = ca::authority()?.cert_pem();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: application/x-x509-ca-cert\r\n\
+         Content-Disposition: attachment; filename=\"big-data-rpa-ca.pem\"\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        pem.len(),
+        pem
+    );
+    client.write_all(response.as_bytes())?;
+    Ok(())
+}
+
+fn send_error(client: &mut TcpStream, status: &str, message: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        message.len(),
+        message
+    );
+    let _ = client.write_all(response.as_bytes());
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_request_stops_after_content_length() {
+        let mut single =
+            SingleRequest::new(b"POST http://a/x HTTP/1.1\r\nContent-Length: 5\r\n\r\n").unwrap();
+        assert_eq!(single.request_bytes(b"abc"), 3);
+        assert_eq!(single.request_bytes(b"deGET /next HTTP/1.1\r\n\r\n"), 2);
+        assert_eq!(single.request_bytes(b"more"), 0);
+    }
+
+    #[test]
+    fn single_request_follows_chunked_framing() {
+        let mut single =
+            SingleRequest::new(b"POST http://a/x HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n")
+                .unwrap();
+        assert_eq!(single.request_bytes(b"3\r\nab"), 5);
+        assert_eq!(single.request_bytes(b"c\r\n0"), 4);
+        assert_eq!(single.request_bytes(b"\r\n\r\nGET / HTTP/1.1\r\n"), 4);
+        assert_eq!(single.request_bytes(b"\r\n"), 0);
+    }
+
+    #[test]
+    fn request_without_body_ends_at_head() {
+        let mut single = SingleRequest::new(b"GET http://a/ HTTP/1.1\r\nHost: a\r\n\r\n").unwrap();
+        assert_eq!(single.request_bytes(b"GET /second HTTP/1.1\r\n\r\n"), 0);
+        assert!(
+            SingleRequest::new(b"POST http://a/ HTTP/1.1\r\nContent-Length: x\r\n\r\n").is_none()
+        );
+    }
+
+    #[test]
+    fn response_head_declares_connection_close() {
+        let mut single = SingleRequest::new(b"GET http://a/ HTTP/1.1\r\n\r\n").unwrap();
+        // 临时响应立即转发，最终响应头分多次到达，完整之前不转发
+        assert_eq!(
+            single.response_bytes(
+                b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nConnection: keep"
+            ),
+            b"HTTP/1.1 100 Continue\r\n\r\n"
+        );
+        let forwarded = single
+            .response_bytes(b"-alive\r\nKeep-Alive: timeout=5\r\nContent-Length: 2\r\n\r\nok");
+        assert_eq!(
+            forwarded,
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+        );
+        assert_eq!(single.response_bytes(b"rest"), b"rest");
+    }
+
+    #[test]
+    fn incomplete_response_head_flushed_on_close() {
+        let mut single = SingleRequest::new(b"GET http://a/ HTTP/1.1\r\n\r\n").unwrap();
+        assert!(single.response_bytes(b"HTTP/1.1 200").is_empty());
+        assert_eq!(single.finish_response(), b"HTTP/1.1 200");
+    }
+
+    #[test]
+    fn request_head_rewritten_for_origin_server() {
+        let rewritten = rewrite_request_head(
+            b"GET http://a/x?y HTTP/1.1\r\nHost: a\r\nProxy-Connection: keep-alive\r\nConnection: keep-alive\r\n\r\n",
+            "/x?y",
+        );
+        assert_eq!(
+            rewritten,
+            b"GET /x?y HTTP/1.1\r\nHost: a\r\nConnection: close\r\n\r\n"
+        );
+    }
+}
@@ -0,0 +1,488 @@
+use log::{debug, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 单个方向最多缓存的已排序字节数，超过后丢弃（通常是非 HTTP 数据或解析失败）
+const MAX_STREAM_BUFFER: usize = 4 * 1024 * 1024;
+/// 单个方向最多缓存的乱序字节数，超过后放弃等待缺失的数据段
+const MAX_PENDING_BYTES: usize = 1024 * 1024;
+/// 最多同时跟踪的 TCP 流数量
+const MAX_FLOWS: usize = 4096;
+/// 流空闲超时时间
+const FLOW_TIMEOUT: Duration = Duration::from_secs(120);
+/// 已关闭的流保留时间（等待迟到的重传）
+const CLOSED_FLOW_TIMEOUT: Duration = Duration::from_secs(5);
+/// 清理过期流的间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(10);
+
+// 字节流代数计数器，保证不同流、同一流丢弃数据前后的代数都不相同
+static STREAM_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn next_generation() -> u64 {
+    STREAM_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// TCP 流的单方向四元组
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowKey {
+    pub src_ip: IpAddr,
+    pub src_port: u16,
+    pub dst_ip: IpAddr,
+    pub dst_port: u16,
+}
+
+impl FlowKey {
+    /// 获取反方向的四元组
+    pub fn reverse(&self) -> Self {
+        Self {
+            src_ip: self.dst_ip,
+            src_port: self.dst_port,
+            dst_ip: self.src_ip,
+            dst_port: self.src_port,
+        }
+    }
+}
+
+/// 从数据包中提取的 TCP 段信息
+#[derive(Debug, Clone, Copy)]
+pub struct TcpSegment<'a> {
+    pub seq: u32,
+    pub syn: bool,
+    pub fin: bool,
+    pub rst: bool,
+    pub payload: &'a [u8],
+}
+
+/// 单方向的重组字节流
+#[derive(Debug)]
+pub struct StreamBuffer {
+    /// 流起始序列号（偏移量 0 对应的序列号）
+    base_seq: u32,
+    /// 下一个期望的字节偏移量
+    next_offset: u64,
+    /// 乱序到达、尚未连续的数据段（按偏移量排序）
+    pending: BTreeMap<u64, Vec<u8>>,
+    pending_bytes: usize,
+    /// 已按顺序拼接、尚未被消费的数据
+    data: Vec<u8>,
+    last_seen: Instant,
+    closed: bool,
+    /// 数据代数，未消费数据被丢弃时改变
+    generation: u64,
+}
+
+impl StreamBuffer {
+    fn new(segment: &TcpSegment) -> Self {
+        // SYN 本身占用一个序列号，数据从 seq + 1 开始
+        let base_seq = if segment.syn {
+            segment.seq.wrapping_add(1)
+        } else {
+            segment.seq
+        };
+
+        Self {
+            base_seq,
+            next_offset: 0,
+            pending: BTreeMap::new(),
+            pending_bytes: 0,
+            data: Vec::new(),
+            last_seen: Instant::now(),
+            closed: false,
+            generation: next_generation(),
+        }
+    }
+
+    /// 不对应 TCP 序列号的字节流，用于存放解密后的数据
+    pub fn detached() -> Self {
+        Self::new(&TcpSegment {
+            seq: 0,
+            syn: false,
+            fin: false,
+            rst: false,
+            payload: &[],
+        })
+    }
+
+    /// 直接追加数据，只用于 detached 字节流
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.last_seen = Instant::now();
+        self.append(bytes);
+    }
+
+    /// 标记字节流已结束
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    /// 已按顺序重组、尚未消费的数据
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 消费开头的 n 个字节
+    pub fn consume(&mut self, n: usize) {
+        let n = n.min(self.data.len());
+        self.data.drain(..n);
+    }
+
+    /// 丢弃所有未消费的数据
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.generation = next_generation();
+    }
+
+    /// 数据代数：相同代数下 data() 的开头始终对应同一字节，
+    /// 上层可据此判断缓存的解析进度是否仍然有效
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// 流是否已经结束（FIN/RST）
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// 将序列号换算为距离期望位置最近的偏移量，以处理序列号回绕
+    fn offset_of(&self, seq: u32) -> i64 {
+        let relative = seq.wrapping_sub(self.base_seq);
+        let delta = relative.wrapping_sub(self.next_offset as u32) as i32;
+        self.next_offset as i64 + delta as i64
+    }
+
+    /// 添加一个数据段，返回是否有新的连续数据或流刚刚结束
+    fn push(&mut self, segment: &TcpSegment) -> bool {
+        self.last_seen = Instant::now();
+
+        if segment.rst {
+            let newly_closed = !self.closed;
+            self.closed = true;
+            return newly_closed;
+        }
+
+        let mut appended = false;
+        if !segment.payload.is_empty() {
+            let seq = if segment.syn {
+                segment.seq.wrapping_add(1)
+            } else {
+                segment.seq
+            };
+            let offset = self.offset_of(seq);
+            appended = self.insert(offset, segment.payload);
+        }
+
+        // 流结束时需要通知调用方，以便处理以连接关闭为结束标志的消息
+        // （TLS 连接的原始数据可能已全部消费，解密后的明文仍在等待结束）
+        if segment.fin && !self.closed {
+            self.closed = true;
+            return true;
+        }
+
+        appended
+    }
+
+    fn insert(&mut self, offset: i64, payload: &[u8]) -> bool {
+        let end = offset + payload.len() as i64;
+
+        // 完全重复的重传（或开始跟踪之前的数据）
+        if end <= self.next_offset as i64 {
+            debug!("丢弃重传数据段: 偏移 {}, 长度 {}", offset, payload.len());
+            return false;
+        }
+
+        if offset > self.next_offset as i64 {
+            let offset = offset as u64;
+            // 乱序到达，先缓存；相同偏移保留较长的数据段
+            let replace = self
+                .pending
+                .get(&offset)
+                .is_none_or(|existing| existing.len() < payload.len());
+            if replace {
+                if let Some(old) = self.pending.insert(offset, payload.to_vec()) {
+                    self.pending_bytes -= old.len();
+                }
+                self.pending_bytes += payload.len();
+            }
+
+            if self.pending_bytes > MAX_PENDING_BYTES {
+                // 缺失的数据段迟迟不到，跳过空洞；已有数据的消息边界已不可信
+                warn!("TCP 流缺失数据过多，跳过空洞并丢弃 {} 字节未完成数据", self.data.len());
+                self.clear();
+                if let Some(&first) = self.pending.keys().next() {
+                    self.next_offset = first;
+                }
+                return self.drain_pending();
+            }
+            return false;
+        }
+
+        // 与已接收数据部分重叠时只追加新的部分
+        let skip = (self.next_offset as i64 - offset) as usize;
+        self.append(&payload[skip..]);
+        self.drain_pending();
+        true
+    }
+
+    /// 将已连续的乱序数据段移入缓冲区
+    fn drain_pending(&mut self) -> bool {
+        let mut appended = false;
+        while let Some(entry) = self.pending.first_entry() {
+            let offset = *entry.key();
+            if offset > self.next_offset {
+                break;
+            }
+            let payload = entry.remove();
+            self.pending_bytes -= payload.len();
+
+            let end = offset + payload.len() as u64;
+            if end > self.next_offset {
+                let skip = (self.next_offset - offset) as usize;
+                self.append(&payload[skip..]);
+                appended = true;
+            }
+        }
+        appended
+    }
+
+    fn append(&mut self, bytes: &[u8]) {
+        self.next_offset += bytes.len() as u64;
+        self.data.extend_from_slice(bytes);
+
+        if self.data.len() > MAX_STREAM_BUFFER {
+            warn!("TCP 流缓冲区超过 {} 字节，丢弃未解析数据", MAX_STREAM_BUFFER);
+            self.clear();
+        }
+    }
+}
+
+/// TCP 流重组器，按四元组维护每个方向的字节流
+#[derive(Debug)]
+pub struct TcpReassembler {
+    streams: HashMap<FlowKey, StreamBuffer>,
+    last_cleanup: Instant,
+}
+
+impl Default for TcpReassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TcpReassembler {
+    pub fn new() -> Self {
+        Self {
+            streams: HashMap::new(),
+            last_cleanup: Instant::now(),
+        }
+    }
+
+    /// 处理一个 TCP 段，有新的连续数据或流结束时返回该方向的字节流
+    pub fn process_segment(
+        &mut self,
+        key: FlowKey,
+        segment: &TcpSegment,
+    ) -> Option<&mut StreamBuffer> {
+        if self.last_cleanup.elapsed() >= CLEANUP_INTERVAL {
+            self.cleanup();
+        }
+
+        // 新的 SYN 表示端口被复用，丢弃旧的流状态
+        if segment.syn {
+            if let Some(stream) = self.streams.get(&key) {
+                if stream.base_seq != segment.seq.wrapping_add(1) {
+                    self.streams.remove(&key);
+                }
+            }
+        }
+
+        if !self.streams.contains_key(&key) {
+            // 没有数据的 FIN/RST/ACK 无需建立新流
+            if segment.payload.is_empty() && !segment.syn {
+                return None;
+            }
+            if self.streams.len() >= MAX_FLOWS {
+                self.evict_oldest();
+            }
+            debug!(
+                "开始跟踪 TCP 流: {}:{} -> {}:{}",
+                key.src_ip, key.src_port, key.dst_ip, key.dst_port
+            );
+            self.streams.insert(key, StreamBuffer::new(segment));
+        }
+
+        let stream = self.streams.get_mut(&key)?;
+        if stream.push(segment) {
+            Some(stream)
+        } else {
+            None
+        }
+    }
+
+    /// 当前跟踪的流数量（单方向计数）
+    pub fn flow_count(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// 清理空闲超时和已关闭的流
+    pub fn cleanup(&mut self) {
+        let before = self.streams.len();
+        self.streams.retain(|_, stream| {
+            let idle = stream.last_seen.elapsed();
+            if stream.closed {
+                idle < CLOSED_FLOW_TIMEOUT
+            } else {
+                idle < FLOW_TIMEOUT
+            }
+        });
+        self.last_cleanup = Instant::now();
+
+        let removed = before - self.streams.len();
+        if removed > 0 {
+            debug!("清理了 {} 个过期 TCP 流，剩余 {}", removed, self.streams.len());
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .streams
+            .iter()
+            .min_by_key(|(_, stream)| stream.last_seen)
+            .map(|(key, _)| *key);
+        if let Some(key) = oldest {
+            warn!("跟踪的 TCP 流数量达到上限 {}，淘汰最久未活动的流", MAX_FLOWS);
+            self.streams.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn key() -> FlowKey {
+        FlowKey {
+            src_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            src_port: 50000,
+            dst_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            dst_port: 80,
+        }
+    }
+
+    fn segment(seq: u32, payload: &[u8]) -> TcpSegment<'_> {
+        TcpSegment {
+            seq,
+            syn: false,
+            fin: false,
+            rst: false,
+            payload,
+        }
+    }
+
+    fn syn(seq: u32) -> TcpSegment<'static> {
+        TcpSegment {
+            syn: true,
+            ..segment(seq, b"")
+        }
+    }
+
+    fn data(reassembler: &mut TcpReassembler, seq: u32, payload: &[u8]) -> Option<Vec<u8>> {
+        reassembler
+            .process_segment(key(), &segment(seq, payload))
+            .map(|stream| stream.data().to_vec())
+    }
+
+    #[test]
+    fn out_of_order_segments() {
+        let mut reassembler = TcpReassembler::new();
+        assert!(reassembler.process_segment(key(), &syn(999)).is_none());
+        assert_eq!(data(&mut reassembler, 1006, b"world"), None);
+        assert_eq!(data(&mut reassembler, 1005, b" "), None);
+        assert_eq!(
+            data(&mut reassembler, 1000, b"hello"),
+            Some(b"hello world".to_vec())
+        );
+    }
+
+    #[test]
+    fn retransmitted_segments_ignored() {
+        let mut reassembler = TcpReassembler::new();
+        reassembler.process_segment(key(), &syn(999));
+        assert_eq!(data(&mut reassembler, 1000, b"abc"), Some(b"abc".to_vec()));
+        assert_eq!(data(&mut reassembler, 1000, b"abc"), None);
+
+        // 乱序缓存中的重传保留较长的数据段
+        assert_eq!(data(&mut reassembler, 1005, b"f"), None);
+        assert_eq!(data(&mut reassembler, 1005, b"fgh"), None);
+        assert_eq!(
+            data(&mut reassembler, 1003, b"de"),
+            Some(b"abcdefgh".to_vec())
+        );
+    }
+
+    #[test]
+    fn overlapping_segments_append_new_bytes() {
+        let mut reassembler = TcpReassembler::new();
+        reassembler.process_segment(key(), &syn(999));
+        data(&mut reassembler, 1000, b"abcd");
+        assert_eq!(
+            data(&mut reassembler, 1002, b"cdef"),
+            Some(b"abcdef".to_vec())
+        );
+
+        // 缓存的数据段与新到达的数据重叠
+        assert_eq!(data(&mut reassembler, 1008, b"ij"), None);
+        assert_eq!(
+            data(&mut reassembler, 1005, b"fghi"),
+            Some(b"abcdefghij".to_vec())
+        );
+    }
+
+    #[test]
+    fn sequence_number_wraparound() {
+        let mut reassembler = TcpReassembler::new();
+        reassembler.process_segment(key(), &syn(u32::MAX - 2));
+        assert_eq!(data(&mut reassembler, 0, b"cd"), None);
+        assert_eq!(
+            data(&mut reassembler, u32::MAX - 1, b"ab"),
+            Some(b"abcd".to_vec())
+        );
+    }
+
+    #[test]
+    fn consumed_data_and_fin() {
+        let mut reassembler = TcpReassembler::new();
+        reassembler.process_segment(key(), &syn(0));
+        let stream = reassembler
+            .process_segment(key(), &segment(1, b"abc"))
+            .unwrap();
+        stream.consume(2);
+        assert_eq!(stream.data(), b"c");
+
+        let fin = TcpSegment {
+            fin: true,
+            ..segment(4, b"")
+        };
+        let stream = reassembler.process_segment(key(), &fin).unwrap();
+        assert!(stream.is_closed());
+        assert_eq!(stream.data(), b"c");
+    }
+
+    #[test]
+    fn new_syn_resets_reused_port() {
+        let mut reassembler = TcpReassembler::new();
+        reassembler.process_segment(key(), &syn(0));
+        let generation = reassembler
+            .process_segment(key(), &segment(1, b"old"))
+            .unwrap()
+            .generation();
+
+        reassembler.process_segment(key(), &syn(5000));
+        let stream = reassembler
+            .process_segment(key(), &segment(5001, b"new"))
+            .unwrap();
+        assert_eq!(stream.data(), b"new");
+        assert_ne!(stream.generation(), generation);
+    }
+}
@@ -0,0 +1,97 @@
+use super::filter::CaptureFilterConfig;
+use super::proxy::ProxyConfig;
+use super::APP_HANDLE;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Manager;
+
+/// 设置文件名（位于应用配置目录）
+const SETTINGS_FILE: &str = "capture_settings.json";
+
+// 当前抓包设置（首次访问时从文件加载）
+static SETTINGS: OnceCell<Mutex<CaptureSettings>> = OnceCell::new();
+
+/// 抓包设置，在应用重启后保留
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureSettings {
+    /// 上次选择的网卡名称列表，"auto" 表示自动选择
+    pub devices: Vec<String>,
+    /// 捕获过滤器配置
+    pub filter: CaptureFilterConfig,
+    /// TLS 密钥日志文件（SSLKEYLOGFILE 格式），用于解密 HTTPS 流量
+    pub key_log_file: Option<String>,
+    /// 本地代理配置
+    pub proxy: ProxyConfig,
+}
+
+/// 应用配置目录
+pub(super) fn config_dir() -> Option<PathBuf> {
+    let app_handle = APP_HANDLE.get()?;
+    match app_handle.path().app_config_dir() {
+        Ok(dir) => Some(dir),
+        Err(e) => {
+            warn!("获取应用配置目录失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 设置文件路径
+fn settings_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(SETTINGS_FILE))
+}
+
+/// 从文件读取设置，文件不存在或损坏时使用默认值
+fn load_from_file() -> CaptureSettings {
+    let path = match settings_path() {
+        Some(path) => path,
+        None => return CaptureSettings::default(),
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(settings) => {
+                info!("已加载抓包设置: {}", path.display());
+                settings
+            }
+            Err(e) => {
+                warn!("抓包设置文件格式错误，使用默认设置: {}", e);
+                CaptureSettings::default()
+            }
+        },
+        Err(_) => CaptureSettings::default(),
+    }
+}
+
+fn settings() -> &'static Mutex<CaptureSettings> {
+    SETTINGS.get_or_init(|| Mutex::new(load_from_file()))
+}
+
+/// 获取当前抓包设置
+pub fn get_settings() -> CaptureSettings {
+    settings().lock().unwrap().clone()
+}
+
+/// 修改并保存抓包设置
+pub fn update_settings<F>(update: F) -> Result<CaptureSettings>
+where
+    F: FnOnce(&mut CaptureSettings),
+{
+    let mut guard = settings().lock().unwrap();
+    update(&mut guard);
+
+    let path = settings_path().ok_or_else(|| anyhow!("无法确定抓包设置文件路径"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| anyhow!("创建配置目录失败: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(&*guard)?;
+    std::fs::write(&path, content).map_err(|e| anyhow!("保存抓包设置失败: {}", e))?;
+
+    info!("已保存抓包设置: {}", path.display());
+    Ok(guard.clone())
+}
@@ -0,0 +1,567 @@
+use super::TlsConnection;
+use once_cell::sync::OnceCell;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// TLS 记录层的内容类型
+pub(super) const CONTENT_TYPE_CHANGE_CIPHER_SPEC: u8 = 20;
+pub(super) const CONTENT_TYPE_ALERT: u8 = 21;
+pub(super) const CONTENT_TYPE_HANDSHAKE: u8 = 22;
+pub(super) const CONTENT_TYPE_APPLICATION_DATA: u8 = 23;
+/// 握手消息类型
+pub(super) const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+pub(super) const HANDSHAKE_SERVER_HELLO: u8 = 2;
+pub(super) const HANDSHAKE_FINISHED: u8 = 20;
+pub(super) const HANDSHAKE_KEY_UPDATE: u8 = 24;
+/// 单个 TLS 记录的最大长度（RFC 8446 第 5.1 节）
+pub(super) const MAX_RECORD_LEN: usize = (1 << 14) + 2048;
+/// ClientHello 的最大长度，超过时不再等待剩余数据
+const MAX_CLIENT_HELLO_LEN: usize = 64 * 1024;
+/// 最多记录多少个服务端的 SNI
+const MAX_KNOWN_ENDPOINTS: usize = 1024;
+
+const EXTENSION_SERVER_NAME: u16 = 0;
+const EXTENSION_ALPN: u16 = 16;
+const EXTENSION_SUPPORTED_VERSIONS: u16 = 43;
+
+/// 出现过 TLS 握手的服务端：(地址, 端口, SNI)
+type Endpoint = (String, u16, Option<String>);
+
+// 最近出现过 TLS 握手的服务端
+static KNOWN_ENDPOINTS: OnceCell<Mutex<VecDeque<Endpoint>>> = OnceCell::new();
+
+/// ClientHello 中的连接信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHello {
+    /// 服务器名称指示（SNI）
+    pub sni: Option<String>,
+    /// 应用层协议协商（ALPN）中客户端支持的协议，例如 h2、http/1.1
+    pub alpn: Vec<String>,
+    /// 客户端支持的最高 TLS 版本，例如 "TLS 1.3"
+    pub version: String,
+    /// 客户端随机数，密钥日志以它标识会话
+    pub random: [u8; 32],
+}
+
+/// ServerHello 中与解密相关的字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerHello {
+    pub random: [u8; 32],
+    pub cipher_suite: u16,
+    /// 协商的版本，TLS 1.3 取自 supported_versions 扩展
+    pub version: u16,
+}
+
+/// 解析 ClientHello 的结果
+#[derive(Debug)]
+pub enum ClientHelloParse {
+    /// 解析成功，以及 ClientHello 所在记录的总长度
+    Complete(ClientHello, usize),
+    /// 数据尚未收全
+    Incomplete,
+    /// 不是 ClientHello
+    Invalid,
+}
+
+/// 数据开头是否可能是 TLS 握手记录（数据不足时按前缀判断）
+pub fn is_handshake_prefix(data: &[u8]) -> bool {
+    match data {
+        [] => false,
+        [content_type] => *content_type == CONTENT_TYPE_HANDSHAKE,
+        [content_type, major, ..] => *content_type == CONTENT_TYPE_HANDSHAKE && *major == 3,
+    }
+}
+
+/// 从客户端发出的字节流开头解析 ClientHello，ClientHello 可以跨多个 TLS 记录
+pub fn parse_client_hello(data: &[u8]) -> ClientHelloParse {
+    let mut handshake = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let header = match data.get(pos..pos + 5) {
+            Some(header) => header,
+            None => return ClientHelloParse::Incomplete,
+        };
+        if header[0] != CONTENT_TYPE_HANDSHAKE || header[1] != 3 {
+            return ClientHelloParse::Invalid;
+        }
+        let record_len = u16::from_be_bytes([header[3], header[4]]) as usize;
+        if record_len == 0 || record_len > MAX_RECORD_LEN {
+            return ClientHelloParse::Invalid;
+        }
+        let record = match data.get(pos + 5..pos + 5 + record_len) {
+            Some(record) => record,
+            None => return ClientHelloParse::Incomplete,
+        };
+        handshake.extend_from_slice(record);
+        pos += 5 + record_len;
+
+        // 握手消息头：类型（1 字节）和长度（3 字节）
+        if handshake[0] != HANDSHAKE_CLIENT_HELLO {
+            return ClientHelloParse::Invalid;
+        }
+        if handshake.len() < 4 {
+            continue;
+        }
+        let message_len =
+            u32::from_be_bytes([0, handshake[1], handshake[2], handshake[3]]) as usize;
+        if message_len > MAX_CLIENT_HELLO_LEN {
+            return ClientHelloParse::Invalid;
+        }
+        if handshake.len() >= 4 + message_len {
+            return match parse_client_hello_body(&handshake[4..4 + message_len]) {
+                Some(hello) => ClientHelloParse::Complete(hello, pos),
+                None => ClientHelloParse::Invalid,
+            };
+        }
+    }
+}
+
+/// 解析 ClientHello 消息体（RFC 8446 第 4.1.2 节）
+fn parse_client_hello_body(body: &[u8]) -> Option<ClientHello> {
+    let mut reader = Reader::new(body);
+    let legacy_version = reader.u16()?;
+    let random = reader.bytes(32)?.try_into().ok()?;
+    let session_id_len = reader.u8()? as usize;
+    reader.skip(session_id_len)?;
+    let cipher_suites_len = reader.u16()? as usize;
+    reader.skip(cipher_suites_len)?;
+    let compression_len = reader.u8()? as usize;
+    reader.skip(compression_len)?;
+
+    let mut hello = ClientHello {
+        sni: None,
+        alpn: Vec::new(),
+        version: version_name(legacy_version),
+        random,
+    };
+
+    // 没有扩展的 ClientHello（SSL 3.0 或很旧的客户端）
+    if reader.is_empty() {
+        return Some(hello);
+    }
+    let extensions_len = reader.u16()? as usize;
+    let mut extensions = Reader::new(reader.bytes(extensions_len)?);
+    while !extensions.is_empty() {
+        let extension_type = extensions.u16()?;
+        let extension_len = extensions.u16()? as usize;
+        let mut extension = Reader::new(extensions.bytes(extension_len)?);
+        match extension_type {
+            EXTENSION_SERVER_NAME => hello.sni = parse_server_name(&mut extension),
+            EXTENSION_ALPN => hello.alpn = parse_alpn(&mut extension).unwrap_or_default(),
+            EXTENSION_SUPPORTED_VERSIONS => {
+                if let Some(version) = parse_supported_versions(&mut extension) {
+                    hello.version = version_name(version);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(hello)
+}
+
+/// 解析 ServerHello 消息体（不含 4 字节的握手消息头）
+pub fn parse_server_hello(body: &[u8]) -> Option<ServerHello> {
+    let mut reader = Reader::new(body);
+    let mut version = reader.u16()?;
+    let random = reader.bytes(32)?.try_into().ok()?;
+    let session_id_len = reader.u8()? as usize;
+    reader.skip(session_id_len)?;
+    let cipher_suite = reader.u16()?;
+    reader.skip(1)?; // compression_method
+
+    if !reader.is_empty() {
+        let extensions_len = reader.u16()? as usize;
+        let mut extensions = Reader::new(reader.bytes(extensions_len)?);
+        while !extensions.is_empty() {
+            let extension_type = extensions.u16()?;
+            let extension_len = extensions.u16()? as usize;
+            let mut extension = Reader::new(extensions.bytes(extension_len)?);
+            if extension_type == EXTENSION_SUPPORTED_VERSIONS {
+                version = extension.u16()?;
+            }
+        }
+    }
+
+    Some(ServerHello {
+        random,
+        cipher_suite,
+        version,
+    })
+}
+
+fn parse_server_name(reader: &mut Reader) -> Option<String> {
+    let list_len = reader.u16()? as usize;
+    let mut list = Reader::new(reader.bytes(list_len)?);
+    while !list.is_empty() {
+        let name_type = list.u8()?;
+        let name_len = list.u16()? as usize;
+        let name = list.bytes(name_len)?;
+        // 只有 host_name（0）一种类型
+        if name_type == 0 {
+            return std::str::from_utf8(name)
+                .ok()
+                .map(|name| name.trim_end_matches('.').to_ascii_lowercase());
+        }
+    }
+    None
+}
+
+fn parse_alpn(reader: &mut Reader) -> Option<Vec<String>> {
+    let list_len = reader.u16()? as usize;
+    let mut list = Reader::new(reader.bytes(list_len)?);
+    let mut protocols = Vec::new();
+    while !list.is_empty() {
+        let protocol_len = list.u8()? as usize;
+        let protocol = list.bytes(protocol_len)?;
+        protocols.push(String::from_utf8_lossy(protocol).into_owned());
+    }
+    Some(protocols)
+}
+
+/// 客户端支持的最高版本，忽略 GREASE 值
+fn parse_supported_versions(reader: &mut Reader) -> Option<u16> {
+    let list_len = reader.u8()? as usize;
+    let mut list = Reader::new(reader.bytes(list_len)?);
+    let mut highest = None;
+    while !list.is_empty() {
+        let version = list.u16()?;
+        if !is_grease(version) && highest.is_none_or(|highest| version > highest) {
+            highest = Some(version);
+        }
+    }
+    highest
+}
+
+/// GREASE 保留值（RFC 8701），形如 0x?A?A
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+fn version_name(version: u16) -> String {
+    match version {
+        0x0300 => "SSL 3.0".to_string(),
+        0x0301 => "TLS 1.0".to_string(),
+        0x0302 => "TLS 1.1".to_string(),
+        0x0303 => "TLS 1.2".to_string(),
+        0x0304 => "TLS 1.3".to_string(),
+        _ => format!("0x{:04x}", version),
+    }
+}
+
+/// 记录使用 TLS 的服务端及其 SNI
+pub fn remember_endpoint(connection: &TlsConnection) {
+    let endpoints = KNOWN_ENDPOINTS.get_or_init(|| Mutex::new(VecDeque::new()));
+    let mut endpoints = endpoints.lock().unwrap();
+    endpoints.retain(|(ip, port, _)| !(*ip == connection.dst_ip && *port == connection.dst_port));
+    if endpoints.len() >= MAX_KNOWN_ENDPOINTS {
+        endpoints.pop_front();
+    }
+    endpoints.push_back((
+        connection.dst_ip.clone(),
+        connection.dst_port,
+        connection.sni.clone(),
+    ));
+}
+
+/// 服务端是否出现过 TLS 握手
+pub fn is_tls_endpoint(ip: &str, port: u16) -> bool {
+    find_endpoint(ip, port).is_some()
+}
+
+/// 客户端连接服务端时最近一次使用的 SNI
+pub fn server_name(ip: &str, port: u16) -> Option<String> {
+    find_endpoint(ip, port).and_then(|(_, _, sni)| sni)
+}
+
+fn find_endpoint(ip: &str, port: u16) -> Option<Endpoint> {
+    KNOWN_ENDPOINTS.get().and_then(|endpoints| {
+        endpoints
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(known_ip, known_port, _)| known_ip == ip && *known_port == port)
+            .cloned()
+    })
+}
+
+/// 按大端序读取握手消息字段
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 本机 OpenSSL 3.5 的 s_client 与 s_server 握手时抓取的记录
+    // （-servername Example.COM -alpn h2,http/1.1 -groups X25519）
+
+    /// TLS 1.3 ClientHello
+    const CLIENT_HELLO_TLS13: &str =
+        "16030100f6010000f20303b095e1f56ed0d6ce9f3d9fe5e0d2ab37164b84d8bb\
+         d273a73829ec5c720b459b20db19cc11963b9025347e777f628e37f4f79a2d10\
+         65c93866330880bbad3e91fe0006130213031301010000a300000010000e0000\
+         0b4578616d706c652e434f4d000b000403000102000a00040002001d0010000e\
+         000c02683208687474702f312e310016000000170000000d002a002809050906\
+         090404030503060308070808081a081b081c0809080a080b0804080508060401\
+         05010601002b0003020304002d00020101003300260024001d00202a92a89d59\
+         766323678ac4d76653dd0c8ac84e141a4ce9102744bb6772ec8355";
+
+    /// TLS 1.2 ClientHello（-tls1_2，没有 supported_versions 扩展）
+    const CLIENT_HELLO_TLS12: &str =
+        "16030100d4010000d003039cc8ee341c2ed88bdc488999c2e1191b440eda016e\
+         b9896150a839b9759ae37b000036c02cc030009fcca9cca8ccaac02bc02f009e\
+         c024c028006bc023c0270067c00ac0140039c009c0130033009d009c003d003c\
+         0035002f01000071ff0100010000000010000e00000b4578616d706c652e434f\
+         4d000b000403000102000a00040002001d0010000e000c02683208687474702f\
+         312e310016000000170000000d002a0028040305030603080708080809080a08\
+         0b080408050806040105010601030303010302040205020602";
+
+    /// TLS 1.3 ServerHello，TLS_AES_256_GCM_SHA384
+    const SERVER_HELLO_TLS13: &str =
+        "160303007a02000076030371d0c6ed237fe1d0fe4ac7dc908419b4cfd7b026c3\
+         372c8d4cd344a7cafc11e420db19cc11963b9025347e777f628e37f4f79a2d10\
+         65c93866330880bbad3e91fe130200002e002b0002030400330024001d0020c0\
+         02a0a8854a178ff30fee5910f2dde751840daf13e21d4d2a2785251d68ee1f";
+
+    /// TLS 1.2 ServerHello，ECDHE-RSA-AES256-GCM-SHA384
+    const SERVER_HELLO_TLS12: &str =
+        "160303006602000062030394fccaad8155b8ed80884509336f79f738ce6b93a8\
+         76d8b5444f574e47524401201a3d5f089c0ed926d5bfc03993ac26550b55dfb4\
+         00dbfc4d08031587e4d3e049c03000001aff01000100000b0004030001020010\
+         0005000302683200170000";
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// 把握手消息拆成多个记录，每个记录最多 size 字节
+    fn records(handshake: &[u8], size: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        for chunk in handshake.chunks(size) {
+            data.extend_from_slice(&[CONTENT_TYPE_HANDSHAKE, 3, 1]);
+            data.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+            data.extend_from_slice(chunk);
+        }
+        data
+    }
+
+    /// 构造只包含给定扩展的 ClientHello 记录
+    fn client_hello_with(extensions: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(&[0x11; 32]);
+        body.push(0); // session_id
+        body.extend_from_slice(&[0x00, 0x02, 0x13, 0x01]);
+        body.extend_from_slice(&[0x01, 0x00]);
+        let mut list = Vec::new();
+        for (extension_type, data) in extensions {
+            list.extend_from_slice(&extension_type.to_be_bytes());
+            list.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            list.extend_from_slice(data);
+        }
+        body.extend_from_slice(&(list.len() as u16).to_be_bytes());
+        body.extend_from_slice(&list);
+
+        let mut handshake = vec![HANDSHAKE_CLIENT_HELLO];
+        handshake.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        handshake.extend_from_slice(&body);
+        records(&handshake, MAX_RECORD_LEN)
+    }
+
+    fn complete(data: &[u8]) -> (ClientHello, usize) {
+        match parse_client_hello(data) {
+            ClientHelloParse::Complete(hello, len) => (hello, len),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn real_client_hello() {
+        let data = hex(CLIENT_HELLO_TLS13);
+        let (hello, len) = complete(&data);
+        assert_eq!(len, data.len());
+        assert_eq!(hello.sni.as_deref(), Some("example.com"));
+        assert_eq!(hello.alpn, ["h2", "http/1.1"]);
+        assert_eq!(hello.version, "TLS 1.3");
+        assert_eq!(hello.random[..], data[11..43]);
+
+        let (hello, _) = complete(&hex(CLIENT_HELLO_TLS12));
+        assert_eq!(hello.sni.as_deref(), Some("example.com"));
+        assert_eq!(hello.alpn, ["h2", "http/1.1"]);
+        assert_eq!(hello.version, "TLS 1.2");
+    }
+
+    #[test]
+    fn real_server_hello() {
+        // 跳过 5 字节的记录头和 4 字节的握手消息头
+        let data = hex(SERVER_HELLO_TLS13);
+        let hello = parse_server_hello(&data[9..]).unwrap();
+        assert_eq!(hello.version, 0x0304);
+        assert_eq!(hello.cipher_suite, 0x1302);
+        assert_eq!(hello.random[..], data[11..43]);
+
+        let data = hex(SERVER_HELLO_TLS12);
+        let hello = parse_server_hello(&data[9..]).unwrap();
+        assert_eq!(hello.version, 0x0303);
+        assert_eq!(hello.cipher_suite, 0xc030);
+    }
+
+    #[test]
+    fn supported_versions_ignore_grease() {
+        let versions: &[u8] = &[8, 0x1a, 0x1a, 0x03, 0x04, 0x03, 0x03, 0xfa, 0xfa];
+        let data = client_hello_with(&[(0x0a0a, &[]), (EXTENSION_SUPPORTED_VERSIONS, versions)]);
+        assert_eq!(complete(&data).0.version, "TLS 1.3");
+
+        // 只有 GREASE 值时使用 legacy_version
+        let data = client_hello_with(&[(EXTENSION_SUPPORTED_VERSIONS, &[2, 0x7a, 0x7a])]);
+        assert_eq!(complete(&data).0.version, "TLS 1.2");
+
+        assert!(is_grease(0x0a0a) && is_grease(0xfafa));
+        assert!(!is_grease(0x0a1a) && !is_grease(0x0304));
+    }
+
+    #[test]
+    fn client_hello_split_across_records() {
+        let data = hex(CLIENT_HELLO_TLS13);
+        let (expected, _) = complete(&data);
+        // 第一个记录连握手消息头都不完整的情况也要支持
+        for size in [1, 3, 100, 245] {
+            let mut split = records(&data[5..], size);
+            let len = split.len();
+            split.extend_from_slice(&[CONTENT_TYPE_APPLICATION_DATA, 3, 3, 0, 1, 0]);
+            assert_eq!(
+                complete(&split),
+                (expected.clone(), len),
+                "record size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn client_hello_split_across_segments() {
+        let data = records(&hex(CLIENT_HELLO_TLS13)[5..], 100);
+        for end in 0..data.len() {
+            assert!(
+                matches!(
+                    parse_client_hello(&data[..end]),
+                    ClientHelloParse::Incomplete
+                ),
+                "{} bytes",
+                end
+            );
+        }
+        complete(&data);
+    }
+
+    #[test]
+    fn invalid_record_and_message_lengths() {
+        let mut data = hex(CLIENT_HELLO_TLS13);
+        data[3..5].copy_from_slice(&[0, 0]);
+        assert!(matches!(
+            parse_client_hello(&data),
+            ClientHelloParse::Invalid
+        ));
+
+        let mut data = hex(CLIENT_HELLO_TLS13);
+        data[6..9].copy_from_slice(&[0x01, 0x00, 0x01]);
+        assert!(matches!(
+            parse_client_hello(&data),
+            ClientHelloParse::Invalid
+        ));
+
+        assert!(matches!(
+            parse_client_hello(&[CONTENT_TYPE_APPLICATION_DATA, 3, 3, 0, 1, 0]),
+            ClientHelloParse::Invalid
+        ));
+    }
+
+    #[test]
+    fn over_long_extension_lengths() {
+        // 扩展长度超过扩展列表
+        let mut data = client_hello_with(&[(EXTENSION_ALPN, &[0, 3, 2, b'h', b'2'])]);
+        let len = data.len();
+        data[len - 6] = 0xff;
+        assert!(matches!(
+            parse_client_hello(&data),
+            ClientHelloParse::Invalid
+        ));
+
+        // 扩展内部的长度字段超出扩展本身时忽略该扩展
+        let sni: &[u8] = &[0, 8, 0, 0, 0xff, b'a', b'.', b'c', b'o', b'm'];
+        let alpn: &[u8] = &[0, 9, 2, b'h', b'2'];
+        let (hello, _) = complete(&client_hello_with(&[
+            (EXTENSION_SERVER_NAME, sni),
+            (EXTENSION_ALPN, alpn),
+        ]));
+        assert_eq!(hello.sni, None);
+        assert!(hello.alpn.is_empty());
+    }
+
+    #[test]
+    fn truncated_or_corrupted_hello_does_not_panic() {
+        let client = hex(CLIENT_HELLO_TLS13);
+        let body = &client[9..];
+        for end in 0..body.len() {
+            parse_client_hello_body(&body[..end]);
+        }
+        // 截断在扩展列表中间时长度字段不符
+        for end in body.len() - 40..body.len() {
+            assert_eq!(parse_client_hello_body(&body[..end]), None);
+        }
+
+        let server = hex(SERVER_HELLO_TLS13);
+        for end in 0..server.len() - 9 {
+            parse_server_hello(&server[9..9 + end]);
+        }
+        assert_eq!(parse_server_hello(&server[9..server.len() - 1]), None);
+
+        for data in [client, server] {
+            for i in 0..data.len() {
+                for value in [0x00, 0xff] {
+                    let mut corrupted = data.clone();
+                    corrupted[i] = value;
+                    parse_client_hello(&corrupted);
+                    parse_server_hello(&corrupted[9..]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,842 @@
+use super::keylog::{self, SessionSecrets};
+use super::reassembly::{FlowKey, StreamBuffer};
+use super::tls::{self, ClientHello};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use log::{debug, info, warn};
+use sha2::{Sha256, Sha384};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// TLS 会话空闲超时时间
+const SESSION_TIMEOUT: Duration = Duration::from_secs(120);
+/// 密钥日志中还没有会话密钥时，保留加密记录等待浏览器写入的最长时间
+const SECRETS_WAIT: Duration = Duration::from_secs(2);
+/// AEAD 认证标签长度
+const TAG_LEN: usize = 16;
+/// HelloRetryRequest 使用的固定随机数（RFC 8446 第 4.1.3 节）
+const HELLO_RETRY_REQUEST_RANDOM: [u8; 32] = [
+    0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8, 0x91,
+    0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8, 0x33, 0x9c,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HashAlgorithm {
+    Sha256,
+    Sha384,
+}
+
+impl HashAlgorithm {
+    fn output_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AeadAlgorithm {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl AeadAlgorithm {
+    fn key_len(self) -> usize {
+        match self {
+            AeadAlgorithm::Aes128Gcm => 16,
+            AeadAlgorithm::Aes256Gcm | AeadAlgorithm::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    /// TLS 1.2 密钥块中 IV 的长度：GCM 只有 4 字节的隐式部分（RFC 5288），ChaCha20 为完整的 12 字节（RFC 7905）
+    fn tls12_iv_len(self) -> usize {
+        match self {
+            AeadAlgorithm::Aes128Gcm | AeadAlgorithm::Aes256Gcm => 4,
+            AeadAlgorithm::ChaCha20Poly1305 => 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CipherSuite {
+    aead: AeadAlgorithm,
+    hash: HashAlgorithm,
+}
+
+/// 支持解密的加密套件（AEAD），CBC 套件已基本不再使用
+fn cipher_suite(id: u16) -> Option<CipherSuite> {
+    let (aead, hash) = match id {
+        // TLS_AES_128_GCM_SHA256 以及 TLS 1.2 的 *_AES_128_GCM_SHA256
+        0x1301 | 0x009c | 0x009e | 0xc02b | 0xc02f => {
+            (AeadAlgorithm::Aes128Gcm, HashAlgorithm::Sha256)
+        }
+        // TLS_AES_256_GCM_SHA384 以及 TLS 1.2 的 *_AES_256_GCM_SHA384
+        0x1302 | 0x009d | 0x009f | 0xc02c | 0xc030 => {
+            (AeadAlgorithm::Aes256Gcm, HashAlgorithm::Sha384)
+        }
+        // TLS_CHACHA20_POLY1305_SHA256 以及 TLS 1.2 的 *_CHACHA20_POLY1305_SHA256
+        0x1303 | 0xcca8 | 0xcca9 | 0xccaa => {
+            (AeadAlgorithm::ChaCha20Poly1305, HashAlgorithm::Sha256)
+        }
+        _ => return None,
+    };
+    Some(CipherSuite { aead, hash })
+}
+
+enum Cipher {
+    Aes128Gcm(Box<Aes128Gcm>),
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(Box<ChaCha20Poly1305>),
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Cipher::Aes128Gcm(_) => "AES-128-GCM",
+            Cipher::Aes256Gcm(_) => "AES-256-GCM",
+            Cipher::ChaCha20Poly1305(_) => "ChaCha20-Poly1305",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Cipher {
+    fn new(aead: AeadAlgorithm, key: &[u8]) -> Option<Self> {
+        match aead {
+            AeadAlgorithm::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .ok()
+                .map(|cipher| Cipher::Aes128Gcm(Box::new(cipher))),
+            AeadAlgorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .ok()
+                .map(|cipher| Cipher::Aes256Gcm(Box::new(cipher))),
+            AeadAlgorithm::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key)
+                .ok()
+                .map(|cipher| Cipher::ChaCha20Poly1305(Box::new(cipher))),
+        }
+    }
+
+    fn decrypt(&self, nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+        let payload = Payload {
+            msg: ciphertext,
+            aad,
+        };
+        match self {
+            Cipher::Aes128Gcm(cipher) => cipher.decrypt(nonce.into(), payload).ok(),
+            Cipher::Aes256Gcm(cipher) => cipher.decrypt(nonce.into(), payload).ok(),
+            Cipher::ChaCha20Poly1305(cipher) => cipher.decrypt(nonce.into(), payload).ok(),
+        }
+    }
+}
+
+/// 单方向的记录解密器
+#[derive(Debug)]
+struct RecordDecrypter {
+    suite: CipherSuite,
+    cipher: Cipher,
+    iv: Vec<u8>,
+    /// 记录序号，每成功解密一条记录加一
+    seq: u64,
+    /// TLS 1.3 当前的流量密钥，用于处理 KeyUpdate；TLS 1.2 为 None
+    traffic_secret: Option<Vec<u8>>,
+}
+
+impl RecordDecrypter {
+    fn tls12(suite: CipherSuite, key: &[u8], iv: &[u8]) -> Option<Self> {
+        Some(Self {
+            suite,
+            cipher: Cipher::new(suite.aead, key)?,
+            iv: iv.to_vec(),
+            seq: 0,
+            traffic_secret: None,
+        })
+    }
+
+    /// 由 TLS 1.3 流量密钥派生记录密钥（RFC 8446 第 7.3 节）
+    fn tls13(suite: CipherSuite, traffic_secret: &[u8]) -> Option<Self> {
+        let key = hkdf_expand_label(suite.hash, traffic_secret, "key", suite.aead.key_len())?;
+        let iv = hkdf_expand_label(suite.hash, traffic_secret, "iv", 12)?;
+        Some(Self {
+            suite,
+            cipher: Cipher::new(suite.aead, &key)?,
+            iv,
+            seq: 0,
+            traffic_secret: Some(traffic_secret.to_vec()),
+        })
+    }
+
+    /// 解密一条记录，成功时序号加一
+    fn decrypt(&mut self, header: &[u8], payload: &[u8]) -> Option<Vec<u8>> {
+        let plaintext = if self.traffic_secret.is_some() {
+            // TLS 1.3：记录头作为附加数据
+            self.cipher
+                .decrypt(&self.nonce(&self.iv), header, payload)?
+        } else {
+            let (nonce, ciphertext) = match self.suite.aead {
+                AeadAlgorithm::ChaCha20Poly1305 => (self.nonce(&self.iv), payload),
+                // GCM 的显式 nonce 位于记录开头
+                _ => {
+                    if payload.len() < 8 {
+                        return None;
+                    }
+                    let mut nonce = [0u8; 12];
+                    nonce[..4].copy_from_slice(&self.iv);
+                    nonce[4..].copy_from_slice(&payload[..8]);
+                    (nonce, &payload[8..])
+                }
+            };
+            let plaintext_len = ciphertext.len().checked_sub(TAG_LEN)? as u16;
+            let mut aad = self.seq.to_be_bytes().to_vec();
+            aad.extend_from_slice(&header[..3]);
+            aad.extend_from_slice(&plaintext_len.to_be_bytes());
+            self.cipher.decrypt(&nonce, &aad, ciphertext)?
+        };
+        self.seq += 1;
+        Some(plaintext)
+    }
+
+    /// IV 与记录序号异或得到 nonce
+    fn nonce(&self, iv: &[u8]) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce.copy_from_slice(iv);
+        for (byte, seq) in nonce[4..].iter_mut().zip(self.seq.to_be_bytes()) {
+            *byte ^= seq;
+        }
+        nonce
+    }
+
+    /// 处理 TLS 1.3 的 KeyUpdate，派生下一代流量密钥
+    fn update_key(&mut self) -> Option<()> {
+        let secret = self.traffic_secret.as_ref()?;
+        let next = hkdf_expand_label(
+            self.suite.hash,
+            secret,
+            "traffic upd",
+            self.suite.hash.output_len(),
+        )?;
+        *self = Self::tls13(self.suite, &next)?;
+        Some(())
+    }
+}
+
+/// 单方向的解密状态
+#[derive(Debug, Default)]
+struct DirectionState {
+    decrypter: Option<RecordDecrypter>,
+    /// 收到 ChangeCipherSpec（TLS 1.2）或 ServerHello（TLS 1.3）之后的记录是加密的
+    encrypted: bool,
+    /// TLS 1.3 是否已从握手密钥切换到应用数据密钥
+    application_keys: bool,
+    /// 原始字节流的代数，变化说明丢失了数据，之后的记录无法解密
+    generation: Option<u64>,
+    /// 字节流丢失数据或格式错误，不再处理
+    broken: bool,
+}
+
+/// 一个 TLS 连接的解密状态
+#[derive(Debug)]
+struct TlsSession {
+    client_random: [u8; 32],
+    server_random: Option<[u8; 32]>,
+    cipher_suite: Option<u16>,
+    tls13: bool,
+    secrets: Option<SessionSecrets>,
+    /// 没有密钥或加密套件不支持，不再尝试解密
+    unavailable: bool,
+    client: DirectionState,
+    server: DirectionState,
+    started: Instant,
+    last_seen: Instant,
+}
+
+impl TlsSession {
+    fn direction(&mut self, from_client: bool) -> &mut DirectionState {
+        if from_client {
+            &mut self.client
+        } else {
+            &mut self.server
+        }
+    }
+
+    /// 处理字节流中完整的 TLS 记录，返回解密后的应用数据
+    fn process(&mut self, from_client: bool, stream: &mut StreamBuffer) -> Vec<u8> {
+        let mut plaintext = Vec::new();
+
+        let generation = stream.generation();
+        let unavailable = self.unavailable;
+        let state = self.direction(from_client);
+        if state.generation.is_some_and(|known| known != generation) {
+            debug!("TLS 字节流丢失数据，停止解密该方向");
+            state.broken = true;
+        }
+        state.generation = Some(generation);
+        // 没有密钥日志或已确定无法解密时不需要解析记录
+        if state.broken || unavailable {
+            stream.clear();
+            self.direction(from_client).generation = Some(stream.generation());
+            return plaintext;
+        }
+
+        loop {
+            let data = stream.data();
+            if data.len() < 5 {
+                break;
+            }
+            let record_len = u16::from_be_bytes([data[3], data[4]]) as usize;
+            if data[1] != 3 || record_len > tls::MAX_RECORD_LEN {
+                debug!("TLS 记录格式错误，停止解密该方向");
+                self.direction(from_client).broken = true;
+                stream.clear();
+                self.direction(from_client).generation = Some(stream.generation());
+                break;
+            }
+            if data.len() < 5 + record_len {
+                break;
+            }
+            if self.direction(from_client).encrypted && self.wait_for_secrets(stream.is_closed()) {
+                break;
+            }
+            let (header, payload) = data[..5 + record_len].split_at(5);
+            self.process_record(from_client, header, payload, &mut plaintext);
+            stream.consume(5 + record_len);
+        }
+
+        plaintext
+    }
+
+    fn process_record(
+        &mut self,
+        from_client: bool,
+        header: &[u8],
+        payload: &[u8],
+        plaintext: &mut Vec<u8>,
+    ) {
+        let content_type = header[0];
+        let encrypted = self.direction(from_client).encrypted;
+
+        match content_type {
+            // TLS 1.3 中的 ChangeCipherSpec 只是为了兼容中间设备
+            tls::CONTENT_TYPE_CHANGE_CIPHER_SPEC if !self.tls13 => {
+                self.direction(from_client).encrypted = true;
+            }
+            tls::CONTENT_TYPE_HANDSHAKE if !encrypted && !from_client => {
+                self.handle_server_handshake(payload);
+            }
+            tls::CONTENT_TYPE_HANDSHAKE
+            | tls::CONTENT_TYPE_ALERT
+            | tls::CONTENT_TYPE_APPLICATION_DATA
+                if encrypted =>
+            {
+                // 握手和告警消息也要解密，以保持记录序号同步
+                let content = match self.decrypt_record(from_client, header, payload) {
+                    Some(content) => content,
+                    None => return,
+                };
+                if !self.tls13 {
+                    if content_type == tls::CONTENT_TYPE_APPLICATION_DATA {
+                        plaintext.extend_from_slice(&content);
+                    }
+                    return;
+                }
+
+                // TLS 1.3 的真实内容类型位于明文末尾的填充之前
+                let end = match content.iter().rposition(|&b| b != 0) {
+                    Some(end) => end,
+                    None => return,
+                };
+                match content[end] {
+                    tls::CONTENT_TYPE_APPLICATION_DATA => {
+                        plaintext.extend_from_slice(&content[..end])
+                    }
+                    tls::CONTENT_TYPE_HANDSHAKE => {
+                        self.handle_encrypted_handshake(from_client, &content[..end])
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 查找会话密钥；密钥日志可能还没有读取到浏览器刚写入的密钥，
+    /// 等待期间返回 true，加密记录留在字节流中稍后再解密
+    fn wait_for_secrets(&mut self, closed: bool) -> bool {
+        if self.secrets.is_some() || self.unavailable {
+            return false;
+        }
+        self.secrets = keylog::lookup(&self.client_random);
+        if self.secrets.is_some() {
+            return false;
+        }
+        if !closed && self.started.elapsed() < SECRETS_WAIT {
+            return true;
+        }
+        debug!("密钥日志中没有该 TLS 会话的密钥，无法解密");
+        self.unavailable = true;
+        false
+    }
+
+    /// 从 ServerHello 中取得服务端随机数、加密套件和协商的版本
+    fn handle_server_handshake(&mut self, payload: &[u8]) {
+        let body = match handshake_messages(payload).next() {
+            Some((tls::HANDSHAKE_SERVER_HELLO, body)) => body,
+            _ => return,
+        };
+        let hello = match tls::parse_server_hello(body) {
+            Some(hello) => hello,
+            None => return,
+        };
+        // HelloRetryRequest 之后客户端会重新发送 ClientHello，随机数不变
+        if hello.random == HELLO_RETRY_REQUEST_RANDOM {
+            return;
+        }
+
+        self.server_random = Some(hello.random);
+        self.cipher_suite = Some(hello.cipher_suite);
+        self.tls13 = hello.version == 0x0304;
+        if self.tls13 {
+            // ServerHello 之后双方的握手消息都已加密
+            self.client.encrypted = true;
+            self.server.encrypted = true;
+        }
+    }
+
+    /// TLS 1.3 加密的握手消息：Finished 之后切换到应用数据密钥，KeyUpdate 更新密钥
+    fn handle_encrypted_handshake(&mut self, from_client: bool, data: &[u8]) {
+        for (message_type, _) in handshake_messages(data) {
+            match message_type {
+                tls::HANDSHAKE_FINISHED if !self.direction(from_client).application_keys => {
+                    let decrypter = self.application_decrypter(from_client);
+                    let state = self.direction(from_client);
+                    state.decrypter = decrypter;
+                    state.application_keys = true;
+                }
+                tls::HANDSHAKE_KEY_UPDATE if self.direction(from_client).application_keys => {
+                    if let Some(decrypter) = self.direction(from_client).decrypter.as_mut() {
+                        if decrypter.update_key().is_none() {
+                            debug!("TLS 1.3 密钥更新失败");
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn decrypt_record(
+        &mut self,
+        from_client: bool,
+        header: &[u8],
+        payload: &[u8],
+    ) -> Option<Vec<u8>> {
+        if self.unavailable {
+            return None;
+        }
+        if self.direction(from_client).decrypter.is_none() {
+            self.init_decrypter(from_client)?;
+        }
+
+        let state = self.direction(from_client);
+        if let Some(content) = state
+            .decrypter
+            .as_mut()
+            .and_then(|decrypter| decrypter.decrypt(header, payload))
+        {
+            return Some(content);
+        }
+
+        // Finished 跨越多条记录时可能没有识别到，尝试应用数据密钥
+        if self.tls13 && !self.direction(from_client).application_keys {
+            if let Some(mut decrypter) = self.application_decrypter(from_client) {
+                if let Some(content) = decrypter.decrypt(header, payload) {
+                    let state = self.direction(from_client);
+                    state.decrypter = Some(decrypter);
+                    state.application_keys = true;
+                    return Some(content);
+                }
+            }
+        }
+
+        debug!("TLS 记录解密失败（{} 字节）", payload.len());
+        None
+    }
+
+    /// 根据密钥日志和协商的加密套件创建解密器
+    fn init_decrypter(&mut self, from_client: bool) -> Option<()> {
+        let suite = match self.cipher_suite.and_then(cipher_suite) {
+            Some(suite) => suite,
+            None => {
+                if let Some(id) = self.cipher_suite {
+                    info!("不支持解密的 TLS 加密套件 0x{:04x}", id);
+                }
+                self.unavailable = true;
+                return None;
+            }
+        };
+        let secrets = self.secrets.as_ref()?;
+
+        let decrypter = if self.tls13 {
+            let handshake_secret = if from_client {
+                &secrets.client_handshake_traffic_secret
+            } else {
+                &secrets.server_handshake_traffic_secret
+            };
+            match handshake_secret {
+                Some(secret) => RecordDecrypter::tls13(suite, secret),
+                // 只有应用数据密钥时直接使用
+                None => {
+                    self.direction(from_client).application_keys = true;
+                    self.application_decrypter(from_client)
+                }
+            }
+        } else {
+            let master_secret = secrets.master_secret.as_ref()?;
+            let server_random = self.server_random?;
+            tls12_decrypter(
+                suite,
+                master_secret,
+                &self.client_random,
+                &server_random,
+                from_client,
+            )
+        };
+
+        if decrypter.is_none() {
+            warn!("无法根据密钥日志派生 TLS 会话密钥");
+            self.unavailable = true;
+            return None;
+        }
+        self.direction(from_client).decrypter = decrypter;
+        Some(())
+    }
+
+    /// TLS 1.3 应用数据的解密器
+    fn application_decrypter(&self, from_client: bool) -> Option<RecordDecrypter> {
+        let suite = self.cipher_suite.and_then(cipher_suite)?;
+        let secrets = self.secrets.as_ref()?;
+        let secret = if from_client {
+            secrets.client_traffic_secret_0.as_ref()?
+        } else {
+            secrets.server_traffic_secret_0.as_ref()?
+        };
+        RecordDecrypter::tls13(suite, secret)
+    }
+}
+
+/// 由 TLS 1.2 主密钥派生单方向的解密器（RFC 5246 第 6.3 节）
+fn tls12_decrypter(
+    suite: CipherSuite,
+    master_secret: &[u8],
+    client_random: &[u8; 32],
+    server_random: &[u8; 32],
+    from_client: bool,
+) -> Option<RecordDecrypter> {
+    let key_len = suite.aead.key_len();
+    let iv_len = suite.aead.tls12_iv_len();
+    let mut seed = server_random.to_vec();
+    seed.extend_from_slice(client_random);
+    let key_block = prf(
+        suite.hash,
+        master_secret,
+        b"key expansion",
+        &seed,
+        2 * (key_len + iv_len),
+    );
+
+    // 密钥块依次为 client_write_key、server_write_key、client_write_IV、server_write_IV
+    let (keys, ivs) = key_block.split_at(2 * key_len);
+    let (key, iv) = if from_client {
+        (&keys[..key_len], &ivs[..iv_len])
+    } else {
+        (&keys[key_len..], &ivs[iv_len..])
+    };
+    RecordDecrypter::tls12(suite, key, iv)
+}
+
+/// TLS 1.2 的伪随机函数 P_hash（RFC 5246 第 5 节）
+fn prf(hash: HashAlgorithm, secret: &[u8], label: &[u8], seed: &[u8], len: usize) -> Vec<u8> {
+    let mut label_seed = label.to_vec();
+    label_seed.extend_from_slice(seed);
+
+    let mut output = Vec::with_capacity(len);
+    let mut a = hmac(hash, secret, &[&label_seed]);
+    while output.len() < len {
+        output.extend(hmac(hash, secret, &[&a, &label_seed]));
+        a = hmac(hash, secret, &[&a]);
+    }
+    output.truncate(len);
+    output
+}
+
+fn hmac(hash: HashAlgorithm, key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    match hash {
+        HashAlgorithm::Sha256 => {
+            let mut mac =
+                <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC 可以使用任意长度的密钥");
+            for part in parts {
+                mac.update(part);
+            }
+            mac.finalize().into_bytes().to_vec()
+        }
+        HashAlgorithm::Sha384 => {
+            let mut mac =
+                <Hmac<Sha384> as Mac>::new_from_slice(key).expect("HMAC 可以使用任意长度的密钥");
+            for part in parts {
+                mac.update(part);
+            }
+            mac.finalize().into_bytes().to_vec()
+        }
+    }
+}
+
+/// TLS 1.3 的 HKDF-Expand-Label（RFC 8446 第 7.1 节），上下文为空
+fn hkdf_expand_label(
+    hash: HashAlgorithm,
+    secret: &[u8],
+    label: &str,
+    len: usize,
+) -> Option<Vec<u8>> {
+    let label = format!("tls13 {}", label);
+    let mut info = (len as u16).to_be_bytes().to_vec();
+    info.push(label.len() as u8);
+    info.extend_from_slice(label.as_bytes());
+    info.push(0);
+
+    let mut output = vec![0u8; len];
+    match hash {
+        HashAlgorithm::Sha256 => Hkdf::<Sha256>::from_prk(secret)
+            .ok()?
+            .expand(&info, &mut output)
+            .ok()?,
+        HashAlgorithm::Sha384 => Hkdf::<Sha384>::from_prk(secret)
+            .ok()?
+            .expand(&info, &mut output)
+            .ok()?,
+    }
+    Some(output)
+}
+
+/// 依次取出握手消息的类型和消息体，不完整的消息被忽略
+fn handshake_messages(data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.len() < 4 {
+            return None;
+        }
+        let len = u32::from_be_bytes([0, rest[1], rest[2], rest[3]]) as usize;
+        let body = rest.get(4..4 + len)?;
+        let message_type = rest[0];
+        rest = &rest[4 + len..];
+        Some((message_type, body))
+    })
+}
+
+/// 使用密钥日志解密 TLS 连接，按客户端 -> 服务端方向的四元组跟踪会话
+#[derive(Debug, Default)]
+pub struct TlsDecryptor {
+    sessions: HashMap<FlowKey, TlsSession>,
+}
+
+impl TlsDecryptor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 收到 ClientHello 后开始跟踪会话，key 为客户端发出数据的方向
+    /// 没有配置密钥日志时只跟踪连接，丢弃加密数据而不尝试解密
+    pub fn start_session(&mut self, key: FlowKey, hello: &ClientHello, generation: u64) {
+        let now = Instant::now();
+        let session = TlsSession {
+            client_random: hello.random,
+            server_random: None,
+            cipher_suite: None,
+            tls13: false,
+            secrets: None,
+            unavailable: !keylog::is_enabled(),
+            client: DirectionState {
+                generation: Some(generation),
+                ..DirectionState::default()
+            },
+            server: DirectionState::default(),
+            started: now,
+            last_seen: now,
+        };
+        self.sessions.insert(key, session);
+    }
+
+    /// 字节流是否属于已跟踪的 TLS 连接（任一方向）
+    pub fn is_tls_flow(&self, key: &FlowKey) -> bool {
+        self.sessions.contains_key(key) || self.sessions.contains_key(&key.reverse())
+    }
+
+    /// 客户端方向的字节流是否已不是会话开始时的字节流（端口复用或丢弃过数据）
+    pub fn is_stale(&self, key: &FlowKey, generation: u64) -> bool {
+        self.sessions
+            .get(key)
+            .is_some_and(|session| session.client.generation != Some(generation))
+    }
+
+    /// 处理 TLS 连接上新到达的数据，返回解密后的应用数据（无法解密时为空）
+    pub fn decrypt(&mut self, key: FlowKey, stream: &mut StreamBuffer) -> Vec<u8> {
+        let (session, from_client) = match self.sessions.get_mut(&key) {
+            Some(session) => (session, true),
+            None => match self.sessions.get_mut(&key.reverse()) {
+                Some(session) => (session, false),
+                None => return Vec::new(),
+            },
+        };
+        session.last_seen = Instant::now();
+        session.process(from_client, stream)
+    }
+
+    /// 清理空闲超时的会话
+    pub fn cleanup(&mut self) {
+        self.sessions
+            .retain(|_, session| session.last_seen.elapsed() < SESSION_TIMEOUT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 期望值由独立实现（Python hmac/cryptography）计算，PRF 和 HKDF 的部分与公开的测试向量一致
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn suite(id: u16) -> CipherSuite {
+        cipher_suite(id).unwrap()
+    }
+
+    #[test]
+    fn tls12_prf_sha256() {
+        let output = prf(
+            HashAlgorithm::Sha256,
+            &hex("9bbe436ba940f017b17652849a71db35"),
+            b"test label",
+            &hex("a0ba9f936cda311827a6f796ffd5198c"),
+            100,
+        );
+        assert_eq!(
+            output,
+            hex(
+                "e3f229ba727be17b8d122620557cd453c2aab21d07c3d495329b52d4e61edb5a\
+                 6b301791e90d35c9c9a46b4e14baf9af0fa022f7077def17abfd3797c0564bab\
+                 4fbc91666e9def9b97fce34f796789baa48082d122ee42c5a72e5a5110fff701\
+                 87347b66"
+            )
+        );
+    }
+
+    #[test]
+    fn tls12_prf_sha384() {
+        let output = prf(
+            HashAlgorithm::Sha384,
+            &hex("b80b733d6ceefcdc71566ea48e5567df"),
+            b"test label",
+            &hex("cd665cf6a8447dd6ff8b27555edb7465"),
+            148,
+        );
+        assert_eq!(
+            output,
+            hex(
+                "7b0c18e9ced410ed1804f2cfa34a336a1c14dffb4900bb5fd7942107e81c83cd\
+                 e9ca0faa60be9fe34f82b1233c9146a0e534cb400fed2700884f9dc236f80edd\
+                 8bfa961144c9e8d792eca722a7b32fc3d416d473ebc2c5fd4abfdad05d918425\
+                 9b5bf8cd4d90fa0d31e2dec479e4f1a26066f2eea9a69236a3e52655c9e9aee6\
+                 91c8f3a26854308d5eaa3be85e0990703d73e56f"
+            )
+        );
+    }
+
+    #[test]
+    fn tls13_traffic_keys_rfc8448() {
+        // RFC 8448 第 3 节：服务端握手和应用数据的流量密钥
+        let handshake = hex("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38");
+        assert_eq!(
+            hkdf_expand_label(HashAlgorithm::Sha256, &handshake, "key", 16),
+            Some(hex("3fce516009c21727d0f2e4e86ee403bc"))
+        );
+        assert_eq!(
+            hkdf_expand_label(HashAlgorithm::Sha256, &handshake, "iv", 12),
+            Some(hex("5d313eb2671276ee13000b30"))
+        );
+
+        let application = hex("a11af9f05531f856ad47116b45a950328204b4f44bfb6b3a4b4f1f3fcb631643");
+        assert_eq!(
+            hkdf_expand_label(HashAlgorithm::Sha256, &application, "key", 16),
+            Some(hex("9f02283b6c9c07efc26bb9f2ac92e356"))
+        );
+        assert_eq!(
+            hkdf_expand_label(HashAlgorithm::Sha256, &application, "iv", 12),
+            Some(hex("cf782b88dd83549aadf1e984"))
+        );
+        assert_eq!(
+            hkdf_expand_label(HashAlgorithm::Sha256, &application, "traffic upd", 32),
+            Some(hex(
+                "51921b8aa3001976eb401d0a4319a8516416a6c56001a357e5d162031e84f916"
+            ))
+        );
+    }
+
+    #[test]
+    fn tls13_key_update_aes256_gcm_sha384() {
+        let secret: Vec<u8> = (0..48).collect();
+        let mut decrypter = RecordDecrypter::tls13(suite(0x1302), &secret).unwrap();
+        decrypter.seq = 5;
+        decrypter.update_key().unwrap();
+        assert_eq!(decrypter.seq, 0);
+
+        let content = decrypter
+            .decrypt(
+                &hex("1703030024"),
+                &hex("64c1f7d7a837b1478afd0fe0699ff507a970391e8a295aaab0e9c100cc229b9da97a9ff7"),
+            )
+            .unwrap();
+        assert_eq!(content, b"HTTP/1.1 200 OK\r\n\r\n\x17");
+    }
+
+    #[test]
+    fn tls12_aes128_gcm_record() {
+        let decrypter =
+            tls12_decrypter(suite(0xc02f), &[0x11; 48], &[0x01; 32], &[0x02; 32], false);
+        let content = decrypter
+            .unwrap()
+            .decrypt(
+                &hex("1703030033"),
+                &hex(
+                    "00000000000000015ca7969db90bb68c08e51b7d391a184a01d61c51d948bfc6\
+                      5540d0845607cdf8a76b564ef53442b05f3def",
+                ),
+            )
+            .unwrap();
+        assert_eq!(content, b"HTTP/1.1 204 No Content\r\n\r\n");
+    }
+
+    #[test]
+    fn tls12_chacha20_records_advance_sequence() {
+        let mut decrypter =
+            tls12_decrypter(suite(0xcca8), &[0x11; 48], &[0x01; 32], &[0x02; 32], true).unwrap();
+        let first = decrypter.decrypt(
+            &hex("1703030020"),
+            &hex("47c6da3a2694aa18f7e16c2998fe101ca701d4da66e98fa61abf0a8aa2b36be0"),
+        );
+        assert_eq!(first.as_deref(), Some(&b"GET / HTTP/1.1\r\n"[..]));
+        let second = decrypter.decrypt(
+            &hex("170303001b"),
+            &hex("65136dffe83bcf52d3989700a1a944b5f80d1dee0ddfc915591cfd"),
+        );
+        assert_eq!(second.as_deref(), Some(&b"Host: a\r\n\r\n"[..]));
+        assert_eq!(decrypter.seq, 2);
+    }
+}
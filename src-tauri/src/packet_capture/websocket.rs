@@ -0,0 +1,550 @@
+use super::body::MAX_BODY_SIZE;
+use super::reassembly::StreamBuffer;
+use flate2::{Decompress, FlushDecompress, Status};
+use log::debug;
+
+/// 单个帧的最大长度，超过时停止解析该连接
+const MAX_FRAME_LEN: u64 = 16 * 1024 * 1024;
+/// permessage-deflate 压缩的消息末尾省略的 4 个字节（RFC 7692 第 7.2.2 节）
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// WebSocket 消息类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_u8(opcode: u8) -> Option<Self> {
+        match opcode {
+            OPCODE_TEXT => Some(Opcode::Text),
+            OPCODE_BINARY => Some(Opcode::Binary),
+            OPCODE_CLOSE => Some(Opcode::Close),
+            OPCODE_PING => Some(Opcode::Ping),
+            OPCODE_PONG => Some(Opcode::Pong),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Opcode::Text => "text",
+            Opcode::Binary => "binary",
+            Opcode::Close => "close",
+            Opcode::Ping => "ping",
+            Opcode::Pong => "pong",
+        }
+    }
+}
+
+/// 一条完整的消息（分片已合并、已解压）
+#[derive(Debug)]
+pub struct Frame {
+    pub opcode: Opcode,
+    pub data: Vec<u8>,
+    /// 消息超过 MAX_BODY_SIZE 时只保留开头部分
+    pub truncated: bool,
+}
+
+/// 握手时协商的 permessage-deflate 参数（RFC 7692）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeflateConfig {
+    /// 客户端每条消息使用新的压缩上下文
+    pub client_no_context_takeover: bool,
+    /// 服务端每条消息使用新的压缩上下文
+    pub server_no_context_takeover: bool,
+}
+
+/// 从服务端响应的 Sec-WebSocket-Extensions 中读取 permessage-deflate 参数，未启用时返回 None
+pub fn parse_deflate_extension(header: &str) -> Option<DeflateConfig> {
+    header.split(',').find_map(|extension| {
+        let mut params = extension.split(';').map(str::trim);
+        if !params.next()?.eq_ignore_ascii_case("permessage-deflate") {
+            return None;
+        }
+        let mut config = DeflateConfig {
+            client_no_context_takeover: false,
+            server_no_context_takeover: false,
+        };
+        for param in params {
+            let name = param.split('=').next().unwrap_or_default().trim();
+            if name.eq_ignore_ascii_case("client_no_context_takeover") {
+                config.client_no_context_takeover = true;
+            } else if name.eq_ignore_ascii_case("server_no_context_takeover") {
+                config.server_no_context_takeover = true;
+            }
+        }
+        Some(config)
+    })
+}
+
+/// 正在接收的分片消息
+#[derive(Debug)]
+struct Fragmented {
+    opcode: Opcode,
+    compressed: bool,
+    data: Vec<u8>,
+    truncated: bool,
+}
+
+/// 连接一个方向上的解析状态
+#[derive(Debug)]
+struct Direction {
+    /// permessage-deflate 的解压上下文，未启用压缩时为 None
+    inflater: Option<Decompress>,
+    /// 每条消息结束后重置解压上下文
+    no_context_takeover: bool,
+    fragmented: Option<Fragmented>,
+}
+
+impl Direction {
+    fn new(deflate: bool, no_context_takeover: bool) -> Self {
+        Self {
+            inflater: deflate.then(|| Decompress::new(false)),
+            no_context_takeover,
+            fragmented: None,
+        }
+    }
+
+    /// 处理一个帧，消息结束时返回完整的消息，分片尚未收全时返回 Some(None)
+    fn process_frame(
+        &mut self,
+        fin: bool,
+        rsv1: bool,
+        opcode: u8,
+        payload: &[u8],
+    ) -> Option<Option<Frame>> {
+        if opcode == OPCODE_CONTINUATION {
+            let mut message = self.fragmented.take()?;
+            self.append(&mut message, payload)?;
+            if !fin {
+                self.fragmented = Some(message);
+                return Some(None);
+            }
+            return self.finish(message).map(Some);
+        }
+
+        let opcode = Opcode::from_u8(opcode)?;
+        if matches!(opcode, Opcode::Close | Opcode::Ping | Opcode::Pong) {
+            // 控制帧不分片、不压缩，可以穿插在分片消息之间
+            if !fin || rsv1 {
+                return None;
+            }
+            return Some(Some(Frame {
+                opcode,
+                data: payload.to_vec(),
+                truncated: false,
+            }));
+        }
+
+        // 上一条分片消息尚未结束
+        if self.fragmented.is_some() || (rsv1 && self.inflater.is_none()) {
+            return None;
+        }
+        let mut message = Fragmented {
+            opcode,
+            compressed: rsv1,
+            data: Vec::new(),
+            truncated: false,
+        };
+        self.append(&mut message, payload)?;
+        if !fin {
+            self.fragmented = Some(message);
+            return Some(None);
+        }
+        self.finish(message).map(Some)
+    }
+
+    /// 追加分片数据，压缩的消息边接收边解压，以免保留全部压缩数据
+    fn append(&mut self, message: &mut Fragmented, payload: &[u8]) -> Option<()> {
+        if message.compressed {
+            let inflater = self.inflater.as_mut()?;
+            inflate(inflater, payload, message)
+        } else {
+            push_limited(message, payload);
+            Some(())
+        }
+    }
+
+    fn finish(&mut self, mut message: Fragmented) -> Option<Frame> {
+        if message.compressed {
+            let inflater = self.inflater.as_mut()?;
+            inflate(inflater, &DEFLATE_TAIL, &mut message)?;
+            if self.no_context_takeover {
+                inflater.reset(false);
+            }
+        }
+        Some(Frame {
+            opcode: message.opcode,
+            data: message.data,
+            truncated: message.truncated,
+        })
+    }
+}
+
+/// 解压一段数据并追加到消息中
+fn inflate(inflater: &mut Decompress, mut input: &[u8], message: &mut Fragmented) -> Option<()> {
+    let mut buf = [0u8; 16 * 1024];
+    loop {
+        let (before_in, before_out) = (inflater.total_in(), inflater.total_out());
+        let status = inflater
+            .decompress(input, &mut buf, FlushDecompress::Sync)
+            .ok()?;
+        let consumed = (inflater.total_in() - before_in) as usize;
+        let produced = (inflater.total_out() - before_out) as usize;
+        input = &input[consumed..];
+        push_limited(message, &buf[..produced]);
+
+        // 输入已用完且输出缓冲区未写满时，已取出全部解压数据
+        if status == Status::StreamEnd
+            || (input.is_empty() && produced < buf.len())
+            || (consumed == 0 && produced == 0)
+        {
+            return Some(());
+        }
+    }
+}
+
+/// 追加消息数据，超过 MAX_BODY_SIZE 的部分丢弃
+fn push_limited(message: &mut Fragmented, data: &[u8]) {
+    let available = MAX_BODY_SIZE.saturating_sub(message.data.len());
+    if data.len() > available {
+        message.truncated = true;
+    }
+    message
+        .data
+        .extend_from_slice(&data[..data.len().min(available)]);
+}
+
+/// 一个 WebSocket 连接上两个方向的帧解析状态
+#[derive(Debug)]
+pub struct WebSocketConnection {
+    client: Direction,
+    server: Direction,
+}
+
+impl WebSocketConnection {
+    pub fn new(deflate: Option<DeflateConfig>) -> Self {
+        Self {
+            client: Direction::new(
+                deflate.is_some(),
+                deflate.is_some_and(|config| config.client_no_context_takeover),
+            ),
+            server: Direction::new(
+                deflate.is_some(),
+                deflate.is_some_and(|config| config.server_no_context_takeover),
+            ),
+        }
+    }
+
+    /// 解析字节流中的完整帧，把结束的消息放入 messages
+    /// 格式错误时返回 None，此后连接上的数据无法继续解析
+    pub fn process(
+        &mut self,
+        from_client: bool,
+        stream: &mut StreamBuffer,
+        messages: &mut Vec<Frame>,
+    ) -> Option<()> {
+        let direction = if from_client {
+            &mut self.client
+        } else {
+            &mut self.server
+        };
+
+        loop {
+            let data = stream.data();
+            let header = match parse_frame_header(data) {
+                FrameHeaderParse::Complete(header) => header,
+                FrameHeaderParse::Incomplete => break,
+                FrameHeaderParse::Invalid => return None,
+            };
+            // 客户端发送的帧必须使用掩码
+            if from_client != header.mask.is_some() {
+                return None;
+            }
+            let frame_len = header.len + header.payload_len;
+            if data.len() < frame_len {
+                break;
+            }
+
+            let mut payload = data[header.len..frame_len].to_vec();
+            if let Some(mask) = header.mask {
+                for (i, byte) in payload.iter_mut().enumerate() {
+                    *byte ^= mask[i % 4];
+                }
+            }
+            let fin = data[0] & 0x80 != 0;
+            let rsv1 = data[0] & 0x40 != 0;
+            let opcode = data[0] & 0x0f;
+            if data[0] & 0x30 != 0 {
+                return None;
+            }
+            stream.consume(frame_len);
+
+            if let Some(frame) = direction.process_frame(fin, rsv1, opcode, &payload)? {
+                messages.push(frame);
+            }
+        }
+        Some(())
+    }
+}
+
+/// 帧头
+struct FrameHeader {
+    /// 帧头长度（包括掩码）
+    len: usize,
+    payload_len: usize,
+    /// 客户端发送的帧使用的掩码
+    mask: Option<[u8; 4]>,
+}
+
+/// 帧头的解析结果
+enum FrameHeaderParse {
+    Complete(FrameHeader),
+    /// 数据尚未收全
+    Incomplete,
+    /// 帧过大，无法继续解析
+    Invalid,
+}
+
+/// 解析帧头（RFC 6455 第 5.2 节）
+fn parse_frame_header(data: &[u8]) -> FrameHeaderParse {
+    if data.len() < 2 {
+        return FrameHeaderParse::Incomplete;
+    }
+    let (len_bytes, payload_len) = match data[1] & 0x7f {
+        126 => match data.get(2..4) {
+            Some(len) => (2, u64::from(u16::from_be_bytes([len[0], len[1]]))),
+            None => return FrameHeaderParse::Incomplete,
+        },
+        127 => match data.get(2..10) {
+            Some(len) => (
+                8,
+                u64::from_be_bytes([
+                    len[0], len[1], len[2], len[3], len[4], len[5], len[6], len[7],
+                ]),
+            ),
+            None => return FrameHeaderParse::Incomplete,
+        },
+        len => (0, u64::from(len)),
+    };
+    if payload_len > MAX_FRAME_LEN {
+        debug!("WebSocket 帧过大: {} 字节", payload_len);
+        return FrameHeaderParse::Invalid;
+    }
+
+    let mut len = 2 + len_bytes;
+    let mask = if data[1] & 0x80 != 0 {
+        match data.get(len..len + 4) {
+            Some(mask) => {
+                len += 4;
+                Some([mask[0], mask[1], mask[2], mask[3]])
+            }
+            None => return FrameHeaderParse::Incomplete,
+        }
+    } else {
+        None
+    };
+    FrameHeaderParse::Complete(FrameHeader {
+        len,
+        payload_len: payload_len as usize,
+        mask,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASK: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+    /// 构造一个帧，mask 为 Some 时按客户端帧加掩码
+    fn frame(first: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        let mut data = vec![first];
+        match payload.len() {
+            len @ 0..=125 => data.push(mask_bit | len as u8),
+            len @ 126..=0xffff => {
+                data.push(mask_bit | 126);
+                data.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                data.push(mask_bit | 127);
+                data.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        match mask {
+            Some(mask) => {
+                data.extend_from_slice(&mask);
+                data.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+            }
+            None => data.extend_from_slice(payload),
+        }
+        data
+    }
+
+    fn run(
+        connection: &mut WebSocketConnection,
+        from_client: bool,
+        stream: &mut StreamBuffer,
+        bytes: &[u8],
+    ) -> Option<Vec<Frame>> {
+        stream.extend(bytes);
+        let mut messages = Vec::new();
+        connection.process(from_client, stream, &mut messages)?;
+        Some(messages)
+    }
+
+    fn parse(from_client: bool, bytes: &[u8]) -> Option<Vec<Frame>> {
+        let mut connection = WebSocketConnection::new(None);
+        run(
+            &mut connection,
+            from_client,
+            &mut StreamBuffer::detached(),
+            bytes,
+        )
+    }
+
+    #[test]
+    fn unmasked_server_text_frame() {
+        // RFC 6455 第 5.7 节的示例
+        let messages = parse(false, &[0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f]).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].opcode, Opcode::Text);
+        assert_eq!(messages[0].data, b"Hello");
+        assert!(!messages[0].truncated);
+    }
+
+    #[test]
+    fn masked_client_text_frame() {
+        let bytes = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let messages = parse(true, &bytes).unwrap();
+        assert_eq!(messages[0].data, b"Hello");
+    }
+
+    #[test]
+    fn mask_must_match_direction() {
+        assert!(parse(true, &frame(0x81, b"Hello", None)).is_none());
+        assert!(parse(false, &frame(0x81, b"Hello", Some(MASK))).is_none());
+    }
+
+    #[test]
+    fn extended_payload_lengths() {
+        for len in [126, 256, 0xffff, 0x10000] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let messages = parse(false, &frame(0x82, &payload, None)).unwrap();
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].opcode, Opcode::Binary);
+            assert_eq!(messages[0].data, payload);
+        }
+    }
+
+    #[test]
+    fn oversized_frame_is_invalid() {
+        let mut bytes = vec![0x82, 127];
+        bytes.extend_from_slice(&(MAX_FRAME_LEN + 1).to_be_bytes());
+        assert!(parse(false, &bytes).is_none());
+    }
+
+    #[test]
+    fn incomplete_frame_waits_for_more_data() {
+        let bytes = frame(0x82, &[0xab; 300], Some(MASK));
+        let mut connection = WebSocketConnection::new(None);
+        let mut stream = StreamBuffer::detached();
+        // 依次停在扩展长度、掩码和负载的中间
+        for (start, end) in [(0, 1), (1, 3), (3, 6), (6, 100)] {
+            let messages = run(&mut connection, true, &mut stream, &bytes[start..end]).unwrap();
+            assert!(messages.is_empty());
+            assert_eq!(stream.data().len(), end);
+        }
+        let messages = run(&mut connection, true, &mut stream, &bytes[100..]).unwrap();
+        assert_eq!(messages[0].data, vec![0xab; 300]);
+        assert!(stream.data().is_empty());
+    }
+
+    #[test]
+    fn fragments_with_interleaved_control_frame() {
+        // RFC 6455 第 5.4 节：控制帧可以穿插在分片之间
+        let mut bytes = frame(0x01, b"Hel", None);
+        bytes.extend(frame(0x89, b"ping", None));
+        bytes.extend(frame(0x00, b"l", None));
+        bytes.extend(frame(0x80, b"o", None));
+        let messages = parse(false, &bytes).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].opcode, Opcode::Ping);
+        assert_eq!(messages[0].data, b"ping");
+        assert_eq!(messages[1].opcode, Opcode::Text);
+        assert_eq!(messages[1].data, b"Hello");
+    }
+
+    #[test]
+    fn invalid_fragment_sequences() {
+        // 没有起始帧的后续分片
+        assert!(parse(false, &frame(0x80, b"o", None)).is_none());
+        // 上一条分片消息尚未结束就开始新消息
+        let mut bytes = frame(0x01, b"Hel", None);
+        bytes.extend(frame(0x81, b"Hello", None));
+        assert!(parse(false, &bytes).is_none());
+        // 控制帧不能分片
+        assert!(parse(false, &frame(0x08, &[0x03, 0xe8], None)).is_none());
+    }
+
+    #[test]
+    fn close_frame_keeps_status_code() {
+        let mut payload = 1000u16.to_be_bytes().to_vec();
+        payload.extend_from_slice(b"bye");
+        let messages = parse(true, &frame(0x88, &payload, Some(MASK))).unwrap();
+        assert_eq!(messages[0].opcode, Opcode::Close);
+        assert_eq!(messages[0].data, payload);
+    }
+
+    #[test]
+    fn reserved_bits_and_opcodes_rejected() {
+        assert!(parse(false, &frame(0x91, b"x", None)).is_none());
+        assert!(parse(false, &frame(0x83, b"x", None)).is_none());
+        // 未协商 permessage-deflate 时不能设置 RSV1
+        assert!(parse(false, &frame(0xc1, b"x", None)).is_none());
+    }
+
+    #[test]
+    fn deflate_messages_share_context() {
+        // RFC 7692 第 7.2.3.2 节：第二条消息引用了第一条的内容
+        let config = DeflateConfig {
+            client_no_context_takeover: false,
+            server_no_context_takeover: false,
+        };
+        let mut connection = WebSocketConnection::new(Some(config));
+        let mut stream = StreamBuffer::detached();
+        let mut bytes = vec![0xc1, 0x07, 0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+        bytes.extend_from_slice(&[0xc1, 0x05, 0xf2, 0x00, 0x11, 0x00, 0x00]);
+        let messages = run(&mut connection, false, &mut stream, &bytes).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].data, b"Hello");
+        assert_eq!(messages[1].data, b"Hello");
+    }
+
+    #[test]
+    fn deflate_extension_parameters() {
+        assert_eq!(
+            parse_deflate_extension("permessage-deflate; server_no_context_takeover"),
+            Some(DeflateConfig {
+                client_no_context_takeover: false,
+                server_no_context_takeover: true,
+            })
+        );
+        assert_eq!(parse_deflate_extension("x-webkit-deflate-frame"), None);
+    }
+}
@@ -16,9 +16,35 @@ export interface PacketData {
   http?: {
     method?: string;
     url?: string;
+    query?: [string, string][];
     host?: string;
+    cookies?: [string, string][];
     headers?: Record<string, string>;
-    body?: string;
+    body?: HttpBody;
+    form?: [string, string][];
+    json?: unknown;
+    parts?: MultipartPart[];
+  };
+  tls?: {
+    sni: string | null;
+    alpn: string[];
+    version: string;
+  };
+  websocket?: {
+    connectionId: number;
+    url: string;
+    fromClient: boolean;
+    opcode: string;
+    closeCode: number | null;
+    payload: HttpBody;
+  };
+  response?: {
+    statusCode: number;
+    reason: string;
+    headers: Record<string, string>;
+    body: HttpBody;
+    cookies: Cookie[];
+    durationMs: number;
   };
 }
 
@@ -27,6 +53,8 @@ export interface NetworkDevice {
   name: string;
   description: string;
   is_loopback: boolean;
+  is_up: boolean;
+  is_running: boolean;
   addresses: string[];
 }
 
@@ -36,6 +64,83 @@ export interface CaptureStatus {
   message: string;
   device_name: string;
   start_time: number;
+  interfaces: InterfaceStatus[];
+  stats: CaptureStats;
+}
+
+// 定义单个网卡的捕获统计类型
+export interface InterfaceStatus {
+  name: string;
+  running: boolean;
+  packets: number;
+  bytes: number;
+  received: number; // pcap 统计（回放文件时为 0）
+  dropped: number;
+  if_dropped: number;
+  queue_dropped: number; // 解析队列已满时丢弃
+  error: string | null;
+}
+
+// 定义解析线程的汇总统计类型
+export interface CaptureStats {
+  packets_parsed: number;
+  packets_failed: number;
+  http_requests: number;
+  bytes_per_sec: number;
+  flows: number;
+  fragments: number; // 正在重组的 IP 分片数据报
+  workers: number;
+  auth_dropped: number; // 认证队列已满时未处理的请求
+}
+
+// 定义 HTTP 消息体类型，原始字节通过 get_message_body 按消息 ID 获取
+export interface HttpBody {
+  size: number;
+  truncated: boolean;
+  content_encoding: string | null;
+  charset: string | null;
+  text: string | null;
+}
+
+// 定义 multipart/form-data 中单个部分的类型
+export interface MultipartPart {
+  name: string;
+  filename: string | null;
+  content_type: string;
+  headers: [string, string][];
+  size: number;
+  truncated: boolean; // 请求体被截断或缺少结束分隔符
+  text: string | null;
+}
+
+// 定义服务端通过 Set-Cookie 设置的 Cookie 类型
+export interface Cookie {
+  name: string;
+  value: string;
+  domain: string;
+  host_only: boolean;
+  path: string;
+  expires: number | null; // 秒级时间戳，会话 Cookie 为 null
+  secure: boolean;
+  http_only: boolean;
+  same_site: string | null;
+  created: number;
+}
+
+// 定义本地代理配置类型
+export interface ProxyConfig {
+  port: number;
+  verify_upstream: boolean;
+}
+
+// 定义本地代理状态类型
+export interface ProxyStatus {
+  running: boolean;
+  port: number;
+  verify_upstream: boolean;
+  ca_cert_path: string | null;
+  connections: number;
+  message: string;
 }
 
 // 定义 HTTP 请求类型
@@ -48,11 +153,96 @@ export interface HttpRequest {
   dst_port: number;
   method: string;
   path: string;
+  url_path: string;
+  query: [string, string][];
   version: string;
   host: string;
+  cookies: [string, string][];
   content_type: string;
   headers: [string, string][];
-  body: string;
+  body: HttpBody;
+  form: [string, string][];
+  json: unknown | null;
+  parts: MultipartPart[];
+}
+
+// 定义 HTTP 响应类型
+export interface HttpResponse {
+  id: number;
+  timestamp: number;
+  src_ip: string;
+  src_port: number;
+  dst_ip: string;
+  dst_port: number;
+  version: string;
+  status_code: number;
+  reason: string;
+  content_type: string;
+  headers: [string, string][];
+  body: HttpBody;
+  cookies: Cookie[];
+}
+
+// 定义 HTTP 事务类型（请求与对应的响应）
+export interface HttpTransaction {
+  id: number;
+  request: HttpRequest;
+  response: HttpResponse;
+  duration_ms: number;
+}
+
+// 定义 TLS 连接类型（来自 ClientHello）
+export interface TlsConnection {
+  id: number;
+  timestamp: number;
+  src_ip: string;
+  src_port: number;
+  dst_ip: string;
+  dst_port: number;
+  sni: string | null;
+  alpn: string[];
+  version: string;
+}
+
+// 定义 WebSocket 消息类型（握手完成后连接上的消息）
+export interface WebSocketMessage {
+  id: number;
+  timestamp: number;
+  src_ip: string;
+  src_port: number;
+  dst_ip: string;
+  dst_port: number;
+  connection_id: number;
+  host: string;
+  path: string;
+  from_client: boolean;
+  opcode: 'text' | 'binary' | 'close';
+  close_code: number | null;
+  payload: HttpBody;
+}
+
+// 定义抓包权限检查中的一项
+export interface PermissionCheck {
+  name: string;
+  passed: boolean;
+  detail: string;
+}
+
+// 定义抓包权限检查报告
+export interface CapturePermissionReport {
+  platform: string; // linux / macos / windows
+  can_capture: boolean;
+  checks: PermissionCheck[];
+  fix_commands: string[]; // 修复权限的命令
+  message: string;
+}
+
+// 定义捕获过滤器配置类型
+export interface CaptureFilterConfig {
+  ports: number[];
+  hosts: string[];
+  bpf: string | null;
+  auto_from_systems: boolean;
 }
 
 export const useProxyStore = defineStore('proxy', () => {
@@ -61,14 +251,26 @@ export const useProxyStore = defineStore('proxy', () => {
     running: false,
     message: '未初始化',
     device_name: '未知',
-    start_time: 0
+    start_time: 0,
+    interfaces: [],
+    stats: {
+      packets_parsed: 0,
+      packets_failed: 0,
+      http_requests: 0,
+      bytes_per_sec: 0,
+      flows: 0,
+      fragments: 0,
+      workers: 0,
+      auth_dropped: 0
+    }
   });
   
   const packets = ref<PacketData[]>([]);
   const devices = ref<NetworkDevice[]>([]);
-  const selectedDevice = ref<string>('');
+  const selectedDevices = ref<string[]>([]);
   const error = ref<string>('');
   const isLoading = ref<boolean>(false);
+  const permissionReport = ref<CapturePermissionReport | null>(null);
   
   // 计算属性
   const isCapturing = computed(() => captureStatus.value.running);
@@ -100,13 +302,18 @@ export const useProxyStore = defineStore('proxy', () => {
           srcPort: httpRequest.src_port,
           dstIp: httpRequest.dst_ip,
           dstPort: httpRequest.dst_port,
-          length: httpRequest.body.length,
+          length: httpRequest.body.size,
           http: {
             method: httpRequest.method,
             url: httpRequest.path,
+            query: httpRequest.query,
             host: httpRequest.host,
+            cookies: httpRequest.cookies,
             headers: Object.fromEntries(httpRequest.headers),
-            body: httpRequest.body
+            body: httpRequest.body,
+            form: httpRequest.form,
+            json: httpRequest.json ?? undefined,
+            parts: httpRequest.parts
           }
         };
         
@@ -118,9 +325,88 @@ export const useProxyStore = defineStore('proxy', () => {
         }
       };
       
+      // 设置HTTP事务通道，将响应关联到对应的请求
+      const transactionChannel = new Channel<HttpTransaction>();
+      transactionChannel.onmessage = (transaction: HttpTransaction) => {
+        console.log('收到 HTTP 响应:', transaction);
+        const packet = findPacketById(transaction.id.toString());
+        if (packet) {
+          packet.response = {
+            statusCode: transaction.response.status_code,
+            reason: transaction.response.reason,
+            headers: Object.fromEntries(transaction.response.headers),
+            body: transaction.response.body,
+            cookies: transaction.response.cookies,
+            durationMs: transaction.duration_ms
+          };
+        }
+      };
+      
+      // 设置 TLS 连接通道，HTTPS 连接只能显示 ClientHello 中的主机名
+      const tlsChannel = new Channel<TlsConnection>();
+      tlsChannel.onmessage = (connection: TlsConnection) => {
+        console.log('收到 TLS 连接:', connection);
+        const packet: PacketData = {
+          id: connection.id.toString(),
+          timestamp: connection.timestamp * 1000, // 转换为毫秒
+          type: 'tcp',
+          protocol: 'TLS',
+          srcIp: connection.src_ip,
+          srcPort: connection.src_port,
+          dstIp: connection.dst_ip,
+          dstPort: connection.dst_port,
+          length: 0,
+          tls: {
+            sni: connection.sni,
+            alpn: connection.alpn,
+            version: connection.version
+          }
+        };
+        
+        packets.value.unshift(packet);
+        
+        if (packets.value.length > 1000) {
+          packets.value = packets.value.slice(0, 1000);
+        }
+      };
+      
+      // 设置 WebSocket 消息通道
+      const websocketChannel = new Channel<WebSocketMessage>();
+      websocketChannel.onmessage = (message: WebSocketMessage) => {
+        console.log('收到 WebSocket 消息:', message);
+        const packet: PacketData = {
+          id: message.id.toString(),
+          timestamp: message.timestamp * 1000, // 转换为毫秒
+          type: 'tcp',
+          protocol: 'WebSocket',
+          srcIp: message.src_ip,
+          srcPort: message.src_port,
+          dstIp: message.dst_ip,
+          dstPort: message.dst_port,
+          length: message.payload.size,
+          websocket: {
+            connectionId: message.connection_id,
+            url: `${message.host}${message.path}`,
+            fromClient: message.from_client,
+            opcode: message.opcode,
+            closeCode: message.close_code,
+            payload: message.payload
+          }
+        };
+        
+        packets.value.unshift(packet);
+        
+        if (packets.value.length > 1000) {
+          packets.value = packets.value.slice(0, 1000);
+        }
+      };
+      
       // 发送通道到后端
       await invoke('set_status_channel', { channel: statusChannel });
       await invoke('set_http_channel', { channel: httpChannel });
+      await invoke('set_transaction_channel', { channel: transactionChannel });
+      await invoke('set_tls_channel', { channel: tlsChannel });
+      await invoke('set_websocket_channel', { channel: websocketChannel });
       
       // 获取初始状态
       await getCaptureStatus();
@@ -142,7 +428,7 @@ export const useProxyStore = defineStore('proxy', () => {
       isLoading.value = true;
       error.value = '';
       
-      await invoke('init_packet_capture');
+      await invoke('init_packet_capture', { devices: selectedDevices.value });
       
     } catch (err) {
       error.value = `启动失败: ${err}`;
@@ -152,6 +438,22 @@ export const useProxyStore = defineStore('proxy', () => {
     }
   };
   
+  // 回放抓包文件（speed 为回放倍速，不传则尽快处理）
+  const startFileCapture = async (path: string, speed?: number) => {
+    try {
+      isLoading.value = true;
+      error.value = '';
+      
+      await invoke('init_file_capture', { path, speed });
+      
+    } catch (err) {
+      error.value = `回放失败: ${err}`;
+      console.error('回放抓包文件失败:', err);
+    } finally {
+      isLoading.value = false;
+    }
+  };
+  
   // 停止捕获
   const stopCapture = async () => {
     try {
@@ -185,20 +487,128 @@ export const useProxyStore = defineStore('proxy', () => {
     }
   };
   
+  // 获取抓包权限检查报告
+  const getPermissionReport = async () => {
+    try {
+      const report = await invoke('check_capture_permissions') as CapturePermissionReport;
+      permissionReport.value = report;
+      return report;
+    } catch (err) {
+      console.error('获取抓包权限报告失败:', err);
+      throw err;
+    }
+  };
+  
   // 检查权限
   const checkPermissions = async () => {
     try {
-      const hasPermission = await invoke('has_chmodbpf') as boolean;
-      if (!hasPermission) {
-        error.value = '缺少网络捕获权限，请安装 ChmodBPF 权限包';
+      const report = await getPermissionReport();
+      if (!report.can_capture) {
+        error.value = report.message;
       }
-      return hasPermission;
+      return report.can_capture;
     } catch (err) {
       console.error('权限检查失败:', err);
       throw err;
     }
   };
   
+  // 获取捕获过滤器配置
+  const getFilterConfig = async () => {
+    try {
+      return await invoke('get_capture_filter_config') as CaptureFilterConfig;
+    } catch (err) {
+      console.error('获取过滤器配置失败:', err);
+      throw err;
+    }
+  };
+  
+  // 保存捕获过滤器配置，返回生成的过滤表达式
+  const setFilterConfig = async (config: CaptureFilterConfig) => {
+    try {
+      error.value = '';
+      return await invoke('set_capture_filter_config', { config }) as string;
+    } catch (err) {
+      error.value = `过滤器配置无效: ${err}`;
+      console.error('保存过滤器配置失败:', err);
+      throw err;
+    }
+  };
+  
+  // 查询从 Set-Cookie 中收集的 Cookie，host 为空时返回全部
+  const getCookies = async (host?: string) => {
+    try {
+      return await invoke('get_cookies', { host: host || null }) as Cookie[];
+    } catch (err) {
+      console.error('获取 Cookie 失败:', err);
+      throw err;
+    }
+  };
+  
+  // 清空收集的 Cookie
+  const clearCookies = async () => {
+    try {
+      await invoke('clear_cookies');
+    } catch (err) {
+      console.error('清空 Cookie 失败:', err);
+      throw err;
+    }
+  };
+  
+  // 获取当前使用的 TLS 密钥日志文件
+  const getKeyLogFile = async () => {
+    try {
+      return await invoke('get_key_log_file') as string | null;
+    } catch (err) {
+      console.error('获取密钥日志文件失败:', err);
+      throw err;
+    }
+  };
+  
+  // 设置 TLS 密钥日志文件，为空时使用 SSLKEYLOGFILE 环境变量，返回包含密钥的会话数量
+  const setKeyLogFile = async (path: string | null) => {
+    try {
+      error.value = '';
+      return await invoke('set_key_log_file', { path: path || null }) as number;
+    } catch (err) {
+      error.value = `加载密钥日志失败: ${err}`;
+      console.error('设置密钥日志文件失败:', err);
+      throw err;
+    }
+  };
+  
+  // 启动本地代理，config 为空时使用上次保存的配置
+  const startProxy = async (config: ProxyConfig | null = null) => {
+    try {
+      error.value = '';
+      return await invoke('start_proxy', { config }) as ProxyStatus;
+    } catch (err) {
+      error.value = `启动本地代理失败: ${err}`;
+      console.error('启动本地代理失败:', err);
+      throw err;
+    }
+  };
+  
+  // 停止本地代理
+  const stopProxy = async () => {
+    try {
+      await invoke('stop_proxy');
+    } catch (err) {
+      console.error('停止本地代理失败:', err);
+      throw err;
+    }
+  };
+  
+  // 获取本地代理状态
+  const getProxyStatus = async () => {
+    try {
+      return await invoke('get_proxy_status') as ProxyStatus;
+    } catch (err) {
+      console.error('获取本地代理状态失败:', err);
+      throw err;
+    }
+  };
+  
   // 获取网络设备列表
   const getNetworkDevices = async () => {
     try {
@@ -208,10 +618,9 @@ export const useProxyStore = defineStore('proxy', () => {
       const deviceList = await invoke('get_network_devices') as NetworkDevice[];
       devices.value = deviceList;
       
-      // 自动选择第一个非回环设备
-      const nonLoopbackDevice = deviceList.find(device => !device.is_loopback);
-      if (nonLoopbackDevice && !selectedDevice.value) {
-        selectedDevice.value = nonLoopbackDevice.name;
+      // 默认选择上次使用的设备，没有时由后端自动选择
+      if (selectedDevices.value.length === 0) {
+        selectedDevices.value = await invoke('get_default_capture_devices') as string[];
       }
       
       return deviceList;
@@ -270,9 +679,10 @@ export const useProxyStore = defineStore('proxy', () => {
     captureStatus,
     packets,
     devices,
-    selectedDevice,
+    selectedDevices,
     error,
     isLoading,
+    permissionReport,
     
     // 计算属性
     isCapturing,
@@ -281,11 +691,22 @@ export const useProxyStore = defineStore('proxy', () => {
     // 方法
     initialize,
     startCapture,
+    startFileCapture,
     stopCapture,
     clearPackets,
     getCaptureStatus,
     checkPermissions,
+    getPermissionReport,
     getNetworkDevices,
+    getFilterConfig,
+    setFilterConfig,
+    getCookies,
+    clearCookies,
+    getKeyLogFile,
+    setKeyLogFile,
+    startProxy,
+    stopProxy,
+    getProxyStatus,
     cleanup,
     savePacketForWindow,
     getPacketForWindow,